@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use super::{
     animation::{AnimationResource, PlayerAssets},
@@ -10,7 +11,13 @@ pub const DOWN: IVec2 = IVec2::new(0, -1);
 // pub const LEFT: IVec2 = IVec2::new(-1, 0);
 pub const RIGHT: IVec2 = IVec2::new(1, 0);
 
-#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+/// The furthest the player can `Fall` before the command becomes invalid.
+pub const MAX_FALL: i32 = 8;
+
+/// How often a newly typed `Repeat` runs its body.
+pub const DEFAULT_REPEAT: u8 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub enum ScriptCommand {
     Walk,
     Climb,
@@ -18,11 +25,185 @@ pub enum ScriptCommand {
     Idle,
     Turn,
     Jump,
+    /// Drop straight down until landing on ground.
+    Fall,
+    /// Face the given direction, turning only if needed.
+    Face(Facing),
+    /// Wait until the number of ticks since respawning has the given parity.
+    Sync(Parity),
+    /// Remember the current cell, without spending a tick.
+    Mark,
+    /// Teleport back to the most recent `Mark` and forget it.
+    Return,
+    /// Pause autoplay here, without spending a tick.
+    Breakpoint,
     OpenBracket,
+    /// Like `OpenBracket`, but the loop is left after its body ran this many
+    /// times.
+    Repeat(u8),
     CloseBracket,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum Facing {
+    Left,
+    Right,
+}
+
+impl Facing {
+    pub fn from_x_dir(x_dir: i32) -> Self {
+        match x_dir < 0 {
+            true => Facing::Left,
+            false => Facing::Right,
+        }
+    }
+
+    pub fn x_dir(self) -> i32 {
+        match self {
+            Facing::Left => -1,
+            Facing::Right => 1,
+        }
+    }
+}
+
+/// Hazards move back and forth, so they are in their starting position after
+/// an even number of ticks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum Parity {
+    Even,
+    Odd,
+}
+
+impl Parity {
+    pub fn matches(self, ticks: usize) -> bool {
+        let odd = ticks % 2 == 1;
+        odd == (self == Parity::Odd)
+    }
+}
+
+/// The script items from an `OpenBracket` or `Repeat` to its matching
+/// `CloseBracket`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scope {
+    pub start: usize,
+    pub end: usize,
+    /// How many scopes this one is nested in.
+    pub depth: usize,
+}
+
+/// Broad groups of commands, used to tell them apart in the editor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandCategory {
+    /// Moves or turns the player.
+    Movement,
+    /// Changes which command runs next.
+    ControlFlow,
+    /// Spends time without moving.
+    Timing,
+}
+
 impl ScriptCommand {
+    /// The command that has to be unlocked for this command to be available.
+    pub fn unlock_key(self) -> ScriptCommand {
+        match self {
+            ScriptCommand::CloseBracket => ScriptCommand::OpenBracket,
+            // Both directions are unlocked together.
+            ScriptCommand::Face(_) => ScriptCommand::Face(Facing::Right),
+            ScriptCommand::Sync(_) => ScriptCommand::Sync(Parity::Even),
+            ScriptCommand::Return => ScriptCommand::Mark,
+            ScriptCommand::Repeat(_) => ScriptCommand::Repeat(DEFAULT_REPEAT),
+            rest => rest,
+        }
+    }
+
+    /// Whether this command starts a loop that a `CloseBracket` ends.
+    pub fn opens_scope(self) -> bool {
+        matches!(self, ScriptCommand::OpenBracket | ScriptCommand::Repeat(_))
+    }
+
+    pub fn category(self) -> CommandCategory {
+        match self {
+            ScriptCommand::Walk
+            | ScriptCommand::Climb
+            | ScriptCommand::Drop
+            | ScriptCommand::Jump
+            | ScriptCommand::Fall
+            | ScriptCommand::Turn
+            | ScriptCommand::Face(_)
+            | ScriptCommand::Return => CommandCategory::Movement,
+            ScriptCommand::OpenBracket
+            | ScriptCommand::Repeat(_)
+            | ScriptCommand::CloseBracket
+            | ScriptCommand::Mark
+            | ScriptCommand::Breakpoint => CommandCategory::ControlFlow,
+            ScriptCommand::Idle | ScriptCommand::Sync(_) => CommandCategory::Timing,
+        }
+    }
+
+    /// A short explanation for new players, shown when hovering the icon.
+    pub fn description(self) -> &'static str {
+        match self {
+            ScriptCommand::Walk => "Walk: step forward onto the floor ahead",
+            ScriptCommand::Climb => "Climb: climb up onto the ledge ahead",
+            ScriptCommand::Drop => "Drop: step forward and down one or two tiles",
+            ScriptCommand::Idle => "Idle: wait in place for a tick",
+            ScriptCommand::Turn => "Turn: face the other way",
+            ScriptCommand::Jump => "Jump: leap over a gap onto the ledge two tiles ahead",
+            ScriptCommand::Fall => "Fall: drop straight down until landing",
+            ScriptCommand::Face(Facing::Left) => "Face left: turn around unless facing left",
+            ScriptCommand::Face(Facing::Right) => "Face right: turn around unless facing right",
+            ScriptCommand::Sync(Parity::Even) => {
+                "Sync even: wait until an even number of ticks passed since respawning"
+            }
+            ScriptCommand::Sync(Parity::Odd) => {
+                "Sync odd: wait until an odd number of ticks passed since respawning"
+            }
+            ScriptCommand::Mark => "Mark: remember this tile, without spending a tick",
+            ScriptCommand::Return => "Return: teleport back to the last marked tile",
+            ScriptCommand::Breakpoint => "Breakpoint: pause autoplay here",
+            ScriptCommand::OpenBracket => {
+                "Open bracket: start a loop, which is left when a command in it fails"
+            }
+            ScriptCommand::Repeat(_) => {
+                "Repeat: start a loop that runs as many times as its number, type 1-9 to change it"
+            }
+            ScriptCommand::CloseBracket => "Close bracket: go back to the start of the loop",
+        }
+    }
+
+    /// The direction the player faces after executing this command.
+    pub fn apply_facing(self, x_dir: i32) -> i32 {
+        match self {
+            ScriptCommand::Turn => -x_dir,
+            ScriptCommand::Face(facing) => facing.x_dir(),
+            _ => x_dir,
+        }
+    }
+
+    /// All matched bracket pairs of the script, outer scopes first.
+    /// Unmatched brackets are left out.
+    pub fn scopes(script: &[ScriptCommand]) -> Vec<Scope> {
+        let mut open = Vec::new();
+        let mut scopes = Vec::new();
+        for (index, command) in script.iter().enumerate() {
+            match command {
+                command if command.opens_scope() => open.push(index),
+                ScriptCommand::CloseBracket => {
+                    if let Some(start) = open.pop() {
+                        scopes.push(Scope {
+                            start,
+                            end: index,
+                            depth: open.len(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        scopes.sort_by_key(|scope| scope.start);
+        scopes
+    }
+
     pub fn get_resource(self, assets: &PlayerAssets) -> Vec<&AnimationResource> {
         match self {
             ScriptCommand::Walk => vec![&assets.walk],
@@ -30,9 +211,19 @@ impl ScriptCommand {
             ScriptCommand::Drop => vec![&assets.drop, &assets.drop2],
             ScriptCommand::Idle => vec![&assets.idle],
             ScriptCommand::Turn => vec![&assets.turn],
+            // Only executed when it actually turns the player.
+            ScriptCommand::Face(_) => vec![&assets.turn],
+            // Waiting for the right tick is the same as idling.
+            ScriptCommand::Sync(_) => vec![&assets.idle],
             ScriptCommand::Jump => vec![&assets.jump],
+            // The real depth is only known once the level is checked.
+            ScriptCommand::Fall => vec![&assets.fall],
+            // Never moves the player, so it is never valid on its own.
+            ScriptCommand::Breakpoint => vec![],
+            // Both depend on the marks, so the interpreter handles them.
+            ScriptCommand::Mark | ScriptCommand::Return => unreachable!(),
             ScriptCommand::CloseBracket => unreachable!(),
-            ScriptCommand::OpenBracket => unreachable!(),
+            ScriptCommand::OpenBracket | ScriptCommand::Repeat(_) => unreachable!(),
         }
     }
 }
@@ -45,14 +236,148 @@ impl Level {
         x_dir: i32,
         assets: &PlayerAssets,
     ) -> Option<AnimationResource> {
+        if let ScriptCommand::Fall = action {
+            return self
+                .fall_depth(pos)
+                .map(|depth| assets.fall.repeated(depth));
+        }
+
         let anim = action.get_resource(assets);
         anim.into_iter()
             .find(|anim| {
-                let mut squares = anim.squares.iter().copied();
-                let free =
-                    squares.all(|square| !self.is_solid(pos + square * IVec2::new(x_dir, 1)));
+                let flip = IVec2::new(x_dir, 1);
+                let mut squares = anim.swept_squares().into_iter();
+                let free = squares
+                    .all(|(square, dir)| !self.is_solid_from(pos + square * flip, dir * flip));
+                // One-way walls cannot be stood on.
                 free && self.is_solid(pos + anim.final_offset(x_dir) + DOWN)
             })
             .cloned()
     }
 }
+
+impl Level {
+    /// How many cells the player would fall straight down from `pos` before
+    /// landing. Returns `None` when already standing on ground or when the
+    /// fall would be deeper than [`MAX_FALL`].
+    pub fn fall_depth(&self, pos: IVec2) -> Option<i32> {
+        (1..=MAX_FALL)
+            .take_while(|depth| !self.is_solid(pos + DOWN * *depth))
+            .find(|depth| self.is_solid(pos + DOWN * (depth + 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo::{
+        level::DirMask,
+        testing::{assets, floor},
+    };
+
+    #[test]
+    fn fall_depth_counts_cells_above_ground() {
+        let level = floor();
+        assert_eq!(level.fall_depth(IVec2::new(0, 4)), Some(3));
+        assert_eq!(level.fall_depth(IVec2::new(0, 2)), Some(1));
+    }
+
+    #[test]
+    fn fall_depth_is_none_on_ground() {
+        assert_eq!(floor().fall_depth(IVec2::new(0, 1)), None);
+    }
+
+    #[test]
+    fn fall_depth_is_none_when_too_deep() {
+        let level = floor();
+        assert_eq!(
+            level.fall_depth(IVec2::new(0, MAX_FALL + 1)),
+            Some(MAX_FALL)
+        );
+        assert_eq!(level.fall_depth(IVec2::new(0, MAX_FALL + 2)), None);
+        // Nothing to land on.
+        assert_eq!(level.fall_depth(IVec2::new(20, 4)), None);
+    }
+
+    #[test]
+    fn parity_matches_tick_count() {
+        assert!(Parity::Even.matches(0));
+        assert!(Parity::Even.matches(4));
+        assert!(!Parity::Even.matches(3));
+        assert!(Parity::Odd.matches(1));
+        assert!(!Parity::Odd.matches(2));
+    }
+
+    /// A floor with a ledge two cells ahead of `(0, 1)`, which is only
+    /// reachable by jumping, and the given extra walls.
+    fn ledge(extra: &[IVec2]) -> Level {
+        let floor = (-2..=5).map(|x| IVec2::new(x, 0));
+        let walls = floor.chain([IVec2::new(2, 1)]).chain(extra.iter().copied());
+        Level::from_walls(walls, IVec2::new(0, 1))
+    }
+
+    #[test]
+    fn one_way_wall_blocks_only_one_direction() {
+        let mut level = floor();
+        level.one_way.insert(IVec2::new(1, 1), DirMask::RIGHT);
+        let walk = |pos, x_dir| level.check_valid(pos, ScriptCommand::Walk, x_dir, &assets());
+        assert!(walk(IVec2::new(0, 1), 1).is_none());
+        assert!(walk(IVec2::new(2, 1), -1).is_some());
+    }
+
+    #[test]
+    fn jump_onto_ledge() {
+        let anim = ledge(&[]).check_valid(IVec2::new(0, 1), ScriptCommand::Jump, 1, &assets());
+        assert_eq!(
+            anim.map(|anim| anim.final_offset(1)),
+            Some(IVec2::new(2, 1))
+        );
+    }
+
+    #[test]
+    fn jump_blocked_by_ceiling_in_arc() {
+        let level = ledge(&[IVec2::new(1, 2)]);
+        let anim = level.check_valid(IVec2::new(0, 1), ScriptCommand::Jump, 1, &assets());
+        assert!(anim.is_none());
+    }
+
+    #[test]
+    fn jump_below_higher_ceiling() {
+        let level = ledge(&[IVec2::new(0, 3), IVec2::new(1, 3), IVec2::new(2, 3)]);
+        let anim = level.check_valid(IVec2::new(0, 1), ScriptCommand::Jump, 1, &assets());
+        assert!(anim.is_some());
+    }
+
+    #[test]
+    fn every_command_has_its_own_description() {
+        use ScriptCommand::*;
+        let commands = [
+            Walk,
+            Climb,
+            Drop,
+            Idle,
+            Turn,
+            Jump,
+            Fall,
+            Face(Facing::Left),
+            Face(Facing::Right),
+            Sync(Parity::Even),
+            Sync(Parity::Odd),
+            Mark,
+            Return,
+            Breakpoint,
+            OpenBracket,
+            Repeat(DEFAULT_REPEAT),
+            CloseBracket,
+        ];
+        let descriptions: Vec<_> = commands
+            .iter()
+            .map(|command| command.description())
+            .collect();
+        for (command, description) in commands.iter().zip(&descriptions) {
+            assert!(!description.is_empty(), "{command:?} has no description");
+            let same = descriptions.iter().filter(|&other| other == description);
+            assert_eq!(same.count(), 1, "{command:?} shares its description");
+        }
+    }
+}
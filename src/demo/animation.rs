@@ -4,23 +4,29 @@
 //! - [Sprite animation](https://github.com/bevyengine/bevy/blob/latest/examples/2d/sprite_animation.rs)
 //! - [Timers](https://github.com/bevyengine/bevy/blob/latest/examples/time/timers.rs)
 
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
 use bevy::{
     prelude::*,
     render::texture::{ImageLoaderSettings, ImageSampler},
     sprite::Anchor,
 };
+use serde::Deserialize;
 
 use super::{
     action::{DOWN, RIGHT, UP},
-    level::{AnimationTick, GridTransform, WorldGrid},
-    player::PlayerState,
+    level::{AnimationTick, GridTransform, Level, NextGridTransform, WorldGrid, PLAYER_Z},
+    obstacle::HAZARD_FRAMES,
+    player::{PlayerState, Respawning},
 };
-use crate::{demo::player::Player, AppSet};
+use crate::{demo::player::Player, modding, AppSet};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(Update, apply_animation.in_set(AppSet::ApplyAnimation));
+    app.add_systems(
+        Update,
+        validate_sheet_size.run_if(resource_added::<PlayerAssets>),
+    );
 }
 
 #[derive(Reflect, PartialEq, Clone, Copy)]
@@ -31,41 +37,96 @@ pub enum PlayerAnimationState {
     Idle(usize),
 }
 
-fn apply_animation(
+pub(super) fn apply_animation(
     state: Res<PlayerState>,
     mut q: Query<
         (
             &mut Transform,
             &GridTransform,
+            &NextGridTransform,
             &mut TextureAtlas,
             &mut Sprite,
+            Option<&Respawning>,
         ),
         With<Player>,
     >,
     grid: Res<WorldGrid>,
+    level: Res<Level>,
     tick: Res<AnimationTick>,
     player_assets: Option<Res<PlayerAssets>>,
 ) {
-    let Ok((mut transform, pos, mut atlas, mut sprite)) = q.get_single_mut() else {
+    let Ok((mut transform, pos, next_pos, mut atlas, mut sprite, respawning)) = q.get_single_mut()
+    else {
         return;
     };
 
-    let anim = state
-        .animation
-        .as_ref()
-        .unwrap_or(&player_assets.as_ref().unwrap().idle);
+    let player_assets = player_assets.unwrap();
+    let anim = match respawning {
+        Some(_) => &player_assets.death,
+        None => state.animation.as_ref().unwrap_or(&player_assets.idle),
+    };
 
-    let new = grid.project_to_world(pos.0.as_vec2());
-    transform.translation = new.extend(transform.translation.z);
+    // Whatever the animation does not cover was done by a platform, which
+    // moves linearly like the hazards.
+    let carried = match tick.0.finished() {
+        true => Vec2::ZERO,
+        false => (next_pos.0 - pos.0 - anim.final_offset(state.x_dir)).as_vec2(),
+    };
+    let mut offset = carried * tick.0.fraction();
+    if anim.moves_sprite {
+        let t = anim.easing.apply(tick.0.fraction());
+        offset += anim.final_offset(state.x_dir).as_vec2() * t;
+        offset.y += anim.arc * 4.0 * t * (1.0 - t);
+    }
+    let new = grid.project_to_world(pos.0.as_vec2() + offset);
+    transform.translation = new.extend(PLAYER_Z);
 
-    atlas.index = anim.row_number * (PlayerAssets::ANIM_COLUMNS as usize)
-        + ((tick.0.fraction() * anim.frame_count as f32) as usize).min(anim.frame_count - 1);
-    if state.animation.is_none() {
+    // The death animation plays while the tick is paused.
+    let fraction = respawning.map_or(tick.0.fraction(), |respawning| respawning.0.fraction());
+    atlas.index = anim.row_number * player_assets.columns
+        + ((fraction * anim.frame_count as f32) as usize).min(anim.frame_count - 1);
+    if state.animation.is_none() && respawning.is_none() {
         atlas.index = 0;
     }
 
     sprite.flip_x = state.x_dir == -1;
-    sprite.anchor = Anchor::Custom(anim.anchor.as_vec() * Vec2::new(state.x_dir as f32, 1.));
+    let mut anchor = anim.anchor(state.x_dir);
+    // Animations in place should stay in their cell, so do not let the anchor
+    // draw them into a wall next to it.
+    let drawn = grid.world_to_grid(new + anchor_offset(anchor, player_assets.frame_size));
+    if anim.squares.is_empty() && drawn != pos.0 && level.is_solid(drawn) {
+        anchor = Vec2::ZERO;
+    }
+    sprite.anchor = Anchor::Custom(anchor);
+}
+
+/// How far the center of a sprite of `frame_size` is drawn from its
+/// transform with the given anchor.
+fn anchor_offset(anchor: Vec2, frame_size: Vec2) -> Vec2 {
+    -anchor * frame_size
+}
+
+/// How the progress through a move speeds up and slows down.
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Map the linear progress `t` from 0 to 1 to the eased progress.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
 }
 
 #[derive(Clone, Reflect)]
@@ -74,13 +135,77 @@ pub struct AnimationResource {
     pub duration: Duration,
     frame_count: usize,
     anchor: Anchor,
+    /// Anchor when facing left, for frames that are not drawn symmetrically.
+    /// Without one, `anchor` is mirrored.
+    anchor_left: Option<Anchor>,
     row_number: usize,
+    /// Whether the sprite is moved along `squares` in code, instead of the
+    /// motion being drawn into the animation frames.
+    moves_sprite: bool,
+    /// Whether diagonal steps between `squares` also need both cells at the
+    /// corner they cut to be free, for arcs that would otherwise clip walls.
+    corner_clearance: bool,
+    /// Easing of the motion of sprites moved in code.
+    easing: Easing,
+    /// Height in cells of a parabola that sprites moved in code are lifted
+    /// along, peaking halfway through the move.
+    arc: f32,
 }
 
 impl AnimationResource {
+    /// The anchor of the sprite when facing in `x_dir`.
+    pub fn anchor(&self, x_dir: i32) -> Vec2 {
+        match (x_dir, &self.anchor_left) {
+            (-1, Some(anchor_left)) => anchor_left.as_vec(),
+            _ => self.anchor.as_vec() * Vec2::new(x_dir as f32, 1.),
+        }
+    }
+
     pub fn final_offset(&self, x_dir: i32) -> IVec2 {
         self.squares.last().copied().unwrap_or(IVec2::ZERO) * IVec2::new(x_dir, 1)
     }
+
+    /// Every cell the player passes through relative to the start when facing
+    /// right, including corners cut by diagonal steps if required. Each cell
+    /// comes with the direction it is entered from.
+    pub fn swept_squares(&self) -> Vec<(IVec2, IVec2)> {
+        let mut cells = Vec::new();
+        let mut prev = IVec2::ZERO;
+        for &square in &self.squares {
+            let step = square - prev;
+            if self.corner_clearance && step.x != 0 && step.y != 0 {
+                cells.push((prev + IVec2::new(step.x, 0), IVec2::new(step.x, 0).signum()));
+                cells.push((prev + IVec2::new(0, step.y), IVec2::new(0, step.y).signum()));
+            }
+            cells.push((square, step.signum()));
+            prev = square;
+        }
+        cells
+    }
+
+    /// Stand still for the tick and then appear at `offset`, which is
+    /// relative to the start regardless of which way the player faces.
+    pub fn teleport(&self, offset: IVec2, x_dir: i32) -> AnimationResource {
+        AnimationResource {
+            squares: vec![offset * IVec2::new(x_dir, 1)],
+            moves_sprite: false,
+            corner_clearance: false,
+            easing: Easing::Linear,
+            arc: 0.0,
+            ..self.clone()
+        }
+    }
+
+    /// Repeat a single-square animation `count` times in a row, e.g. to fall
+    /// several cells at once.
+    pub fn repeated(&self, count: i32) -> AnimationResource {
+        let step = self.squares.first().copied().unwrap_or(IVec2::ZERO);
+        AnimationResource {
+            squares: (1..=count).map(|i| step * i).collect(),
+            duration: self.duration.mul_f32(count as f32),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Resource, Asset, Reflect, Clone)]
@@ -101,9 +226,20 @@ pub struct PlayerAssets {
 
     pub turn: AnimationResource,
 
+    pub fall: AnimationResource,
+
+    /// Played after touching a hazard, before respawning.
+    pub death: AnimationResource,
+
     #[dependency]
     pub texture: Handle<Image>,
     pub layout: Handle<TextureAtlasLayout>,
+    /// Number of frames in each row of `texture`.
+    pub columns: usize,
+    /// The size of `texture` according to its descriptor.
+    sheet_size: UVec2,
+    /// The size of a single frame of `texture`.
+    frame_size: Vec2,
 
     #[dependency]
     pub hazard_texture: Handle<Image>,
@@ -111,10 +247,85 @@ pub struct PlayerAssets {
 }
 
 impl PlayerAssets {
-    pub const ANIM_COLUMNS: u32 = 16;
-    pub const ANIM_ROWS: u32 = 8;
     pub const HAZARD_PATH: &'static str = "images/hazard.png";
     pub const PATH: &'static str = "images/robot.png";
+    pub const SHEET_PATH: &'static str = "images/robot.ron";
+}
+
+/// Layout of the player sprite sheet, read from a RON file next to the texture
+/// so that skins with a different layout do not need code changes.
+#[derive(Deserialize, Debug)]
+struct SheetDescriptor {
+    frame_size: (u32, u32),
+    columns: u32,
+    rows: u32,
+    animations: HashMap<String, SheetAnimation>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+struct SheetAnimation {
+    row: usize,
+    frames: usize,
+}
+
+impl SheetDescriptor {
+    /// Every descriptor has to contain these animations.
+    const ANIMATIONS: [&'static str; 9] = [
+        "idle", "walk", "climb", "turn", "drop", "drop2", "jump", "fall", "death",
+    ];
+    const BUNDLED: &'static str = include_str!("../../assets/images/robot.ron");
+
+    /// The descriptor from the mods folder if there is a valid one, otherwise
+    /// the bundled one.
+    fn load() -> Self {
+        if let Some(source) = modding::read_override(PlayerAssets::SHEET_PATH) {
+            match Self::parse(&source) {
+                Ok(sheet) => return sheet,
+                Err(err) => log::error!("Invalid `{}`: {err}", PlayerAssets::SHEET_PATH),
+            }
+        }
+        Self::parse(Self::BUNDLED).expect("the bundled sheet descriptor should be valid")
+    }
+
+    fn parse(source: &str) -> Result<Self, String> {
+        let sheet: Self = ron::from_str(source).map_err(|err| err.to_string())?;
+        for name in Self::ANIMATIONS {
+            let Some(anim) = sheet.animations.get(name) else {
+                return Err(format!("missing animation `{name}`"));
+            };
+            if anim.row >= sheet.rows as usize {
+                return Err(format!("row of `{name}` is outside the sheet"));
+            }
+            if anim.frames == 0 || anim.frames > sheet.columns as usize {
+                return Err(format!("frame count of `{name}` does not fit in a row"));
+            }
+        }
+        Ok(sheet)
+    }
+
+    fn get(&self, name: &str) -> SheetAnimation {
+        self.animations[name]
+    }
+
+    fn size(&self) -> UVec2 {
+        UVec2::from(self.frame_size) * UVec2::new(self.columns, self.rows)
+    }
+}
+
+/// Warn about a sprite sheet that does not match its descriptor, which would
+/// otherwise only show up as garbled animations.
+fn validate_sheet_size(assets: Res<PlayerAssets>, images: Res<Assets<Image>>) {
+    let Some(image) = images.get(&assets.texture) else {
+        return;
+    };
+    if image.size() != assets.sheet_size {
+        log::warn!(
+            "`{}` is {} pixels, but its descriptor expects {}",
+            PlayerAssets::PATH,
+            image.size(),
+            assets.sheet_size
+        );
+    }
 }
 
 impl FromWorld for PlayerAssets {
@@ -126,8 +337,9 @@ impl FromWorld for PlayerAssets {
             settings.sampler = ImageSampler::nearest();
         };
 
-        let texture = assets.load_with_settings(PlayerAssets::PATH, settings);
-        let hazard_texture = assets.load_with_settings(PlayerAssets::HAZARD_PATH, settings);
+        let texture = assets.load_with_settings(modding::resolve(PlayerAssets::PATH), settings);
+        let hazard_texture =
+            assets.load_with_settings(modding::resolve(PlayerAssets::HAZARD_PATH), settings);
 
         // A texture atlas is a way to split one image with a grid into multiple
         // sprites. By attaching it to a [`SpriteBundle`] and providing an index, we
@@ -135,17 +347,29 @@ impl FromWorld for PlayerAssets {
         // to animate our player character. You can learn more about texture atlases in
         // this example: https://github.com/bevyengine/bevy/blob/latest/examples/2d/texture_atlas.rs
         let mut texture_atlas_layouts = world.resource_mut::<Assets<TextureAtlasLayout>>();
+        Self::new(texture, hazard_texture, &mut texture_atlas_layouts)
+    }
+}
 
+impl PlayerAssets {
+    /// Describe the animations of the given textures, adding their atlas
+    /// layouts to `texture_atlas_layouts`.
+    pub(crate) fn new(
+        texture: Handle<Image>,
+        hazard_texture: Handle<Image>,
+        texture_atlas_layouts: &mut Assets<TextureAtlasLayout>,
+    ) -> Self {
+        let sheet = SheetDescriptor::load();
         let layout = texture_atlas_layouts.add(TextureAtlasLayout::from_grid(
-            UVec2::splat(48),
-            PlayerAssets::ANIM_COLUMNS,
-            PlayerAssets::ANIM_ROWS,
+            UVec2::from(sheet.frame_size),
+            sheet.columns,
+            sheet.rows,
             None,
             None,
         ));
         let hazard_layout = texture_atlas_layouts.add(TextureAtlasLayout::from_grid(
             UVec2::splat(16),
-            4,
+            HAZARD_FRAMES as u32,
             1,
             None,
             None,
@@ -155,56 +379,148 @@ impl FromWorld for PlayerAssets {
             idle: AnimationResource {
                 squares: vec![],
                 duration: Duration::from_secs_f32(0.8),
-                frame_count: 4,
+                frame_count: sheet.get("idle").frames,
                 anchor: Anchor::Center,
-                row_number: 0,
+                anchor_left: None,
+                row_number: sheet.get("idle").row,
+                moves_sprite: false,
+                corner_clearance: false,
+                easing: Easing::Linear,
+                arc: 0.0,
             },
             walk: AnimationResource {
                 squares: vec![RIGHT],
                 duration: Duration::from_secs_f32(0.8),
-                frame_count: 12,
+                frame_count: sheet.get("walk").frames,
                 anchor: Anchor::Center,
-                row_number: 1,
+                anchor_left: None,
+                row_number: sheet.get("walk").row,
+                moves_sprite: false,
+                corner_clearance: false,
+                easing: Easing::Linear,
+                arc: 0.0,
             },
             climb: AnimationResource {
                 squares: vec![UP, UP + RIGHT],
                 duration: Duration::from_secs_f32(0.8),
-                frame_count: 10,
+                frame_count: sheet.get("climb").frames,
                 anchor: Anchor::Center,
-                row_number: 2,
+                anchor_left: None,
+                row_number: sheet.get("climb").row,
+                moves_sprite: false,
+                corner_clearance: false,
+                easing: Easing::Linear,
+                arc: 0.0,
             },
             turn: AnimationResource {
                 squares: vec![],
                 duration: Duration::from_secs_f32(0.8),
-                frame_count: 7,
+                frame_count: sheet.get("turn").frames,
                 anchor: Anchor::Center,
-                row_number: 3,
+                anchor_left: None,
+                row_number: sheet.get("turn").row,
+                moves_sprite: false,
+                corner_clearance: false,
+                easing: Easing::Linear,
+                arc: 0.0,
             },
             drop: AnimationResource {
                 squares: vec![RIGHT, DOWN + RIGHT],
                 duration: Duration::from_secs_f32(0.8),
-                frame_count: 11,
+                frame_count: sheet.get("drop").frames,
                 anchor: Anchor::Center,
-                row_number: 4,
+                anchor_left: None,
+                row_number: sheet.get("drop").row,
+                moves_sprite: false,
+                corner_clearance: false,
+                easing: Easing::Linear,
+                arc: 0.0,
             },
             drop2: AnimationResource {
                 squares: vec![RIGHT, DOWN + RIGHT, DOWN + DOWN + RIGHT],
                 duration: Duration::from_secs_f32(0.8),
-                frame_count: 12,
+                frame_count: sheet.get("drop2").frames,
                 anchor: Anchor::Custom(Vec2::new(0.0, 1.0 / 3.0)),
-                row_number: 5,
+                anchor_left: None,
+                row_number: sheet.get("drop2").row,
+                moves_sprite: false,
+                corner_clearance: false,
+                easing: Easing::Linear,
+                arc: 0.0,
             },
             jump: AnimationResource {
                 squares: vec![RIGHT, UP, RIGHT + UP, RIGHT + UP + RIGHT],
                 duration: Duration::from_secs_f32(0.8),
-                frame_count: 13,
+                frame_count: sheet.get("jump").frames,
                 anchor: Anchor::Custom(Vec2::new(-1.0 / 3.0, 0.0)),
-                row_number: 6,
+                anchor_left: None,
+                row_number: sheet.get("jump").row,
+                moves_sprite: false,
+                corner_clearance: true,
+                easing: Easing::Linear,
+                arc: 0.0,
+            },
+            fall: AnimationResource {
+                squares: vec![DOWN],
+                duration: Duration::from_secs_f32(0.2),
+                frame_count: sheet.get("fall").frames,
+                anchor: Anchor::Center,
+                anchor_left: None,
+                row_number: sheet.get("fall").row,
+                moves_sprite: true,
+                corner_clearance: false,
+                easing: Easing::EaseIn,
+                arc: 0.0,
+            },
+            death: AnimationResource {
+                squares: vec![],
+                duration: Duration::from_secs_f32(Respawning::DURATION),
+                frame_count: sheet.get("death").frames,
+                anchor: Anchor::Center,
+                anchor_left: None,
+                row_number: sheet.get("death").row,
+                moves_sprite: false,
+                corner_clearance: false,
+                easing: Easing::Linear,
+                arc: 0.0,
             },
             texture,
             layout,
+            columns: sheet.columns as usize,
+            sheet_size: sheet.size(),
+            frame_size: UVec2::from(sheet.frame_size).as_vec2(),
             hazard_layout,
             hazard_texture,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchor_offset_is_mirrored_when_facing_left() {
+        let assets = PlayerAssets::new(default(), default(), &mut Assets::default());
+        let mut anim = assets.climb.clone();
+        anim.anchor = Anchor::Custom(Vec2::new(0.25, -0.125));
+        let size = assets.frame_size;
+        let right = anchor_offset(anim.anchor(1), size);
+        let left = anchor_offset(anim.anchor(-1), size);
+        assert_ne!(right.x, 0.0);
+        assert_eq!(left, Vec2::new(-right.x, right.y));
+    }
+
+    #[test]
+    fn ease_in_out_keeps_the_ends_and_the_middle() {
+        let ease = Easing::EaseInOut;
+        assert_eq!(ease.apply(0.0), 0.0);
+        assert_eq!(ease.apply(1.0), 1.0);
+        assert_eq!(ease.apply(0.5), 0.5);
+        // Fastest in the middle, slower than linear at the ends.
+        let slope = |t: f32| (ease.apply(t + 0.01) - ease.apply(t - 0.01)) / 0.02;
+        assert!(slope(0.5) > 1.0);
+        assert!(slope(0.5) > slope(0.25));
+        assert!(slope(0.1) < 1.0);
+    }
+}
@@ -0,0 +1,161 @@
+//! A dismissible dialogue box, e.g. for the intro text of a level.
+//! Editing is paused while it is open.
+
+use std::collections::BTreeSet;
+
+use bevy::{
+    ecs::{system::RunSystemOnce as _, world::Command},
+    prelude::*,
+    ui::Val::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    persistence,
+    screens::Screen,
+    theme::{palette::NODE_BACKGROUND, prelude::*},
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.insert_resource(SeenTutorials::load());
+    app.add_systems(
+        Update,
+        advance_on_key
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::Gameplay).and_then(resource_exists::<Dialogue>)),
+    );
+    app.add_systems(OnExit(Screen::Gameplay), |mut commands: Commands| {
+        commands.remove_resource::<Dialogue>();
+    });
+}
+
+/// The currently open dialogue. Only exists while the dialogue box is shown.
+#[derive(Resource, Debug)]
+pub struct Dialogue {
+    pages: Vec<String>,
+    page: usize,
+}
+
+/// Tutorial messages (such as level intros) that were already shown, by id.
+/// This is saved so that returning players are not taught again.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct SeenTutorials(BTreeSet<String>);
+
+impl SeenTutorials {
+    const KEY: &'static str = "seen-tutorials";
+
+    fn load() -> Self {
+        persistence::load(Self::KEY).unwrap_or_default()
+    }
+
+    /// Mark the tutorial as seen. Returns whether this is the first time.
+    pub fn first_time(&mut self, id: &str) -> bool {
+        let new = self.0.insert(id.to_string());
+        if new {
+            persistence::save(Self::KEY, self);
+        }
+        new
+    }
+
+    /// Show all tutorials again.
+    pub fn reset(&mut self) {
+        self.0.clear();
+        persistence::save(Self::KEY, self);
+    }
+}
+
+#[derive(Component)]
+struct DialogueBox;
+
+#[derive(Component)]
+struct DialogueText;
+
+/// Open a dialogue box showing the given pages one after another.
+pub struct ShowDialogue {
+    pub pages: Vec<String>,
+}
+
+impl Command for ShowDialogue {
+    fn apply(self, world: &mut World) {
+        world.run_system_once_with(self, show_dialogue);
+    }
+}
+
+fn show_dialogue(config: In<ShowDialogue>, mut commands: Commands) {
+    let pages = config.0.pages;
+    let Some(first) = pages.first().cloned() else {
+        return;
+    };
+    commands.insert_resource(Dialogue { pages, page: 0 });
+
+    commands
+        .ui_root()
+        .insert((
+            Name::new("Dialogue"),
+            DialogueBox,
+            StateScoped(Screen::Gameplay),
+        ))
+        .with_children(|children| {
+            children
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::End,
+                        row_gap: Px(10.0),
+                        padding: UiRect::all(Px(20.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(NODE_BACKGROUND.with_alpha(0.9)),
+                    ..default()
+                })
+                .with_children(|children| {
+                    children.label(first).insert(DialogueText);
+                    children.small_button("OK").observe(advance_on_press);
+                });
+        });
+}
+
+/// Go to the next page, closing the dialogue after the last one.
+struct AdvanceDialogue;
+
+impl Command for AdvanceDialogue {
+    fn apply(self, world: &mut World) {
+        world.run_system_once(advance_dialogue);
+    }
+}
+
+fn advance_dialogue(
+    dialogue: Option<ResMut<Dialogue>>,
+    mut text: Query<&mut Text, With<DialogueText>>,
+    dialogue_box: Query<Entity, With<DialogueBox>>,
+    mut commands: Commands,
+) {
+    let Some(mut dialogue) = dialogue else {
+        return;
+    };
+    dialogue.page += 1;
+    match dialogue.pages.get(dialogue.page) {
+        Some(page) => {
+            for mut text in &mut text {
+                text.sections[0].value.clone_from(page);
+            }
+        }
+        None => {
+            commands.remove_resource::<Dialogue>();
+            for entity in &dialogue_box {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn advance_on_key(input: Res<ButtonInput<KeyCode>>, mut commands: Commands) {
+    if input.just_pressed(KeyCode::Enter) || input.just_pressed(KeyCode::Space) {
+        commands.add(AdvanceDialogue);
+    }
+}
+
+fn advance_on_press(_trigger: Trigger<OnPress>, mut commands: Commands) {
+    commands.add(AdvanceDialogue);
+}
@@ -1,14 +1,50 @@
+use std::{ops::Range, time::Duration};
+
 use bevy::{
+    color::Mix,
     ecs::{system::RunSystemOnce, world::Command},
+    input::{
+        keyboard::KeyboardInput,
+        mouse::{MouseScrollUnit, MouseWheel},
+        ButtonState,
+    },
     prelude::*,
     render::texture::{ImageLoaderSettings, ImageSampler},
+    ui::RelativeCursorPosition,
+    utils::{HashMap, HashSet},
+    window::PrimaryWindow,
 };
+use bevy_ecs_ldtk::LevelSelection;
 
-use super::{action::ScriptCommand, player::PlayerState};
+use super::{
+    action::{CommandCategory, Facing, Parity, Scope, ScriptCommand},
+    player::PlayerState,
+};
 use crate::{
     asset_tracking::LoadResource,
-    demo::{level::Level, player::AddUnlockedCommand},
-    screens::Screen,
+    demo::{
+        animation::PlayerAssets,
+        dialogue::Dialogue,
+        level::{level_index, AnimationTick, GridTransform, Level, TickCount, WorldGrid},
+        obstacle::Obstacle,
+        player::{AddUnlockedCommand, Player},
+        progress::{restore_progress, Progress},
+        replay::{RecordReplay, ReplayViewer},
+        simulation::{simulate, SimObstacle, SimState},
+        solutions::save_submitted,
+    },
+    key_bindings::{KeyAction, KeyBindings},
+    modding,
+    screens::{
+        gameplay::{BracketNoticeLabel, ScriptLengthLabel},
+        Screen,
+    },
+    settings::{Settings, SubmitMode},
+    theme::{
+        interaction::{text_input_focused, SilentInteraction},
+        palette::NODE_BACKGROUND,
+    },
+    AppSet,
 };
 
 pub(super) fn plugin(app: &mut App) {
@@ -16,22 +52,184 @@ pub(super) fn plugin(app: &mut App) {
     app.load_resource::<EditorAssets>();
     app.add_systems(
         Update,
-        (edit_script, submit_script).run_if(in_state(Screen::Gameplay)),
+        (
+            (
+                edit_script,
+                submit_script,
+                focus_next_failure,
+                click_editor_item,
+            )
+                .in_set(AppSet::RecordInput)
+                .run_if(
+                    not(text_input_focused)
+                        .and_then(not(resource_exists::<Dialogue>))
+                        .and_then(not(resource_exists::<ReplayViewer>)),
+                ),
+            toggle_compact.run_if(not(text_input_focused)),
+            highlight_cursor_target,
+            highlight_hovered_cell,
+            follow_program_counter.in_set(AppSet::Update),
+            show_command_tooltip.in_set(AppSet::Update),
+            flash_added_brackets.run_if(resource_exists::<AddedBrackets>),
+            show_bracket_warning.run_if(resource_changed::<EditorState>),
+        )
+            .run_if(in_state(Screen::Gameplay)),
     );
     // Send `EditorChanged` event at start.
-    app.add_systems(OnEnter(Screen::Gameplay), |mut ev: Commands| {
-        ev.add(ShowEditor::default());
-        ev.add(AddUnlockedCommand {
-            command: ScriptCommand::Walk,
-        });
-    });
+    app.add_systems(
+        OnEnter(Screen::Gameplay),
+        show_editor_with_unlocks.after(restore_progress),
+    );
 }
 
+/// Show the editor and every command that is already unlocked, which is more
+/// than `Walk` when progress was restored.
+fn show_editor_with_unlocks(level: Res<Level>, mut commands: Commands) {
+    commands.add(ShowEditor::default());
+    for &command in &level.unlocked {
+        commands.add(AddUnlockedCommand { command });
+    }
+}
+
+/// The number keys that set the count of a `Repeat`, starting at one.
+const REPEAT_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
 #[derive(Resource, Debug)]
 pub struct EditorState {
     pub enabled: bool,
     entered: Vec<ScriptCommand>,
     cursor: usize,
+    /// The script as it was typed before the last submit, without the
+    /// brackets added to balance it.
+    submitted: Vec<ScriptCommand>,
+    /// The editor never holds more commands than this, regardless of the
+    /// budget of the level.
+    max_len: usize,
+    /// Show the script as a small single-line overview without the cursor.
+    compact: bool,
+    /// The highlighted command, kept so that the editor can be redrawn.
+    active: Option<(usize, bool)>,
+    /// A command that would fail when running the script, found with N.
+    failure: Option<usize>,
+    /// Earlier scripts with their cursor, the most recent last.
+    undo_stack: Vec<(Vec<ScriptCommand>, usize)>,
+    /// Undone scripts with their cursor, the most recently undone last.
+    redo_stack: Vec<(Vec<ScriptCommand>, usize)>,
+    /// Where the selection started when moving the cursor with Shift. The
+    /// selection spans from here to the cursor.
+    anchor: Option<usize>,
+    /// Commands copied with Ctrl+C, pasted with Ctrl+V.
+    clipboard: Vec<ScriptCommand>,
+    /// Index of the first visible item when there are too many items to
+    /// show at once.
+    scroll: usize,
+    /// The last submit was rejected because of unbalanced brackets. Cleared
+    /// by the next edit.
+    unbalanced: bool,
+}
+
+impl EditorState {
+    pub fn script(&self) -> &[ScriptCommand] {
+        &self.entered
+    }
+
+    /// Replace the entered script and move the cursor to its end.
+    pub fn set_script(&mut self, mut script: Vec<ScriptCommand>) {
+        script.truncate(self.max_len);
+        self.cursor = script.len();
+        self.entered = script;
+        self.failure = None;
+        self.anchor = None;
+    }
+
+    /// The selected items, if any.
+    fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        let range = anchor.min(self.cursor)..anchor.max(self.cursor);
+        (!range.is_empty()).then_some(range)
+    }
+
+    /// The script with brackets added so that they are balanced.
+    pub fn balanced_script(&self) -> Vec<ScriptCommand> {
+        self.balance().0
+    }
+
+    /// Like [`EditorState::balanced_script`], but also returns the indices
+    /// of the added brackets in the balanced script.
+    fn balance(&self) -> (Vec<ScriptCommand>, Vec<usize>) {
+        let open = calculate_open_required(&self.entered);
+        let close = calculate_close_required(&self.entered);
+        let script: Vec<_> = (0..open)
+            .map(|_| ScriptCommand::OpenBracket)
+            .chain(self.entered.iter().copied())
+            .chain((0..close).map(|_| ScriptCommand::CloseBracket))
+            .collect();
+        let added = (0..open)
+            .chain(script.len() - close..script.len())
+            .collect();
+        (script, added)
+    }
+
+    /// The command just left of the cursor, i.e. the one most recently typed.
+    pub fn command_at_cursor(&self) -> Option<ScriptCommand> {
+        let index = self.cursor.checked_sub(1)?;
+        self.entered.get(index).copied()
+    }
+
+    /// Remember the script from before a change, so that the change can be
+    /// undone. This forgets everything that was undone.
+    fn push_history(&mut self, script: Vec<ScriptCommand>, cursor: usize) {
+        if self.undo_stack.len() == MAX_HISTORY {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push((script, cursor));
+        self.redo_stack.clear();
+    }
+
+    /// Go back to the script before the last change. Returns whether there
+    /// was anything to undo.
+    fn undo(&mut self) -> bool {
+        let Some((script, cursor)) = self.undo_stack.pop() else {
+            return false;
+        };
+        let current = std::mem::replace(&mut self.entered, script);
+        self.redo_stack.push((current, self.cursor));
+        self.cursor = cursor;
+        self.failure = None;
+        self.anchor = None;
+        true
+    }
+
+    /// Apply the last undone change again. Returns whether there was anything
+    /// to redo.
+    fn redo(&mut self) -> bool {
+        let Some((script, cursor)) = self.redo_stack.pop() else {
+            return false;
+        };
+        let current = std::mem::replace(&mut self.entered, script);
+        self.undo_stack.push((current, self.cursor));
+        self.cursor = cursor;
+        self.failure = None;
+        self.anchor = None;
+        true
+    }
+
+    /// Go back to editing the script exactly as it was before submitting.
+    pub fn undo_submit(&mut self) {
+        let script = self.submitted.clone();
+        self.set_script(script);
+        self.enabled = true;
+    }
 }
 
 impl Default for EditorState {
@@ -40,6 +238,17 @@ impl Default for EditorState {
             enabled: true,
             entered: Default::default(),
             cursor: Default::default(),
+            submitted: Default::default(),
+            max_len: MAX_SCRIPT_LEN,
+            compact: false,
+            active: None,
+            failure: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            anchor: None,
+            clipboard: Vec::new(),
+            scroll: 0,
+            unbalanced: false,
         }
     }
 }
@@ -48,6 +257,31 @@ impl Default for EditorState {
 #[reflect(Component)]
 struct EditorItem;
 
+/// Where the cursor goes when the editor item is clicked. Added brackets and
+/// empty slots move it to the closest end of the script.
+#[derive(Component, Debug)]
+struct EditorSlot(usize);
+
+/// Brackets that were added to balance the script on submit. They flash for
+/// a moment so that the change is not silent.
+#[derive(Resource)]
+struct AddedBrackets {
+    /// The balanced script, so that nothing flashes once it is edited.
+    script: Vec<ScriptCommand>,
+    indices: Vec<usize>,
+    timer: Timer,
+}
+
+impl AddedBrackets {
+    /// How often the brackets flash while the notice is shown.
+    const FLASHES: f32 = 3.0;
+    const SECONDS: f32 = 2.5;
+}
+
+/// An overlay on an added bracket that fades in and out.
+#[derive(Component)]
+struct BracketFlash;
+
 #[derive(Component, Reflect, Debug)]
 #[reflect(Component)]
 pub struct EditorUI;
@@ -65,6 +299,9 @@ pub struct EditorAssets {
 }
 
 impl EditorAssets {
+    /// Atlas index of the placeholder for an empty slot.
+    pub const EMPTY_INDEX: usize = 8;
+    pub const ICON_COUNT: u32 = 18;
     pub const PATH_CURSOR: &'static str = "images/cursor.png";
     pub const PATH_ICONS: &'static str = "images/icons.png";
 
@@ -78,6 +315,17 @@ impl EditorAssets {
             ScriptCommand::Jump => 5,
             ScriptCommand::OpenBracket => 6,
             ScriptCommand::CloseBracket => 7,
+            // 8 is the empty slot.
+            ScriptCommand::Fall => 9,
+            ScriptCommand::Face(Facing::Left) => 10,
+            ScriptCommand::Face(Facing::Right) => 11,
+            ScriptCommand::Sync(Parity::Even) => 12,
+            ScriptCommand::Sync(Parity::Odd) => 13,
+            ScriptCommand::Mark => 14,
+            ScriptCommand::Return => 15,
+            ScriptCommand::Breakpoint => 16,
+            // The count is drawn on top of the icon.
+            ScriptCommand::Repeat(_) => 17,
         }
     }
 }
@@ -87,7 +335,7 @@ impl FromWorld for EditorAssets {
         let assets = world.resource::<AssetServer>();
 
         let icons = assets.load_with_settings(
-            EditorAssets::PATH_ICONS,
+            modding::resolve(EditorAssets::PATH_ICONS),
             |settings: &mut ImageLoaderSettings| {
                 // Use `nearest` image sampling to preserve the pixel art style.
                 settings.sampler = ImageSampler::nearest();
@@ -95,7 +343,7 @@ impl FromWorld for EditorAssets {
         );
 
         let cursor = assets.load_with_settings(
-            EditorAssets::PATH_CURSOR,
+            modding::resolve(EditorAssets::PATH_CURSOR),
             |settings: &mut ImageLoaderSettings| {
                 // Use `nearest` image sampling to preserve the pixel art style.
                 settings.sampler = ImageSampler::nearest();
@@ -106,7 +354,7 @@ impl FromWorld for EditorAssets {
         let atlas = texture_atlas_layouts.add(TextureAtlasLayout::from_grid(
             UVec2::splat(16),
             1,
-            9,
+            EditorAssets::ICON_COUNT,
             None,
             None,
         ));
@@ -119,62 +367,205 @@ impl FromWorld for EditorAssets {
     }
 }
 
-fn edit_script(
+/// Keeps track of held editor keys to repeat them.
+///
+/// Editor keys trigger once when pressed and, with [`Settings::repeat_keys`],
+/// repeatedly while held like in a text field. In contrast, the dev movement
+/// in `debug_actions` reads which keys are held at the start of every tick.
+#[derive(Default)]
+pub(super) struct KeyRepeat {
+    /// How long each key has been held and when it should repeat next.
+    held: HashMap<KeyCode, (Duration, Duration)>,
+    /// Keys that repeated this frame.
+    repeated: HashSet<KeyCode>,
+}
+
+impl KeyRepeat {
+    const DELAY: Duration = Duration::from_millis(400);
+    const INTERVAL: Duration = Duration::from_millis(80);
+
+    fn update(&mut self, input: &ButtonInput<KeyCode>, delta: Duration) {
+        self.held.retain(|key, _| input.pressed(*key));
+        self.repeated.clear();
+        for &key in input.get_pressed() {
+            if input.just_pressed(key) {
+                self.held.insert(key, (Duration::ZERO, Self::DELAY));
+                continue;
+            }
+            let (held, next) = self
+                .held
+                .entry(key)
+                .or_insert((Duration::ZERO, Self::DELAY));
+            *held += delta;
+            if *held >= *next {
+                *next += Self::INTERVAL;
+                self.repeated.insert(key);
+            }
+        }
+    }
+
+    /// Whether the key should be handled this frame.
+    fn triggered(&self, input: &ButtonInput<KeyCode>, key: KeyCode, repeat: bool) -> bool {
+        input.just_pressed(key) || (repeat && self.repeated.contains(&key))
+    }
+}
+
+pub(super) fn edit_script(
     input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut key_repeat: Local<KeyRepeat>,
+    mut scroll: EventReader<MouseWheel>,
+    mut scrolled: Local<f32>,
+    mut keyboard: EventReader<KeyboardInput>,
+    editor_ui: Query<&RelativeCursorPosition, With<EditorUI>>,
     mut editor_state: ResMut<EditorState>,
     mut commands: Commands,
     level: Res<Level>,
 ) {
+    key_repeat.update(&input, time.delta());
+    let pressed = |key| key_repeat.triggered(&input, key, settings.repeat_keys);
+
+    // The order in which keys were pressed this frame.
+    let press_order: Vec<KeyCode> = keyboard
+        .read()
+        .filter(|ev| ev.state == ButtonState::Pressed)
+        .map(|ev| ev.key_code)
+        .collect();
+
     if !editor_state.enabled {
+        scroll.clear();
         return;
     }
 
     let mut changed = false;
 
+    // Undo and redo.
+    let ctrl = input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if ctrl {
+        if pressed(bindings.key(KeyAction::Undo)) {
+            changed |= editor_state.undo();
+        }
+        if pressed(bindings.key(KeyAction::Redo)) {
+            changed |= editor_state.redo();
+        }
+    }
+    // Everything else in this frame is a single change for undo.
+    let before = (editor_state.entered.clone(), editor_state.cursor);
+
+    // Copy and paste. Pasting may go over the budget of the level, which is
+    // only enforced when submitting.
+    if ctrl && pressed(bindings.key(KeyAction::Copy)) {
+        if let Some(range) = editor_state.selection() {
+            editor_state.clipboard = editor_state.entered[range].to_vec();
+        }
+    }
+    if ctrl && pressed(bindings.key(KeyAction::Paste)) {
+        let room = editor_state
+            .max_len
+            .saturating_sub(editor_state.entered.len());
+        let pasted: Vec<_> = editor_state
+            .clipboard
+            .iter()
+            .copied()
+            .filter(|&command| level.allows(command))
+            .take(room)
+            .collect();
+        let index = editor_state.cursor;
+        editor_state.cursor += pasted.len();
+        editor_state.entered.splice(index..index, pasted);
+        changed = true;
+    }
+
     // Command input.
-    let key_command_map = [
-        (KeyCode::KeyW, ScriptCommand::Walk),
-        (KeyCode::KeyC, ScriptCommand::Climb),
-        (KeyCode::KeyD, ScriptCommand::Drop),
-        (KeyCode::KeyI, ScriptCommand::Idle),
-        (KeyCode::KeyJ, ScriptCommand::Jump),
-        (KeyCode::KeyT, ScriptCommand::Turn),
-        (KeyCode::BracketLeft, ScriptCommand::OpenBracket),
-        (KeyCode::BracketRight, ScriptCommand::CloseBracket),
-    ];
-    for (key, command) in key_command_map {
-        let check = match command {
-            ScriptCommand::CloseBracket => ScriptCommand::OpenBracket,
-            rest => rest,
-        };
-        if input.just_pressed(key) && level.unlocked.contains(&check) {
-            changed = true;
-            let index = editor_state.cursor;
-            editor_state.entered.insert(index, command);
-            editor_state.cursor += 1;
+    // All commands typed in the same frame are inserted together, in the
+    // order their keys were pressed. Keys that only repeat go last, in the
+    // order of the key bindings.
+    let mut typed: Vec<_> = bindings
+        .commands()
+        .filter(|&(key, command)| !ctrl && pressed(key) && level.allows(command))
+        .collect();
+    typed.sort_by_key(|(key, _)| {
+        press_order
+            .iter()
+            .position(|pressed| pressed == key)
+            .unwrap_or(usize::MAX)
+    });
+    let room = editor_state
+        .max_len
+        .saturating_sub(editor_state.entered.len());
+    typed.truncate(room);
+    if !typed.is_empty() {
+        changed = true;
+        let index = editor_state.cursor;
+        editor_state
+            .entered
+            .splice(index..index, typed.iter().map(|&(_, command)| command));
+        editor_state.cursor += typed.len();
+    }
+
+    // Number keys set how often the loop just left of the cursor runs.
+    if let Some(ScriptCommand::Repeat(_)) = editor_state.command_at_cursor() {
+        for (count, key) in (1..).zip(REPEAT_KEYS) {
+            if !ctrl && pressed(key) {
+                changed = true;
+                let index = editor_state.cursor - 1;
+                editor_state.entered[index] = ScriptCommand::Repeat(count);
+            }
         }
     }
 
     // Cursor movement.
-    if input.just_pressed(KeyCode::ArrowRight) {
+    let cursor_before = editor_state.cursor;
+    if pressed(bindings.key(KeyAction::CursorRight)) {
         changed = true;
         editor_state.cursor = (editor_state.cursor + 1).min(editor_state.entered.len());
     }
-    if input.just_pressed(KeyCode::ArrowLeft) {
+    if pressed(bindings.key(KeyAction::CursorLeft)) {
         changed = true;
         editor_state.cursor = editor_state.cursor.saturating_sub(1);
     }
-    if input.just_pressed(KeyCode::ArrowUp) {
+    if pressed(bindings.key(KeyAction::CursorToStart)) {
         changed = true;
         editor_state.cursor = 0;
     }
-    if input.just_pressed(KeyCode::ArrowDown) {
+    if pressed(bindings.key(KeyAction::CursorToEnd)) {
         changed = true;
         editor_state.cursor = editor_state.entered.len();
     }
 
+    // Scrolling over the editor moves the cursor, one item per line.
+    if editor_ui.iter().any(RelativeCursorPosition::mouse_over) {
+        for ev in scroll.read() {
+            *scrolled += match ev.unit {
+                MouseScrollUnit::Line => ev.y,
+                MouseScrollUnit::Pixel => ev.y / 50.0,
+            };
+        }
+        let steps = scrolled.trunc();
+        *scrolled -= steps;
+        if steps != 0.0 {
+            changed = true;
+            // Scrolling up moves towards the start of the script.
+            let cursor = editor_state.cursor as i64 - steps as i64;
+            editor_state.cursor = cursor.clamp(0, editor_state.entered.len() as i64) as usize;
+        }
+    } else {
+        scroll.clear();
+        *scrolled = 0.0;
+    }
+    // Moving the cursor while holding Shift selects the items it passes.
+    if editor_state.cursor != cursor_before {
+        let shift = input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        editor_state.anchor = match shift {
+            true => Some(editor_state.anchor.unwrap_or(cursor_before)),
+            false => None,
+        };
+    }
+
     // Delete stuff.
-    if input.just_pressed(KeyCode::Backspace) {
+    if pressed(bindings.key(KeyAction::DeleteBackward)) {
         changed = true;
         let index = editor_state.cursor;
         if index > 0 {
@@ -182,7 +573,7 @@ fn edit_script(
         }
         editor_state.cursor = editor_state.cursor.saturating_sub(1);
     }
-    if input.just_pressed(KeyCode::Delete) {
+    if pressed(bindings.key(KeyAction::DeleteForward)) {
         changed = true;
         let index = editor_state.cursor;
         if index < editor_state.entered.len() {
@@ -190,11 +581,137 @@ fn edit_script(
         }
     }
 
+    if editor_state.entered != before.0 {
+        editor_state.push_history(before.0, before.1);
+        editor_state.anchor = None;
+    }
+
     if changed {
+        editor_state.failure = None;
+        editor_state.unbalanced = false;
         commands.add(ShowEditor::default());
     }
 }
 
+/// Move the cursor in front of the clicked editor item.
+fn click_editor_item(
+    items: Query<(&Interaction, &EditorSlot), Changed<Interaction>>,
+    mut editor_state: ResMut<EditorState>,
+    mut commands: Commands,
+) {
+    if !editor_state.enabled {
+        return;
+    }
+    for (interaction, slot) in &items {
+        if *interaction == Interaction::Pressed {
+            editor_state.cursor = slot.0;
+            editor_state.anchor = None;
+            commands.add(ShowEditor::default());
+        }
+    }
+}
+
+/// How far ahead to look for failing commands.
+const FAILURE_SEARCH_TICKS: usize = 1000;
+
+/// Move the cursor just past the next command that would fail when running
+/// the script from where the player is now, wrapping around to the first one.
+fn focus_next_failure(
+    input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut editor_state: ResMut<EditorState>,
+    player: Query<&GridTransform, With<Player>>,
+    obstacles: Query<(&GridTransform, &Obstacle)>,
+    player_state: Res<PlayerState>,
+    tick_count: Res<TickCount>,
+    level: Res<Level>,
+    assets: Res<PlayerAssets>,
+    mut commands: Commands,
+) {
+    if !input.just_pressed(bindings.key(KeyAction::FocusFailure)) || !editor_state.enabled {
+        return;
+    }
+    let Ok(pos) = player.get_single() else {
+        return;
+    };
+    let start = SimState {
+        player: pos.0,
+        x_dir: player_state.x_dir,
+        cursor: 0,
+        ticks: tick_count.0,
+        obstacles: obstacles
+            .iter()
+            .map(|(pos, obstacle)| SimObstacle {
+                pos: pos.0,
+                patrol: obstacle.patrol().clone(),
+                kind: obstacle.kind,
+                timing: obstacle.timing,
+            })
+            .collect(),
+    };
+    let sequence = editor_state.balanced_script();
+    let run = simulate(&level, &assets, &sequence, start, FAILURE_SEARCH_TICKS);
+
+    // Skip the brackets that were added in front to balance the script.
+    let open = calculate_open_required(&editor_state.entered);
+    let mut failures: Vec<usize> = run
+        .failures
+        .iter()
+        .filter_map(|index| index.checked_sub(open))
+        .filter(|&index| index < editor_state.entered.len())
+        .collect();
+    failures.sort_unstable();
+    failures.dedup();
+
+    let next = failures
+        .iter()
+        .find(|&&index| Some(index) > editor_state.failure)
+        .or(failures.first());
+    let Some(&next) = next else {
+        // The whole script runs fine.
+        return;
+    };
+    editor_state.failure = Some(next);
+    editor_state.cursor = next + 1;
+    commands.add(ShowEditor::default());
+}
+
+/// Default for [`EditorState::max_len`], to keep the editor UI manageable.
+const MAX_SCRIPT_LEN: usize = 100;
+
+/// How many changes to the script can be undone.
+const MAX_HISTORY: usize = 64;
+
+/// Items never shrink below this fraction of the editor height.
+const MIN_ITEM_SCALE: f32 = 0.4;
+/// Height of the editor row as a fraction of the window height.
+const EDITOR_HEIGHT: f32 = 0.1;
+const COMPACT_EDITOR_HEIGHT: f32 = 0.04;
+
+/// The first of `visible` items to show out of `total`, so that the item at
+/// `focus` is in view. Scrolls as little as possible from `first`, so going to
+/// the start or the end snaps there.
+fn scroll_offset(first: usize, focus: usize, visible: usize, total: usize) -> usize {
+    let first = first.min(focus).max((focus + 1).saturating_sub(visible));
+    first.min(total.saturating_sub(visible))
+}
+
+/// Switch between the full editor and a compact overview.
+fn toggle_compact(
+    input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut editor_state: ResMut<EditorState>,
+    mut commands: Commands,
+) {
+    if !input.just_pressed(bindings.key(KeyAction::ToggleCompact)) {
+        return;
+    }
+    editor_state.compact = !editor_state.compact;
+    commands.add(ShowEditor {
+        active: editor_state.active,
+    });
+}
+
 #[derive(Default)]
 pub struct ShowEditor {
     // (currently executed, succes)
@@ -209,23 +726,69 @@ impl Command for ShowEditor {
 
 fn show_script(
     config: In<ShowEditor>,
-    editor_state: Res<EditorState>,
+    mut editor_state: ResMut<EditorState>,
     mut commands: Commands,
-    editor_ui_query: Query<Entity, With<EditorUI>>,
+    mut editor_ui_query: Query<(Entity, &mut Style), With<EditorUI>>,
     editor_items_query: Query<Entity, (With<EditorItem>, Without<EditorUI>)>,
     editor_assets: Res<EditorAssets>,
     level: Res<Level>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut length_label: Query<&mut Text, With<ScriptLengthLabel>>,
+    player_state: Res<PlayerState>,
+    added_brackets: Option<Res<AddedBrackets>>,
+    progress: Res<Progress>,
+    selection: Res<LevelSelection>,
 ) {
+    editor_state.active = config.active;
+    let compact = editor_state.compact;
+    let row_height = match compact {
+        true => COMPACT_EDITOR_HEIGHT,
+        false => EDITOR_HEIGHT,
+    };
+
     let open = calculate_open_required(&editor_state.entered);
     let close = calculate_close_required(&editor_state.entered);
 
+    // Shrink the items of long scripts so that they still fit on screen, and
+    // scroll through them once they cannot shrink any further.
+    let slots = (open + editor_state.entered.len() + close).max(level.command_count);
+    let (item_height, scroll_window) = match window.get_single() {
+        Ok(window) => {
+            // Items are square and as high as the editor row.
+            let item_size = window.height() * row_height;
+            let width = window.width() * 0.9;
+            let scale = (width / (slots as f32 * item_size)).clamp(MIN_ITEM_SCALE, 1.0);
+            let visible = ((width / (item_size * scale)) as usize).max(1);
+            (
+                Val::Percent(100.0 * scale),
+                Some((visible, item_size * scale)),
+            )
+        }
+        Err(_) => (Val::Percent(100.0), None),
+    };
+
+    // Counted like `submit_script` does, with the brackets it would add.
+    let length = open + editor_state.entered.len() + close;
+    let best = match progress.best_length(level_index(&selection)) {
+        Some(best) => format!(", best {best}"),
+        None if progress.assisted(level_index(&selection)) => ", solved with assist".to_string(),
+        None => String::new(),
+    };
+    for mut label in &mut length_label {
+        label.sections[0].value = format!(
+            "{length} / {} commands (at most {}{best})",
+            level.command_count, editor_state.max_len
+        );
+    }
+
     // Despawn all current editor item entities.
     for entity in &editor_items_query {
         commands.entity(entity).despawn_recursive();
     }
 
     // Spawn new editor items.
-    let editor_ui = editor_ui_query.single();
+    let (editor_ui, mut style) = editor_ui_query.single_mut();
+    style.height = Val::Percent(100.0 * row_height);
     let mut total = 0;
     let make_color = |index| {
         if index < level.command_count {
@@ -234,42 +797,67 @@ fn show_script(
             Color::linear_rgba(1.0, 0.0, 0.0, 1.0)
         }
     };
+    let selection = editor_state.selection().unwrap_or_default();
+    let mut items = Vec::new();
     commands.entity(editor_ui).with_children(|children| {
         for _ in 0..open {
             let color = make_color(total).with_alpha(0.5);
             let command = ScriptCommand::OpenBracket;
-            spawn_editor_item(&editor_assets, children, &command, color);
+            items.push(spawn_editor_item(
+                &editor_assets,
+                children,
+                &command,
+                color,
+                item_height,
+            ));
             total += 1;
         }
         for (i, command) in editor_state.entered.iter().enumerate() {
-            if i == editor_state.cursor && config.active.is_none() {
-                add_cursor(children, &editor_assets);
-            }
-
             let mut color = make_color(total);
             // when executing, gray out all non active commands
             if config.active == Some((i, true)) {
                 color = Color::linear_rgba(0.0, 0.5, 0.0, 1.0);
             } else if config.active == Some((i, false)) {
                 color = Color::linear_rgba(0.5, 0.5, 0.0, 1.0);
+            } else if config.active.is_some() && player_state.visited.contains(&i) {
+                // Leave a trail of the commands that ran so far.
+                color = Color::linear_rgba(0.0, 0.15, 0.0, 1.0);
+            } else if editor_state.failure == Some(i) {
+                color = Color::linear_rgba(0.6, 0.0, 0.0, 1.0);
+            } else if selection.contains(&i) {
+                color = Color::linear_rgba(0.1, 0.3, 0.8, 1.0);
             }
-            spawn_editor_item(&editor_assets, children, command, color);
+            items.push(spawn_editor_item(
+                &editor_assets,
+                children,
+                command,
+                color,
+                item_height,
+            ));
             total += 1;
         }
-        if editor_state.cursor == editor_state.entered.len() && config.active.is_none() {
-            add_cursor(children, &editor_assets);
-        }
         for _ in 0..close {
             let color = make_color(total).with_alpha(0.5);
             let command = ScriptCommand::CloseBracket;
-            spawn_editor_item(&editor_assets, children, &command, color);
+            items.push(spawn_editor_item(
+                &editor_assets,
+                children,
+                &command,
+                color,
+                item_height,
+            ));
             total += 1;
         }
-        for _ in total..level.command_count {
-            children.spawn((
+        // The overview only shows what is in the script.
+        let empty_slots = match compact {
+            true => 0..0,
+            false => total..level.command_count,
+        };
+        for _ in empty_slots {
+            let empty = children.spawn((
                 ImageBundle {
                     style: Style {
-                        height: Val::Percent(100.0),
+                        height: item_height,
                         ..default()
                     },
                     image: UiImage::new(editor_assets.icons.clone()),
@@ -277,26 +865,168 @@ fn show_script(
                 },
                 TextureAtlas {
                     layout: editor_assets.atlas.clone(),
-                    index: 8,
+                    index: EditorAssets::EMPTY_INDEX,
                 },
                 EditorItem,
             ));
+            items.push(empty.id());
         }
     });
+
+    // Keep the cursor, or the command being run, in view.
+    let len = editor_state.entered.len();
+    let focus = match config.active {
+        Some((index, _)) if index < len => open + index,
+        _ => open + editor_state.cursor,
+    };
+    let focus = focus.min(items.len().saturating_sub(1));
+    editor_state.scroll = match scroll_window {
+        Some((visible, _)) if items.len() > visible => {
+            scroll_offset(editor_state.scroll, focus, visible, items.len())
+        }
+        _ => 0,
+    };
+    match scroll_window {
+        Some((visible, item_size)) if items.len() > visible => {
+            style.width = Val::Px(visible as f32 * item_size);
+            style.overflow = Overflow::clip_x();
+            style.justify_content = JustifyContent::FlexStart;
+            // Shifting the first item shifts all others with it.
+            if let Some(&first) = items.first() {
+                commands.entity(first).insert(Style {
+                    height: item_height,
+                    margin: UiRect::left(Val::Px(-(editor_state.scroll as f32) * item_size)),
+                    ..default()
+                });
+            }
+        }
+        _ => {
+            style.width = Val::Auto;
+            style.overflow = Overflow::visible();
+            style.justify_content = JustifyContent::Center;
+        }
+    }
+
+    for (index, &item) in items.iter().enumerate() {
+        let slot = index.saturating_sub(open).min(len);
+        commands
+            .entity(item)
+            .insert((EditorSlot(slot), Interaction::default(), SilentInteraction));
+    }
+
+    let flashing = added_brackets
+        .as_ref()
+        .filter(|added| added.script == editor_state.entered)
+        .map_or(&[][..], |added| &added.indices);
+    for &index in flashing {
+        if let Some(&item) = items.get(index) {
+            commands.entity(item).with_children(|children| {
+                children.spawn((BracketFlash, NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::NONE),
+                    ..default()
+                }));
+            });
+        }
+    }
+
+    // Underline the extent of each loop, with nested loops further down.
+    if !compact {
+        let script = editor_state.balanced_script();
+        for scope in ScriptCommand::scopes(&script) {
+            let covered = items
+                .iter()
+                .enumerate()
+                .take(scope.end + 1)
+                .skip(scope.start);
+            for (index, &item) in covered {
+                commands.entity(item).with_children(|children| {
+                    add_scope_line(children, &scope, index);
+                });
+            }
+        }
+    }
+
+    // The cursor sits on the edge of an item so that it does not move the others.
+    if config.active.is_none() && !compact {
+        let index = open + editor_state.cursor;
+        let cursor = match items.get(index) {
+            Some(&item) => Some((item, CursorEdge::Left)),
+            None => items.last().map(|&item| (item, CursorEdge::Right)),
+        };
+        if let Some((item, edge)) = cursor {
+            commands.entity(item).with_children(|children| {
+                add_cursor(children, &editor_assets, edge);
+            });
+        }
+    }
 }
 
-fn add_cursor(children: &mut ChildBuilder, editor_assets: &Res<EditorAssets>) {
-    children.spawn((
-        ImageBundle {
-            style: Style {
-                height: Val::Percent(100.0),
-                ..default()
-            },
-            image: UiImage::new(editor_assets.cursor.clone()),
+fn add_scope_line(children: &mut ChildBuilder, scope: &Scope, index: usize) {
+    // The line starts and ends in the middle of the brackets.
+    let left = match index == scope.start {
+        true => 50.0,
+        false => 0.0,
+    };
+    let right = match index == scope.end {
+        true => 50.0,
+        false => 0.0,
+    };
+    children.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(left),
+            right: Val::Percent(right),
+            top: Val::Percent(105.0 + 15.0 * scope.depth as f32),
+            height: Val::Percent(8.0),
+            ..default()
+        },
+        background_color: BackgroundColor(Color::srgba(0.9, 0.5, 0.1, 0.8)),
+        ..default()
+    });
+}
+
+enum CursorEdge {
+    Left,
+    Right,
+}
+
+fn add_cursor(children: &mut ChildBuilder, editor_assets: &Res<EditorAssets>, edge: CursorEdge) {
+    // The cursor image is 5 by 16 pixels and items are 16 by 16 pixels.
+    const WIDTH: f32 = 100.0 * 5.0 / 16.0;
+    let left = match edge {
+        CursorEdge::Left => -WIDTH / 2.0,
+        CursorEdge::Right => 100.0 - WIDTH / 2.0,
+    };
+    children.spawn(ImageBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(left),
+            width: Val::Percent(WIDTH),
+            height: Val::Percent(100.0),
             ..default()
         },
-        EditorItem,
-    ));
+        image: UiImage::new(editor_assets.cursor.clone()),
+        ..default()
+    });
+}
+
+/// How strongly the category shows through the budget and execution colors.
+const CATEGORY_TINT: f32 = 0.35;
+
+fn category_tinted(color: Color, category: CommandCategory) -> Color {
+    let tint = match category {
+        CommandCategory::Movement => Color::srgb(0.2, 0.4, 0.8),
+        CommandCategory::ControlFlow => Color::srgb(0.9, 0.5, 0.1),
+        CommandCategory::Timing => Color::srgb(0.6, 0.3, 0.8),
+    };
+    let mixed = color.to_linear().mix(&tint.to_linear(), CATEGORY_TINT);
+    Color::from(mixed).with_alpha(color.alpha())
 }
 
 fn spawn_editor_item(
@@ -304,22 +1034,222 @@ fn spawn_editor_item(
     children: &mut ChildBuilder,
     command: &ScriptCommand,
     color: Color,
-) {
-    children.spawn((
-        ImageBundle {
-            style: Style {
-                height: Val::Percent(100.0),
+    height: Val,
+) -> Entity {
+    children
+        .spawn((
+            ImageBundle {
+                style: Style {
+                    height,
+                    ..default()
+                },
+                image: UiImage::new(editor_assets.icons.clone())
+                    .with_color(category_tinted(color, command.category())),
                 ..default()
             },
-            image: UiImage::new(editor_assets.icons.clone()).with_color(color),
-            ..default()
-        },
-        TextureAtlas {
-            layout: editor_assets.atlas.clone(),
-            index: EditorAssets::get_atlas_index(command),
-        },
-        EditorItem,
-    ));
+            TextureAtlas {
+                layout: editor_assets.atlas.clone(),
+                index: EditorAssets::get_atlas_index(command),
+            },
+            EditorItem,
+            CommandTooltip(*command),
+        ))
+        .with_children(|children| {
+            if let ScriptCommand::Repeat(count) = command {
+                children.spawn(
+                    TextBundle::from_section(count.to_string(), TextStyle {
+                        font_size: 16.0,
+                        color,
+                        ..default()
+                    })
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        right: Val::Px(2.0),
+                        bottom: Val::Px(0.0),
+                        ..default()
+                    }),
+                );
+            }
+        })
+        .id()
+}
+
+/// Highlight the cells the command at the editor cursor would move the player
+/// through when executed from the current position, or mark it as blocked.
+fn highlight_cursor_target(
+    editor_state: Res<EditorState>,
+    player_state: Res<PlayerState>,
+    player: Query<&GridTransform, With<Player>>,
+    level: Res<Level>,
+    grid: Res<WorldGrid>,
+    assets: Option<Res<PlayerAssets>>,
+    mut gizmos: Gizmos,
+) {
+    if !editor_state.enabled {
+        return;
+    }
+    let (Some(command), Ok(pos), Some(assets)) = (
+        editor_state.command_at_cursor(),
+        player.get_single(),
+        assets,
+    ) else {
+        return;
+    };
+    if matches!(
+        command,
+        ScriptCommand::OpenBracket
+            | ScriptCommand::Repeat(_)
+            | ScriptCommand::CloseBracket
+            | ScriptCommand::Mark
+            | ScriptCommand::Return
+            | ScriptCommand::Breakpoint
+    ) {
+        return;
+    }
+
+    let x_dir = player_state.x_dir;
+    let size = grid.cell_size();
+    match level.check_valid(pos.0, command, x_dir, &assets) {
+        Some(anim) => {
+            let path_color = Color::srgba(0.2, 0.8, 0.2, 0.4);
+            for square in &anim.squares {
+                let cell = pos.0 + *square * IVec2::new(x_dir, 1);
+                let center = grid.project_to_world(cell.as_vec2());
+                gizmos.rect_2d(center, 0.0, size * 0.8, path_color);
+            }
+            let target = grid.project_to_world((pos.0 + anim.final_offset(x_dir)).as_vec2());
+            gizmos.rect_2d(target, 0.0, size, Color::srgb(0.2, 0.9, 0.2));
+        }
+        None => {
+            // Mark the cell the simplest variant of the command would end up in.
+            let anim = command.get_resource(&assets)[0];
+            let target = grid.project_to_world((pos.0 + anim.final_offset(x_dir)).as_vec2());
+            let red = Color::srgb(0.9, 0.1, 0.1);
+            let half = size / 2.0;
+            gizmos.rect_2d(target, 0.0, size, red);
+            gizmos.line_2d(target - half, target + half, red);
+            gizmos.line_2d(
+                target + Vec2::new(-half.x, half.y),
+                target + Vec2::new(half.x, -half.y),
+                red,
+            );
+        }
+    }
+}
+
+/// A frame that slides to the executed item, so that the flow of the
+/// program is easy to follow.
+#[derive(Component)]
+struct ProgramCounter;
+
+/// Where the program counter is drawn and where it is heading.
+#[derive(Default)]
+struct ProgramCounterMotion {
+    /// Display index of the item it is moving to.
+    index: Option<usize>,
+    from: f32,
+    current: f32,
+}
+
+fn follow_program_counter(
+    editor_state: Res<EditorState>,
+    tick: Res<AnimationTick>,
+    settings: Res<Settings>,
+    editor_ui: Query<(Entity, &Children, &Node), With<EditorUI>>,
+    items: Query<(&Node, &Transform), With<EditorItem>>,
+    mut counter: Query<(&mut Style, &mut Visibility), With<ProgramCounter>>,
+    mut motion: Local<ProgramCounterMotion>,
+    mut commands: Commands,
+) {
+    let Ok((editor_ui, children, editor_node)) = editor_ui.get_single() else {
+        return;
+    };
+    let Ok((mut style, mut visibility)) = counter.get_single_mut() else {
+        commands.entity(editor_ui).with_children(|children| {
+            children.spawn((Name::new("Program Counter"), ProgramCounter, NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    border: UiRect::all(Val::Px(3.0)),
+                    ..default()
+                },
+                border_color: BorderColor(Color::srgb(0.1, 0.8, 0.1)),
+                z_index: ZIndex::Local(1),
+                visibility: Visibility::Hidden,
+                ..default()
+            }));
+        });
+        return;
+    };
+
+    // Nothing is executed before the first step of a run.
+    let open = calculate_open_required(&editor_state.entered);
+    let index = editor_state
+        .active
+        .map(|(index, _)| open + index)
+        .filter(|_| !editor_state.enabled);
+    let target = index.and_then(|index| {
+        children
+            .iter()
+            .filter_map(|&child| items.get(child).ok())
+            .nth(index)
+    });
+    let Some((node, transform)) = target else {
+        *visibility = Visibility::Hidden;
+        motion.index = None;
+        return;
+    };
+
+    // Node translations are relative to the center of the parent.
+    let size = node.size();
+    let x = transform.translation.x + (editor_node.size().x - size.x) / 2.0;
+    if motion.index != index {
+        // Jump straight to the first item of a run.
+        motion.from = match motion.index {
+            Some(_) => motion.current,
+            None => x,
+        };
+        motion.index = index;
+    }
+    motion.current = match settings.reduce_motion {
+        true => x,
+        false => {
+            let t = tick.0.fraction();
+            let eased = t * t * (3.0 - 2.0 * t);
+            motion.from.lerp(x, eased)
+        }
+    };
+
+    *visibility = Visibility::Inherited;
+    style.left = Val::Px(motion.current);
+    style.width = Val::Px(size.x);
+    style.height = Val::Px(size.y);
+}
+
+/// Outline the grid cell under the mouse, to help reading the level.
+fn highlight_hovered_cell(
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<IsDefaultUiCamera>>,
+    grid: Res<WorldGrid>,
+    mut gizmos: Gizmos,
+) {
+    let (Ok(window), Ok((camera, camera_transform))) = (window.get_single(), camera.get_single())
+    else {
+        return;
+    };
+    let Some(world) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+    else {
+        return;
+    };
+    let cell = grid.world_to_grid(world);
+    let center = grid.project_to_world(cell.as_vec2());
+    gizmos.rect_2d(
+        center,
+        0.0,
+        grid.cell_size(),
+        Color::srgba(1.0, 1.0, 1.0, 0.3),
+    );
 }
 
 fn calculate_open_required(script: &[ScriptCommand]) -> usize {
@@ -327,7 +1257,7 @@ fn calculate_open_required(script: &[ScriptCommand]) -> usize {
     for command in script.iter().rev() {
         match command {
             ScriptCommand::CloseBracket => balance += 1,
-            ScriptCommand::OpenBracket => balance = balance.saturating_sub(1),
+            command if command.opens_scope() => balance = balance.saturating_sub(1),
             _ => {}
         }
     }
@@ -338,7 +1268,7 @@ fn calculate_close_required(script: &[ScriptCommand]) -> usize {
     let mut balance = 0usize;
     for command in script {
         match command {
-            ScriptCommand::OpenBracket => balance += 1,
+            command if command.opens_scope() => balance += 1,
             ScriptCommand::CloseBracket => balance = balance.saturating_sub(1),
             _ => {}
         }
@@ -348,12 +1278,15 @@ fn calculate_close_required(script: &[ScriptCommand]) -> usize {
 
 fn submit_script(
     input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    selection: Res<LevelSelection>,
+    settings: Res<Settings>,
     mut editor_state: ResMut<EditorState>,
     mut player_state: ResMut<PlayerState>,
     mut commands: Commands,
     level: Res<Level>,
 ) {
-    if !input.just_pressed(KeyCode::Enter) {
+    if !input.just_pressed(bindings.key(KeyAction::Submit)) {
         return;
     }
 
@@ -361,16 +1294,29 @@ fn submit_script(
         return;
     }
 
+    let submitted = editor_state.entered.clone();
+
     // Fix sequence (brackets-wise)
-    let open = calculate_open_required(&editor_state.entered);
-    let close = calculate_close_required(&editor_state.entered);
-    let new_sequence: Vec<_> = (0..open)
-        .map(|_| ScriptCommand::OpenBracket)
-        .chain(editor_state.entered.drain(..))
-        .chain((0..close).map(|_| ScriptCommand::CloseBracket))
-        .collect();
+    let (new_sequence, added) = editor_state.balance();
+    if !added.is_empty() && !settings.balance_brackets {
+        editor_state.unbalanced = true;
+        return;
+    }
+    // Undo restores the script as it was typed.
+    if new_sequence != submitted {
+        let cursor = editor_state.cursor;
+        editor_state.push_history(submitted.clone(), cursor);
+    }
     editor_state.entered.clone_from(&new_sequence);
     editor_state.cursor = new_sequence.len();
+    editor_state.anchor = None;
+    if !added.is_empty() {
+        commands.insert_resource(AddedBrackets {
+            script: new_sequence.clone(),
+            indices: added,
+            timer: Timer::from_seconds(AddedBrackets::SECONDS, TimerMode::Once),
+        });
+    }
     // Send event to update the editor view.
     commands.add(ShowEditor::default());
 
@@ -383,6 +1329,425 @@ fn submit_script(
         active: Some((usize::MAX, true)),
     });
     editor_state.enabled = false;
-    player_state.sequence = new_sequence;
+    save_submitted(level_index(&selection), &submitted);
+    editor_state.submitted = submitted;
+    player_state.sequence.clone_from(&new_sequence);
     player_state.cursor = 0;
+    player_state.autoplay = settings.on_submit == SubmitMode::Autoplay;
+    commands.add(RecordReplay {
+        sequence: new_sequence,
+        preview: false,
+    });
+}
+
+fn flash_added_brackets(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut added: ResMut<AddedBrackets>,
+    mut flashes: Query<&mut BackgroundColor, With<BracketFlash>>,
+    mut label: Query<&mut Text, With<BracketNoticeLabel>>,
+    mut commands: Commands,
+) {
+    let fraction = added.timer.fraction();
+    let text = if added.timer.tick(time.delta()).finished() {
+        commands.remove_resource::<AddedBrackets>();
+        String::new()
+    } else {
+        let open = added
+            .indices
+            .iter()
+            .filter(|&&index| added.script[index] == ScriptCommand::OpenBracket)
+            .count();
+        bracket_notice(open, added.indices.len() - open)
+    };
+    for mut label in &mut label {
+        if label.sections[0].value != text {
+            label.sections[0].value = text.clone();
+        }
+    }
+
+    // Pulse a few times while fading out, or just fade out.
+    let pulse = match settings.reduce_motion {
+        true => 1.0,
+        false => 0.5 - 0.5 * (fraction * AddedBrackets::FLASHES * std::f32::consts::TAU).cos(),
+    };
+    let alpha = 0.8 * (1.0 - fraction) * pulse;
+    for mut color in &mut flashes {
+        color.0 = Color::srgba(1.0, 1.0, 0.6, alpha);
+    }
+}
+
+fn show_bracket_warning(
+    editor_state: Res<EditorState>,
+    mut label: Query<&mut Text, With<BracketNoticeLabel>>,
+    mut shown: Local<bool>,
+) {
+    if *shown == editor_state.unbalanced {
+        return;
+    }
+    *shown = editor_state.unbalanced;
+    let text = match editor_state.unbalanced {
+        true => "Unbalanced brackets: every [ needs a matching ]",
+        false => "",
+    };
+    for mut label in &mut label {
+        label.sections[0].value = text.to_string();
+    }
+}
+
+/// E.g. "Added 1 closing bracket".
+fn bracket_notice(open: usize, close: usize) -> String {
+    let count = |n: usize, kind: &str| match n {
+        1 => format!("1 {kind} bracket"),
+        n => format!("{n} {kind} brackets"),
+    };
+    match (open, close) {
+        (0, close) => format!("Added {}", count(close, "closing")),
+        (open, 0) => format!("Added {}", count(open, "opening")),
+        (open, close) => format!(
+            "Added {} and {}",
+            count(open, "opening"),
+            count(close, "closing")
+        ),
+    }
+}
+
+/// Shows the description of the command when hovering the icon.
+#[derive(Component, Debug)]
+pub struct CommandTooltip(pub ScriptCommand);
+
+/// The tooltip shown next to the mouse.
+#[derive(Component)]
+struct Tooltip;
+
+fn show_command_tooltip(
+    hovered: Query<(&Interaction, &CommandTooltip)>,
+    mut tooltip: Query<(Entity, &mut Style, &Children), With<Tooltip>>,
+    mut text: Query<&mut Text>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut commands: Commands,
+) {
+    let command = hovered
+        .iter()
+        .find(|(interaction, _)| **interaction != Interaction::None)
+        .map(|(_, tooltip)| tooltip.0);
+    let cursor = window.get_single().ok().and_then(Window::cursor_position);
+    let (Some(command), Some(cursor)) = (command, cursor) else {
+        for (entity, ..) in &tooltip {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+
+    // Below and to the right of the mouse, like tooltips elsewhere.
+    let left = Val::Px(cursor.x + 16.0);
+    let top = Val::Px(cursor.y + 16.0);
+    if let Ok((_, mut style, children)) = tooltip.get_single_mut() {
+        style.left = left;
+        style.top = top;
+        if let Some(mut text) = children.first().and_then(|&child| text.get_mut(child).ok()) {
+            if text.sections[0].value != command.description() {
+                text.sections[0].value = command.description().to_string();
+            }
+        }
+        return;
+    }
+    commands
+        .spawn((
+            Name::new("Tooltip"),
+            Tooltip,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left,
+                    top,
+                    padding: UiRect::all(Val::Px(6.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(NODE_BACKGROUND),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            StateScoped(Screen::Gameplay),
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section(command.description(), TextStyle {
+                font_size: 20.0,
+                ..default()
+            }));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo::testing::{press_keys, type_keys};
+
+    #[test]
+    fn j_types_jump_once_unlocked() {
+        let mut level = Level::default();
+        level.unlocked.push(ScriptCommand::Jump);
+        let script = type_keys(&mut World::new(), level, &[KeyCode::KeyW, KeyCode::KeyJ]);
+        assert_eq!(script, [ScriptCommand::Walk, ScriptCommand::Jump]);
+    }
+
+    #[test]
+    fn j_is_ignored_while_jump_is_locked() {
+        let level = Level::default();
+        let script = type_keys(&mut World::new(), level, &[KeyCode::KeyW, KeyCode::KeyJ]);
+        assert_eq!(script, [ScriptCommand::Walk]);
+    }
+
+    #[test]
+    fn keys_pressed_together_insert_in_press_order() {
+        let mut level = Level::default();
+        level.unlocked.push(ScriptCommand::Idle);
+        let script = type_keys(&mut World::new(), level.clone(), &[
+            KeyCode::KeyI,
+            KeyCode::KeyW,
+        ]);
+        assert_eq!(script, [ScriptCommand::Idle, ScriptCommand::Walk]);
+        let script = type_keys(&mut World::new(), level, &[KeyCode::KeyW, KeyCode::KeyI]);
+        assert_eq!(script, [ScriptCommand::Walk, ScriptCommand::Idle]);
+    }
+
+    #[test]
+    fn undo_and_redo_restore_script_and_cursor() {
+        let mut editor = EditorState::default();
+        editor.push_history(Vec::new(), 0);
+        editor.set_script(vec![ScriptCommand::Walk, ScriptCommand::Jump]);
+
+        assert!(editor.undo());
+        assert!(editor.script().is_empty());
+        assert_eq!(editor.cursor, 0);
+        assert!(!editor.undo());
+
+        assert!(editor.redo());
+        assert_eq!(editor.script(), [ScriptCommand::Walk, ScriptCommand::Jump]);
+        assert_eq!(editor.cursor, 2);
+        assert!(!editor.redo());
+    }
+
+    /// A world with `script` in the editor, where a loop was copied from it.
+    fn copy_loop(script: &[ScriptCommand], selection: Range<usize>) -> World {
+        let mut level = Level::default();
+        level
+            .unlocked
+            .extend([ScriptCommand::OpenBracket, ScriptCommand::Idle]);
+        let mut world = World::new();
+        type_keys(&mut world, level, &[]);
+        let mut editor = world.resource_mut::<EditorState>();
+        editor.set_script(script.to_vec());
+        editor.anchor = Some(selection.start);
+        editor.cursor = selection.end;
+        press_keys(&mut world, &[KeyCode::ControlLeft, KeyCode::KeyC]);
+        world
+    }
+
+    #[test]
+    fn paste_keeps_copied_loop_in_order() {
+        use ScriptCommand::*;
+        let script = [Walk, OpenBracket, Idle, Walk, CloseBracket, Idle];
+        let mut world = copy_loop(&script, 1..5);
+        let mut editor = world.resource_mut::<EditorState>();
+        editor.anchor = None;
+        editor.cursor = script.len();
+
+        let pasted = press_keys(&mut world, &[KeyCode::ControlLeft, KeyCode::KeyV]);
+        assert_eq!(pasted, [
+            Walk,
+            OpenBracket,
+            Idle,
+            Walk,
+            CloseBracket,
+            Idle,
+            OpenBracket,
+            Idle,
+            Walk,
+            CloseBracket
+        ]);
+        assert_eq!(world.resource::<EditorState>().cursor, pasted.len());
+    }
+
+    #[test]
+    fn paste_inside_a_loop_nests_the_copy() {
+        use ScriptCommand::*;
+        let script = [OpenBracket, Walk, Idle, CloseBracket];
+        let mut world = copy_loop(&script, 0..4);
+        let mut editor = world.resource_mut::<EditorState>();
+        editor.anchor = None;
+        editor.cursor = 2;
+
+        let pasted = press_keys(&mut world, &[KeyCode::ControlLeft, KeyCode::KeyV]);
+        assert_eq!(pasted, [
+            OpenBracket,
+            Walk,
+            OpenBracket,
+            Walk,
+            Idle,
+            CloseBracket,
+            Idle,
+            CloseBracket
+        ]);
+        assert_eq!(world.resource::<EditorState>().cursor, 6);
+    }
+
+    /// Press the editor item at `index` from the left, and return where the
+    /// cursor went. Clicking redraws the editor.
+    fn click(world: &mut World, index: usize) -> usize {
+        let mut editor = world.query_filtered::<&Children, With<EditorUI>>();
+        let item = editor.single(world)[index];
+        let mut items = world.query::<(Entity, &mut Interaction)>();
+        for (entity, mut interaction) in items.iter_mut(world) {
+            *interaction = match entity == item {
+                true => Interaction::Pressed,
+                false => Interaction::None,
+            };
+        }
+        world.run_system_once(click_editor_item);
+        world.resource::<EditorState>().cursor
+    }
+
+    #[test]
+    fn clicking_an_item_moves_the_cursor_in_front_of_it() {
+        // Room for two more commands, shown as empty slots.
+        let mut level = Level::default();
+        level.command_count = 5;
+        let mut world = World::new();
+        type_keys(&mut world, level, &[]);
+        let script = vec![ScriptCommand::Walk; 3];
+        world.resource_mut::<EditorState>().set_script(script);
+        ShowEditor::default().apply(&mut world);
+
+        assert_eq!(click(&mut world, 1), 1);
+        assert_eq!(click(&mut world, 0), 0);
+        // The empty slots after the script move the cursor to its end.
+        assert_eq!(click(&mut world, 4), 3);
+        assert_eq!(click(&mut world, 2), 2);
+        assert_eq!(click(&mut world, 3), 3);
+    }
+
+    #[test]
+    fn used_count_includes_the_brackets_submitting_adds() {
+        use ScriptCommand::{CloseBracket as Close, OpenBracket as Open, Walk};
+        let mut level = Level::default();
+        level.command_count = 5;
+        let mut world = World::new();
+        type_keys(&mut world, level, &[]);
+        let label = world
+            .spawn((Text::from_section("", default()), ScriptLengthLabel))
+            .id();
+        let mut used = |script: Vec<ScriptCommand>| {
+            world.resource_mut::<EditorState>().set_script(script);
+            ShowEditor::default().apply(&mut world);
+            let text = &world.get::<Text>(label).unwrap().sections[0].value;
+            text.split_once(" / ").unwrap().0.to_string()
+        };
+
+        assert_eq!(used(vec![Open, Walk, Close, Walk]), "4");
+        // The missing closing bracket counts.
+        assert_eq!(used(vec![Open, Walk, Close, Open, Walk]), "6");
+        // And so do missing opening brackets.
+        assert_eq!(used(vec![Walk, Close, Close]), "5");
+    }
+
+    /// Press `key` on its own and run `submit_script`.
+    fn submit_with(world: &mut World, key: KeyCode) {
+        let mut input = world.resource_mut::<ButtonInput<KeyCode>>();
+        input.release_all();
+        input.clear();
+        input.press(key);
+        world.run_system_once(submit_script);
+    }
+
+    #[test]
+    fn rebinding_submit_changes_its_key() {
+        let mut world = World::new();
+        type_keys(&mut world, Level::default(), &[KeyCode::KeyW]);
+        let bindings = world.resource::<KeyBindings>();
+        let old = bindings.key(KeyAction::Submit);
+        assert_eq!(old, KeyCode::Enter);
+        world
+            .resource_mut::<KeyBindings>()
+            .rebind(KeyAction::Submit, KeyCode::Space);
+
+        submit_with(&mut world, KeyCode::Space);
+        let sequence = &world.resource::<PlayerState>().sequence;
+        assert_eq!(sequence, &[ScriptCommand::Walk]);
+
+        // Back to editing, the old key no longer submits.
+        world.resource_mut::<PlayerState>().sequence.clear();
+        world.resource_mut::<EditorState>().enabled = true;
+        submit_with(&mut world, old);
+        assert!(world.resource::<PlayerState>().sequence.is_empty());
+        assert!(world.resource::<EditorState>().enabled);
+    }
+
+    #[test]
+    fn scrolling_keeps_the_cursor_in_view() {
+        let (visible, total) = (5, 20);
+        for first in 0..=total - visible {
+            for focus in 0..total {
+                let offset = scroll_offset(first, focus, visible, total);
+                assert!((offset..offset + visible).contains(&focus));
+                assert!(offset + visible <= total);
+            }
+        }
+        // Only scrolls once the cursor leaves the view.
+        assert_eq!(scroll_offset(3, 5, visible, total), 3);
+        assert_eq!(scroll_offset(3, 8, visible, total), 4);
+        assert_eq!(scroll_offset(3, 2, visible, total), 2);
+    }
+
+    #[test]
+    fn scrolling_snaps_to_the_start_and_the_end() {
+        let (visible, total) = (5, 20);
+        assert_eq!(scroll_offset(10, 0, visible, total), 0);
+        assert_eq!(scroll_offset(3, total - 1, visible, total), total - visible);
+        // Nothing scrolls when everything fits.
+        assert_eq!(scroll_offset(0, 3, visible, 4), 0);
+    }
+
+    #[test]
+    fn unbalanced_script_is_not_submitted() {
+        let mut level = Level::default();
+        level.unlocked.push(ScriptCommand::OpenBracket);
+        level.command_count = 5;
+        let mut world = World::new();
+        type_keys(&mut world, level, &[]);
+        let script = vec![ScriptCommand::OpenBracket, ScriptCommand::Walk];
+        world
+            .resource_mut::<EditorState>()
+            .set_script(script.clone());
+
+        submit_with(&mut world, KeyCode::Enter);
+        assert!(world.resource::<PlayerState>().sequence.is_empty());
+        let editor = world.resource::<EditorState>();
+        assert!(editor.unbalanced);
+        assert!(editor.enabled);
+        assert_eq!(editor.script(), script);
+
+        // Unless brackets are balanced automatically.
+        world.resource_mut::<Settings>().balance_brackets = true;
+        submit_with(&mut world, KeyCode::Enter);
+        assert_eq!(world.resource::<PlayerState>().sequence, [
+            ScriptCommand::OpenBracket,
+            ScriptCommand::Walk,
+            ScriptCommand::CloseBracket
+        ]);
+    }
+
+    #[test]
+    fn change_after_undo_forgets_redo() {
+        let mut editor = EditorState::default();
+        editor.push_history(Vec::new(), 0);
+        editor.set_script(vec![ScriptCommand::Walk]);
+        assert!(editor.undo());
+
+        editor.push_history(Vec::new(), 0);
+        editor.set_script(vec![ScriptCommand::Idle]);
+        assert!(!editor.redo());
+        assert!(editor.undo());
+        assert!(editor.script().is_empty());
+    }
 }
@@ -11,8 +11,23 @@ use bevy_ecs_ldtk::prelude::*;
 use super::{animation::PlayerAssets, obstacle::Obstacle, player::Player};
 use crate::{
     asset_tracking::LoadResource,
-    demo::{action::ScriptCommand, obstacle::SpawnObstacle},
+    demo::{
+        action::{Facing, Parity, ScriptCommand, DEFAULT_REPEAT},
+        dialogue::{SeenTutorials, ShowDialogue},
+        editor::{EditorState, ShowEditor},
+        obstacle::{
+            HazardAnimation,
+            HazardKind,
+            HazardTiming,
+            MovementPattern,
+            SpawnObstacle,
+            HAZARD_FRAMES,
+        },
+        solutions::load_submitted,
+    },
+    modding,
     screens::Screen,
+    settings::Settings,
     AppSet,
 };
 
@@ -24,22 +39,68 @@ pub(super) fn plugin(app: &mut App) {
     });
     app.load_resource::<LevelAssets>();
     app.insert_resource(LevelSelection::index(0));
-    app.register_ldtk_entity::<PlayerStartBundle>("PlayerStart");
-    app.register_ldtk_entity::<CheckpointBundle>("Checkpoint");
-    app.register_ldtk_entity::<HazardBundle>("Hazard");
-    app.register_ldtk_int_cell::<WallBundle>(1);
-    app.add_systems(Update, load_level.run_if(in_state(Screen::Gameplay)));
+    register_ldtk_types(app);
+    app.add_systems(
+        Update,
+        (
+            despawn_previous_level.before(load_level),
+            load_level,
+            mirror_level_tiles,
+            tint_markers,
+            (show_collectibles, show_keys_and_doors).run_if(resource_changed::<Level>),
+            clear_script_for_new_level,
+        )
+            .run_if(in_state(Screen::Gameplay)),
+    );
 
-    app.insert_resource(WorldGrid {
-        origin: Vec2::splat(8.),
-        size: Vec2::splat(16.),
-    });
+    app.init_resource::<WorldGrid>();
     app.init_resource::<Level>();
     app.insert_resource(AnimationTick(Timer::from_seconds(0.2, TimerMode::Once)));
 
+    app.init_resource::<TickCount>();
+
     app.add_event::<TickStart>();
     app.add_event::<Reset>();
+    app.add_event::<LevelComplete>();
+    app.add_event::<Rewind>();
+    app.add_systems(
+        Update,
+        (|mut ticks: ResMut<TickCount>| ticks.0 = 0).run_if(on_event::<Reset>()),
+    );
     app.add_systems(Update, update_tick_timer.in_set(AppSet::TickTimers));
+
+    // Dump the logical layout of the level, e.g. for bug reports.
+    #[cfg(feature = "dev")]
+    app.add_systems(
+        Update,
+        log_level_ascii.run_if(in_state(Screen::Gameplay).and_then(
+            bevy::input::common_conditions::input_just_pressed(ASCII_DUMP_KEY),
+        )),
+    );
+}
+
+/// How the entities and IntGrid values of the map are spawned.
+fn register_ldtk_types(app: &mut App) {
+    app.register_ldtk_entity::<PlayerStartBundle>("PlayerStart");
+    app.register_ldtk_entity::<CheckpointBundle>("Checkpoint");
+    app.register_ldtk_entity::<HazardBundle>("Hazard");
+    app.register_ldtk_entity::<GoalBundle>("Goal");
+    app.register_ldtk_entity::<TeleporterBundle>("Teleporter");
+    app.register_ldtk_entity::<CollectibleBundle>("Collectible");
+    app.register_ldtk_entity::<KeyBundle>("Key");
+    app.register_ldtk_entity::<DoorBundle>("Door");
+    app.register_ldtk_int_cell::<WallBundle>(1);
+    app.register_ldtk_int_cell::<OneWayBundle>(OneWay::BLOCK_RIGHT);
+    app.register_ldtk_int_cell::<OneWayBundle>(OneWay::BLOCK_LEFT);
+}
+
+#[cfg(feature = "dev")]
+const ASCII_DUMP_KEY: KeyCode = KeyCode::F7;
+
+#[cfg(feature = "dev")]
+fn log_level_ascii(level: Res<Level>, obstacles: Query<&GridTransform, With<Obstacle>>) {
+    let hazards: Vec<_> = obstacles.iter().map(|pos| pos.0).collect();
+    log::info!("Level layout:\n{}", level.to_ascii(&hazards));
 }
 
 #[derive(Default, Bundle, LdtkEntity)]
@@ -91,24 +152,158 @@ impl Unlock {
                 .get_maybe_enum_field("Unlock")
                 .expect("expected entity to have nullable `Unlock` enum field")
                 .as_ref()
-                .map(|field| match field.as_ref() {
-                    "Walk" => ScriptCommand::Walk,
-                    "Climb" => ScriptCommand::Climb,
-                    "Idle" => ScriptCommand::Idle,
-                    "Jump" => ScriptCommand::Jump,
-                    "Drop" => ScriptCommand::Drop,
-                    "Turn" => ScriptCommand::Turn,
-                    "Brackets" => ScriptCommand::OpenBracket,
-                    x => panic!("unexpected `Unlock` enum variant: {x}"),
+                .map(|field| {
+                    unlock_from_name(field)
+                        .unwrap_or_else(|| panic!("unexpected `Unlock` enum variant: {field}"))
                 }),
         )
     }
 }
 
+/// The command unlocked by a value of the LDtk `Unlock` enum.
+fn unlock_from_name(name: &str) -> Option<ScriptCommand> {
+    Some(match name {
+        "Walk" => ScriptCommand::Walk,
+        "Climb" => ScriptCommand::Climb,
+        "Idle" => ScriptCommand::Idle,
+        "Jump" => ScriptCommand::Jump,
+        "Drop" => ScriptCommand::Drop,
+        "Turn" => ScriptCommand::Turn,
+        "Fall" => ScriptCommand::Fall,
+        "Face" => ScriptCommand::Face(Facing::Right),
+        "Sync" => ScriptCommand::Sync(Parity::Even),
+        "Mark" => ScriptCommand::Mark,
+        "Breakpoint" => ScriptCommand::Breakpoint,
+        "Brackets" => ScriptCommand::OpenBracket,
+        "Repeat" => ScriptCommand::Repeat(DEFAULT_REPEAT),
+        _ => return None,
+    })
+}
+
 #[derive(Component, Reflect, Debug, Default)]
 #[reflect(Component)]
 struct Checkpoint;
 
+/// Reaching any goal completes the level. It uses the checkpoint sprite, tinted
+/// in [`tint_markers`].
+#[derive(Default, Bundle, LdtkEntity)]
+struct GoalBundle {
+    goal: Goal,
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[sprite_sheet_bundle]
+    sprite_sheet_bundle: LdtkSpriteSheetBundle,
+}
+
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+struct Goal;
+
+const GOAL_COLOR: Color = Color::srgb(1.0, 0.85, 0.2);
+
+/// Moving onto a teleporter moves the player on to its destination. It uses
+/// the checkpoint sprite, tinted in [`tint_markers`].
+#[derive(Default, Bundle, LdtkEntity)]
+struct TeleporterBundle {
+    teleporter: Teleporter,
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[with(Destination::from_field)]
+    destination: Destination,
+    #[sprite_sheet_bundle]
+    sprite_sheet_bundle: LdtkSpriteSheetBundle,
+}
+
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+struct Teleporter;
+
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+struct Destination(IVec2);
+
+impl Destination {
+    fn from_field(entity_instance: &EntityInstance) -> Self {
+        Self(
+            *entity_instance
+                .get_point_field("Destination")
+                .expect("expected entity to have non-nullable `Destination` point field"),
+        )
+    }
+}
+
+const TELEPORTER_COLOR: Color = Color::srgb(0.7, 0.4, 1.0);
+
+/// Goals only complete the level once every collectible was picked up in the
+/// same run. It uses the checkpoint sprite, tinted in [`tint_markers`].
+#[derive(Default, Bundle, LdtkEntity)]
+struct CollectibleBundle {
+    collectible: Collectible,
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[sprite_sheet_bundle]
+    sprite_sheet_bundle: LdtkSpriteSheetBundle,
+}
+
+/// The position of the collectible, which is set in `load_level` because it
+/// differs from the `GridCoords` in mirrored levels.
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+struct Collectible(IVec2);
+
+const COLLECTIBLE_COLOR: Color = Color::srgb(0.3, 0.9, 0.4);
+
+/// Picking up a key opens every door with the same id until respawning. Both
+/// use the checkpoint sprite, tinted in [`tint_markers`].
+#[derive(Default, Bundle, LdtkEntity)]
+struct KeyBundle {
+    key: Key,
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[with(LockId::from_field)]
+    id: LockId,
+    #[sprite_sheet_bundle]
+    sprite_sheet_bundle: LdtkSpriteSheetBundle,
+}
+
+/// A door is solid until a key with the same id was picked up.
+#[derive(Default, Bundle, LdtkEntity)]
+struct DoorBundle {
+    door: Door,
+    #[grid_coords]
+    grid_coords: GridCoords,
+    #[with(LockId::from_field)]
+    id: LockId,
+    #[sprite_sheet_bundle]
+    sprite_sheet_bundle: LdtkSpriteSheetBundle,
+}
+
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+struct Key;
+
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+struct Door;
+
+/// Matches keys to the doors they open.
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+struct LockId(i32);
+
+impl LockId {
+    fn from_field(entity_instance: &EntityInstance) -> Self {
+        Self(
+            *entity_instance
+                .get_int_field("Id")
+                .expect("expected entity to have non-nullable `Id` int field"),
+        )
+    }
+}
+
+const KEY_COLOR: Color = Color::srgb(1.0, 0.6, 0.2);
+const DOOR_COLOR: Color = Color::srgb(0.45, 0.3, 0.2);
+
 #[derive(Default, Bundle, LdtkEntity)]
 struct HazardBundle {
     hazard: Hazard,
@@ -116,6 +311,14 @@ struct HazardBundle {
     grid_coords: GridCoords,
     #[with(MoveTo::from_field)]
     move_to: MoveTo,
+    #[with(Kind::from_field)]
+    kind: Kind,
+    #[with(Animation::from_field)]
+    animation: Animation,
+    #[with(Movement::from_field)]
+    movement: Movement,
+    #[with(Timing::from_field)]
+    timing: Timing,
 }
 
 #[derive(Component, Reflect, Debug, Default)]
@@ -136,6 +339,95 @@ impl MoveTo {
     }
 }
 
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+struct Kind(HazardKind);
+
+impl Kind {
+    fn from_field(entity_instance: &EntityInstance) -> Self {
+        // Hazards without a `Kind` field are spikes.
+        let Ok(Some(field)) = entity_instance.get_maybe_enum_field("Kind") else {
+            return Self::default();
+        };
+        Self(match field.as_ref() {
+            "Spikes" => HazardKind::Spikes,
+            "Crusher" => HazardKind::Crusher,
+            "Enemy" => HazardKind::Enemy,
+            "Decoration" => HazardKind::Decoration,
+            "Platform" => HazardKind::Platform,
+            x => panic!("unexpected `Kind` enum variant: {x}"),
+        })
+    }
+}
+
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+struct Animation(HazardAnimation);
+
+impl Animation {
+    fn from_field(entity_instance: &EntityInstance) -> Self {
+        // Both fields are optional and default to the full sheet once per tick.
+        let mut animation = HazardAnimation::default();
+        if let Ok(Some(frames)) = entity_instance.get_maybe_int_field("Frames") {
+            animation.frames = (*frames).clamp(1, HAZARD_FRAMES as i32) as usize;
+        }
+        if let Ok(Some(speed)) = entity_instance.get_maybe_float_field("AnimationSpeed") {
+            animation.speed = speed.max(0.0);
+        }
+        Self(animation)
+    }
+}
+
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+struct Timing(HazardTiming);
+
+impl Timing {
+    fn from_field(entity_instance: &EntityInstance) -> Self {
+        // Both fields are optional and default to being active on every tick.
+        let mut timing = HazardTiming::default();
+        if let Ok(Some(period)) = entity_instance.get_maybe_int_field("Period") {
+            timing.period = (*period).max(1) as usize;
+        }
+        if let Ok(Some(phase)) = entity_instance.get_maybe_int_field("ActivePhase") {
+            timing.active_phase = (*phase).max(0) as usize;
+        }
+        Self(timing)
+    }
+}
+
+/// How a hazard moves relative to its `MoveTo` field. The points are in LDtk
+/// coordinates until the level is loaded.
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+enum Movement {
+    #[default]
+    PingPong,
+    Wrap,
+    Waypoints(Vec<IVec2>),
+}
+
+impl Movement {
+    fn from_field(entity_instance: &EntityInstance) -> Self {
+        // Hazards without a `Movement` field move back and forth.
+        let Ok(Some(field)) = entity_instance.get_maybe_enum_field("Movement") else {
+            return Self::default();
+        };
+        match field.as_ref() {
+            "PingPong" => Self::PingPong,
+            "Wrap" => Self::Wrap,
+            "Waypoints" => Self::Waypoints(
+                entity_instance
+                    .iter_points_field("Waypoints")
+                    .expect("expected waypoint hazard to have non-null `Waypoints` points field")
+                    .copied()
+                    .collect(),
+            ),
+            x => panic!("unexpected `Movement` enum variant: {x}"),
+        }
+    }
+}
+
 #[derive(Default, Bundle, LdtkIntCell)]
 struct WallBundle {
     wall: Wall,
@@ -145,6 +437,74 @@ struct WallBundle {
 #[reflect(Component)]
 struct Wall;
 
+#[derive(Default, Bundle, LdtkIntCell)]
+struct OneWayBundle {
+    #[from_int_grid_cell]
+    one_way: OneWay,
+}
+
+/// A wall that only blocks movement in some directions. Unknown values are
+/// `None` and treated as normal walls.
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+struct OneWay(Option<DirMask>);
+
+impl OneWay {
+    const BLOCK_LEFT: i32 = 3;
+    /// LDtk int cell values of one-way walls.
+    const BLOCK_RIGHT: i32 = 2;
+}
+
+impl From<IntGridCell> for OneWay {
+    fn from(cell: IntGridCell) -> Self {
+        Self(match cell.value {
+            Self::BLOCK_RIGHT => Some(DirMask::RIGHT),
+            Self::BLOCK_LEFT => Some(DirMask::LEFT),
+            value => {
+                log::error!("Unexpected one-way wall value {value}, using a wall instead");
+                None
+            }
+        })
+    }
+}
+
+/// The directions of movement that a one-way wall blocks.
+#[derive(Reflect, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DirMask(u8);
+
+impl DirMask {
+    pub const DOWN: Self = Self(8);
+    pub const LEFT: Self = Self(2);
+    pub const RIGHT: Self = Self(1);
+    pub const UP: Self = Self(4);
+
+    fn contains(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Whether moving in `dir` into the wall is blocked. Diagonal moves are
+    /// blocked if either part is.
+    pub fn blocks(self, dir: IVec2) -> bool {
+        (dir.x > 0 && self.contains(Self::RIGHT))
+            || (dir.x < 0 && self.contains(Self::LEFT))
+            || (dir.y > 0 && self.contains(Self::UP))
+            || (dir.y < 0 && self.contains(Self::DOWN))
+    }
+
+    /// The mask of the wall in a mirrored level.
+    fn mirrored(self) -> Self {
+        let vertical = self.0 & (Self::UP.0 | Self::DOWN.0);
+        let mut mirrored = Self(vertical);
+        if self.contains(Self::RIGHT) {
+            mirrored.0 |= Self::LEFT.0;
+        }
+        if self.contains(Self::LEFT) {
+            mirrored.0 |= Self::RIGHT.0;
+        }
+        mirrored
+    }
+}
+
 #[derive(Resource, Asset, Reflect, Clone)]
 pub struct LevelAssets {
     #[dependency]
@@ -159,20 +519,30 @@ impl FromWorld for LevelAssets {
     fn from_world(world: &mut World) -> Self {
         let assets = world.resource::<AssetServer>();
         Self {
-            ldtk_project: assets.load(LevelAssets::PATH_LDTK),
+            // Tilesets of a modded map are loaded from the mods folder too.
+            ldtk_project: assets.load(modding::resolve(LevelAssets::PATH_LDTK)),
         }
     }
 }
 
 pub fn spawn_level(world: &mut World) {
     world.run_system_once(|mut commands: Commands, level_assets: Res<LevelAssets>| {
-        commands.spawn(LdtkWorldBundle {
-            ldtk_handle: level_assets.ldtk_project.clone(),
-            ..Default::default()
-        });
+        commands.spawn((
+            LdtkWorldBundle {
+                ldtk_handle: level_assets.ldtk_project.clone(),
+                ..Default::default()
+            },
+            StateScoped(Screen::Gameplay),
+        ));
     });
 }
 
+/// Point fields are exported in LDtk cell coordinates, where y points down,
+/// while `GridCoords` are flipped so that y points up.
+fn point_field_to_grid(point: IVec2, level_height: i32) -> IVec2 {
+    IVec2::new(point.x, level_height - 1 - point.y)
+}
+
 // System that checks level spawn and loads the relevant info.
 fn load_level(
     mut commands: Commands,
@@ -207,7 +577,7 @@ fn load_level(
         ),
     >,
     hazards: Query<
-        (&GridCoords, &MoveTo),
+        (&GridCoords, &MoveTo, &Kind, &Animation, &Movement, &Timing),
         (
             With<Hazard>,
             Without<Wall>,
@@ -218,22 +588,136 @@ fn load_level(
     player_assets: Res<PlayerAssets>,
     player: Query<(), With<Player>>,
     obstacles: Query<Entity, With<Obstacle>>,
+    level_assets: Res<LevelAssets>,
+    ldtk_projects: Res<Assets<LdtkProject>>,
+    mut seen_tutorials: ResMut<SeenTutorials>,
+    (grid, settings): (Res<WorldGrid>, Res<Settings>),
+    (mut camera, goals, one_ways, teleporters, mut collectibles, locks): (
+        Query<&mut Transform, With<IsDefaultUiCamera>>,
+        Query<&GridCoords, With<Goal>>,
+        Query<(&GridCoords, &OneWay)>,
+        Query<(&GridCoords, &Destination), With<Teleporter>>,
+        Query<(&GridCoords, &mut Collectible)>,
+        Query<(&GridCoords, &LockId, Has<Key>)>,
+    ),
 ) {
     for level_event in level_events.read() {
-        if let LevelEvent::Spawned(_level_iid) = level_event {
+        if let LevelEvent::Spawned(level_iid) = level_event {
             log::info!("Loading level.");
 
-            let wall_locations = walls.iter().map(|p| IVec2::new(p.x, p.y)).collect();
-            level.walls = wall_locations;
+            let raw_level = ldtk_projects
+                .get(&level_assets.ldtk_project)
+                .and_then(|project| project.get_raw_level_by_iid(level_iid.get()));
+
+            // Height of the level in cells, needed to convert point fields.
+            let level_height = raw_level
+                .map(|raw_level| raw_level.px_hei / grid.cell_size().y as i32)
+                .unwrap_or_default();
+            let to_grid = |point| point_field_to_grid(point, level_height);
+            // Everything is flipped around the middle of a mirrored level.
+            let level_width = raw_level
+                .map(|raw_level| raw_level.px_wid / grid.cell_size().x as i32)
+                .unwrap_or_default();
+            let mirror = |pos: IVec2| match settings.mirror_levels {
+                true => IVec2::new(level_width - 1 - pos.x, pos.y),
+                false => pos,
+            };
+
+            // Where the camera should start, defaults to the player spawn.
+            let camera_focus = raw_level
+                .and_then(|raw_level| raw_level.get_maybe_point_field("Camera").ok())
+                .and_then(|point| point.map(to_grid))
+                .map(mirror);
+
+            // Show the intro text of the level once.
+            if let Some(raw_level) = raw_level {
+                // The `Intro` field is optional, each entry is one page.
+                let pages: Vec<String> = raw_level
+                    .get_maybe_strings_field("Intro")
+                    .map(|pages| pages.iter().flatten().cloned().collect())
+                    .unwrap_or_default();
+                let id = format!("intro-{}", level_iid.get());
+                if !pages.is_empty() && seen_tutorials.first_time(&id) {
+                    commands.add(ShowDialogue { pages });
+                }
+            }
+
+            // The `Banned` field is optional and uses the `Unlock` enum.
+            let banned_names = raw_level
+                .and_then(|raw_level| raw_level.get_maybe_enums_field("Banned").ok())
+                .into_iter()
+                .flatten()
+                .flatten();
+            level.banned = banned_names
+                .filter_map(|name| {
+                    let command = unlock_from_name(name);
+                    if command.is_none() {
+                        log::error!("Unknown banned command `{name}`");
+                    }
+                    command
+                })
+                .collect();
+
+            let broken_one_ways = one_ways
+                .iter()
+                .filter(|(_, one_way)| one_way.0.is_none())
+                .map(|(p, _)| p);
+            level.walls = WallGrid::new(
+                walls
+                    .iter()
+                    .chain(broken_one_ways)
+                    .map(|p| mirror(IVec2::new(p.x, p.y))),
+            );
+            level.one_way = one_ways
+                .iter()
+                .filter_map(|(p, &OneWay(mask))| {
+                    let mask = match settings.mirror_levels {
+                        true => mask?.mirrored(),
+                        false => mask?,
+                    };
+                    Some((mirror(IVec2::new(p.x, p.y)), mask))
+                })
+                .collect();
 
             // Get unlocks from level file.
             let unlocks = checkpoints
                 .iter()
                 .map(|(p, &Unlock(unlock), &CommandCount(x))| {
-                    (IVec2::new(p.x, p.y), (unlock, x.max(0) as usize))
+                    (mirror(IVec2::new(p.x, p.y)), (unlock, x.max(0) as usize))
                 })
                 .collect();
             level.unlocks = unlocks;
+            level.goals = goals.iter().map(|p| mirror(IVec2::new(p.x, p.y))).collect();
+            level.teleporters = teleporters
+                .iter()
+                .map(|(p, &Destination(destination))| {
+                    (mirror(IVec2::new(p.x, p.y)), mirror(to_grid(destination)))
+                })
+                .collect();
+            level.collectibles.clear();
+            level.collected.clear();
+            for (p, mut collectible) in &mut collectibles {
+                collectible.0 = mirror(IVec2::new(p.x, p.y));
+                level.collectibles.insert(collectible.0);
+            }
+            level.keys.clear();
+            level.doors.clear();
+            level.held_keys.clear();
+            for (p, &LockId(id), key) in &locks {
+                let pos = mirror(IVec2::new(p.x, p.y));
+                match key {
+                    true => level.keys.insert(pos, id),
+                    false => level.doors.insert(pos, id),
+                };
+            }
+            level.visited.clear();
+            level.assisted.clear();
+            // The `VisitAll` field is optional and defaults to a linear level.
+            level.objective = match raw_level.map(|raw_level| raw_level.get_bool_field("VisitAll"))
+            {
+                Some(Ok(true)) => Objective::AllCheckpoints,
+                _ => Objective::Linear,
+            };
 
             // Despawn previous hazards.
             for entity in obstacles.iter() {
@@ -241,24 +725,53 @@ fn load_level(
             }
 
             // Spawn hazards.
-            for (grid_coords, move_to) in hazards.iter() {
-                const LEVEL_HEIGHT: i32 = 64; // TODO: Get this info from somewhere.
-                                              // IDK why the exported position uses a different coordinate system than the
-                                              // grid coords.
-                let pos = IVec2::new(grid_coords.x, grid_coords.y);
-                let dest = move_to
-                    .0
-                    .map(|p| IVec2::new(p.x, LEVEL_HEIGHT - 1 - p.y))
-                    .unwrap_or(pos);
-                let dir = dest - pos;
-                commands.add(SpawnObstacle { pos, dir });
+            for (
+                grid_coords,
+                move_to,
+                &Kind(kind),
+                &Animation(animation),
+                movement,
+                &Timing(timing),
+            ) in &hazards
+            {
+                let pos = mirror(IVec2::new(grid_coords.x, grid_coords.y));
+                // This works for any direction, e.g. vertical patrols.
+                let dest = move_to.0.map(to_grid).map(mirror).unwrap_or(pos);
+                let pattern = match movement {
+                    Movement::PingPong => MovementPattern::PingPong(dest),
+                    Movement::Wrap => MovementPattern::Wrap(dest),
+                    Movement::Waypoints(points) => MovementPattern::Waypoints(
+                        points.iter().copied().map(to_grid).map(mirror).collect(),
+                    ),
+                };
+                commands.add(SpawnObstacle {
+                    pos,
+                    pattern,
+                    kind,
+                    animation,
+                    timing,
+                });
             }
 
             // Spawn player and set player start only once.
             if player.get_single().is_err() {
                 // Set player start / last checkpoint.
-                let player_start = player_start.single();
-                level.last_checkpoint = IVec2::new(player_start.x, player_start.y);
+                let Ok(player_start) = player_start.get_single() else {
+                    log::error!("The level should have exactly one player start.");
+                    continue;
+                };
+                // The player still starts facing right, so a solution of the
+                // original level does not simply carry over.
+                let spawn = mirror(IVec2::new(player_start.x, player_start.y));
+                level.last_checkpoint = spawn;
+                level.spawn_known = true;
+
+                // Start the camera in place to avoid panning across the level.
+                let focus = camera_focus.unwrap_or(spawn);
+                for mut camera in &mut camera {
+                    let target = grid.project_to_world(focus.as_vec2());
+                    camera.translation = target.extend(camera.translation.z);
+                }
 
                 // Spawn player.
                 commands.spawn((
@@ -267,11 +780,11 @@ fn load_level(
                     SpriteBundle {
                         texture: player_assets.texture.clone(),
                         sprite: Sprite::default(),
-                        // transform: Transform::from_scale(Vec2::splat(4.0).extend(1.0)),
+                        transform: Transform::from_xyz(0.0, 0.0, PLAYER_Z),
                         ..Default::default()
                     },
-                    GridTransform(level.get_spawn()),
-                    NextGridTransform(level.get_spawn()),
+                    GridTransform(spawn),
+                    NextGridTransform(spawn),
                     TextureAtlas {
                         layout: player_assets.layout.clone(),
                         index: 0,
@@ -283,13 +796,310 @@ fn load_level(
     }
 }
 
-#[derive(Resource, Debug)]
+/// Despawn the player and hazards of the previous level when another level is
+/// selected without leaving the gameplay screen. The LDtk plugin replaces the
+/// level itself, and `load_level` spawns a new player once it is loaded.
+fn despawn_previous_level(
+    selection: Res<LevelSelection>,
+    mut loaded: Local<Option<usize>>,
+    player: Query<Entity, With<Player>>,
+    obstacles: Query<Entity, With<Obstacle>>,
+    mut level: ResMut<Level>,
+    mut commands: Commands,
+) {
+    let index = level_index(&selection);
+    let Some(previous) = loaded.replace(index).filter(|&previous| previous != index) else {
+        return;
+    };
+    log::info!("Switching from level {previous} to level {index}.");
+    for entity in player.iter().chain(obstacles.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    // The new player spawns at the start of the new level.
+    level.spawn_known = false;
+}
+
+/// Spawn the entities and IntGrid cells of `raw_level` in a headless app the
+/// way the LDtk plugin does, and load it with `load_level`. Without a loaded
+/// project the level fields are not read.
+#[cfg(test)]
+pub(super) fn load_raw_level(raw_level: &bevy_ecs_ldtk::ldtk::Level) -> App {
+    use bevy::ecs::system::SystemState;
+    use bevy_ecs_ldtk::{
+        app::{LdtkEntityMap, LdtkIntCellMap},
+        utils::int_grid_index_to_grid_coords,
+    };
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+    app.init_asset::<LdtkProject>();
+    app.init_asset::<TextureAtlasLayout>();
+    register_ldtk_types(&mut app);
+    app.add_event::<LevelEvent>();
+    app.add_systems(Update, load_level);
+
+    let world = app.world_mut();
+    let mut layouts = Assets::<TextureAtlasLayout>::default();
+    world.insert_resource(PlayerAssets::new(default(), default(), &mut layouts));
+    world.insert_resource(LevelAssets {
+        ldtk_project: default(),
+    });
+    world.init_resource::<Level>();
+    world.init_resource::<SeenTutorials>();
+    world.init_resource::<WorldGrid>();
+    world.init_resource::<Settings>();
+
+    let mut state: SystemState<(
+        Commands,
+        Res<AssetServer>,
+        ResMut<Assets<TextureAtlasLayout>>,
+        NonSend<LdtkEntityMap>,
+        NonSend<LdtkIntCellMap>,
+    )> = SystemState::new(world);
+    let (mut commands, asset_server, mut layouts, entity_map, int_cell_map) = state.get_mut(world);
+    for layer in raw_level.layer_instances.iter().flatten() {
+        for entity_instance in &layer.entity_instances {
+            let key = (None, Some(entity_instance.identifier.clone()));
+            if let Some(bundle) = entity_map.get(&key) {
+                bundle.evaluate(
+                    &mut commands.spawn_empty(),
+                    entity_instance,
+                    layer,
+                    None,
+                    None,
+                    &asset_server,
+                    &mut layouts,
+                );
+            }
+        }
+        let cells = layer.int_grid_csv.iter().enumerate();
+        for (index, &value) in cells.filter(|&(_, &value)| value != 0) {
+            let Some(bundle) = int_cell_map.get(&(None, Some(value))) else {
+                continue;
+            };
+            let grid_coords =
+                int_grid_index_to_grid_coords(index, layer.c_wid as u32, layer.c_hei as u32)
+                    .unwrap();
+            bundle.evaluate(
+                &mut commands.spawn(grid_coords),
+                IntGridCell { value },
+                layer,
+            );
+        }
+    }
+    state.apply(world);
+
+    world.send_event(LevelEvent::Spawned(LevelIid::new(&raw_level.iid)));
+    app.update();
+    app
+}
+
+/// Tell goals, teleporters, collectibles, keys and doors apart from the
+/// checkpoints whose sprite they use.
+fn tint_markers(
+    mut markers: Query<
+        (&mut Sprite, Has<Goal>, Has<Teleporter>, Has<Key>, Has<Door>),
+        Or<(
+            Added<Goal>,
+            Added<Teleporter>,
+            Added<Collectible>,
+            Added<Key>,
+            Added<Door>,
+        )>,
+    >,
+) {
+    for (mut sprite, goal, teleporter, key, door) in &mut markers {
+        sprite.color = match (goal, teleporter, key, door) {
+            (true, ..) => GOAL_COLOR,
+            (_, true, ..) => TELEPORTER_COLOR,
+            (_, _, true, _) => KEY_COLOR,
+            (.., true) => DOOR_COLOR,
+            _ => COLLECTIBLE_COLOR,
+        };
+    }
+}
+
+/// Hide the keys that were picked up and the doors they opened.
+fn show_keys_and_doors(
+    level: Res<Level>,
+    mut keys: Query<(&LockId, &mut Visibility), (With<Key>, Without<Door>)>,
+    mut doors: Query<(&LockId, &mut Visibility), (With<Door>, Without<Key>)>,
+) {
+    for (&LockId(id), mut visibility) in keys.iter_mut().chain(doors.iter_mut()) {
+        *visibility = match level.held_keys.contains(&id) {
+            true => Visibility::Hidden,
+            false => Visibility::Inherited,
+        };
+    }
+}
+
+/// Hide the collectibles that were picked up during the current run.
+fn show_collectibles(level: Res<Level>, mut items: Query<(&Collectible, &mut Visibility)>) {
+    for (&Collectible(pos), mut visibility) in &mut items {
+        *visibility = match level.collected.contains(&pos) {
+            true => Visibility::Hidden,
+            false => Visibility::Inherited,
+        };
+    }
+}
+
+/// Flip the tiles and LDtk entities of mirrored levels to match the mirrored
+/// positions set in `load_level`.
+fn mirror_level_tiles(
+    mut level_events: EventReader<LevelEvent>,
+    mut levels: Query<(&LevelIid, &mut Transform)>,
+    level_assets: Res<LevelAssets>,
+    ldtk_projects: Res<Assets<LdtkProject>>,
+    settings: Res<Settings>,
+) {
+    for level_event in level_events.read() {
+        let LevelEvent::Transformed(level_iid) = level_event else {
+            continue;
+        };
+        if !settings.mirror_levels {
+            continue;
+        }
+        let Some(raw_level) = ldtk_projects
+            .get(&level_assets.ldtk_project)
+            .and_then(|project| project.get_raw_level_by_iid(level_iid.get()))
+        else {
+            continue;
+        };
+        for (iid, mut transform) in &mut levels {
+            if iid == level_iid {
+                transform.scale.x = -1.0;
+                transform.translation.x = (raw_level.world_x + raw_level.px_wid) as f32;
+            }
+        }
+    }
+}
+
+/// Start a newly loaded level with the script last submitted there, which is
+/// empty the first time, unless the current script should be carried over.
+fn clear_script_for_new_level(
+    player: Query<(), Added<Player>>,
+    settings: Res<Settings>,
+    selection: Res<LevelSelection>,
+    level: Res<Level>,
+    mut editor_state: ResMut<EditorState>,
+    mut commands: Commands,
+) {
+    if player.is_empty() || settings.carry_over_script {
+        return;
+    }
+    editor_state.set_script(load_submitted(level_index(&selection), &level));
+    commands.add(ShowEditor::default());
+}
+
+/// Index of the selected level, used as a key for saved data.
+pub fn level_index(selection: &LevelSelection) -> usize {
+    match selection {
+        LevelSelection::Indices(indices) => indices.level,
+        _ => 0,
+    }
+}
+
+/// Dense bitset of the solid cells within the bounds of the level.
+/// This is checked many times per move, so it should be cheap to look up.
+#[derive(Debug, Default, Clone)]
+struct WallGrid {
+    min: IVec2,
+    size: IVec2,
+    bits: Vec<u64>,
+}
+
+impl WallGrid {
+    fn new(walls: impl IntoIterator<Item = IVec2>) -> Self {
+        let walls: Vec<_> = walls.into_iter().collect();
+        let Some(&first) = walls.first() else {
+            return Self::default();
+        };
+        let (min, max) = walls
+            .iter()
+            .fold((first, first), |(min, max), &p| (min.min(p), max.max(p)));
+        let size = max - min + IVec2::ONE;
+
+        let mut grid = Self {
+            min,
+            size,
+            bits: vec![0; (size.x * size.y) as usize / 64 + 1],
+        };
+        for wall in walls {
+            let index = grid.index(wall).expect("wall within bounds");
+            grid.bits[index / 64] |= 1 << (index % 64);
+        }
+        grid
+    }
+
+    fn index(&self, pos: IVec2) -> Option<usize> {
+        let local = pos - self.min;
+        let inside = local.cmpge(IVec2::ZERO).all() && local.cmplt(self.size).all();
+        inside.then(|| (local.y * self.size.x + local.x) as usize)
+    }
+
+    fn contains(&self, pos: IVec2) -> bool {
+        self.index(pos)
+            .is_some_and(|index| self.bits[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    /// The smallest rectangle containing every wall, if there are any.
+    fn bounds(&self) -> Option<IRect> {
+        (self.size != IVec2::ZERO)
+            .then(|| IRect::from_corners(self.min, self.min + self.size - IVec2::ONE))
+    }
+
+    /// Every solid cell.
+    #[cfg(feature = "dev")]
+    fn cells(&self) -> impl Iterator<Item = IVec2> + '_ {
+        (0..self.size.y)
+            .flat_map(move |y| (0..self.size.x).map(move |x| self.min + IVec2::new(x, y)))
+            .filter(|&pos| self.contains(pos))
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
 pub struct Level {
-    walls: HashSet<IVec2>,
+    walls: WallGrid,
+    /// Walls that only block some directions, which are not in `walls`.
+    pub one_way: HashMap<IVec2, DirMask>,
     pub unlocks: HashMap<IVec2, (Option<ScriptCommand>, usize)>,
+    /// Reaching any of these completes the level.
+    pub goals: HashSet<IVec2>,
+    /// Teleporters with their destination.
+    pub teleporters: HashMap<IVec2, IVec2>,
+    /// Every collectible has to be picked up before a goal counts.
+    pub collectibles: HashSet<IVec2>,
+    /// Collectibles picked up since respawning.
+    pub collected: HashSet<IVec2>,
+    /// Keys with the id of the doors they open.
+    pub keys: HashMap<IVec2, i32>,
+    /// Doors with their id, which are solid unless the id is held.
+    pub doors: HashMap<IVec2, i32>,
+    /// Ids of the keys picked up since respawning.
+    pub held_keys: HashSet<i32>,
     pub unlocked: Vec<ScriptCommand>,
     pub command_count: usize,
     pub last_checkpoint: IVec2,
+    /// Whether `last_checkpoint` was set by loading a level yet.
+    spawn_known: bool,
+    /// Checkpoints that were reached while playing in assist mode.
+    pub assisted: HashSet<IVec2>,
+    pub objective: Objective,
+    /// Commands that cannot be used in this level even when unlocked, by
+    /// their [`ScriptCommand::unlock_key`].
+    pub banned: HashSet<ScriptCommand>,
+    /// Checkpoints that were reached since the level was loaded.
+    pub visited: HashSet<IVec2>,
+}
+
+/// What the player has to do to complete a level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Objective {
+    /// Go from one checkpoint to the next.
+    #[default]
+    Linear,
+    /// Visit every checkpoint at least once, in any order.
+    AllCheckpoints,
 }
 
 /// Temporary hardcoded level for testing.
@@ -297,9 +1107,22 @@ impl Default for Level {
     fn default() -> Self {
         Self {
             // These will be set on level load.
-            walls: HashSet::default(),
+            walls: WallGrid::default(),
+            one_way: HashMap::default(),
             unlocks: HashMap::default(),
+            goals: HashSet::default(),
+            teleporters: HashMap::default(),
+            collectibles: HashSet::default(),
+            collected: HashSet::default(),
+            keys: HashMap::default(),
+            doors: HashMap::default(),
+            held_keys: HashSet::default(),
             last_checkpoint: IVec2::default(),
+            spawn_known: false,
+            assisted: HashSet::default(),
+            objective: Objective::default(),
+            banned: HashSet::default(),
+            visited: HashSet::default(),
             // Start with just `Walk` and 1 command count.
             unlocked: vec![ScriptCommand::Walk],
             command_count: 1,
@@ -308,9 +1131,68 @@ impl Default for Level {
 }
 
 impl Level {
-    /// Check whether the position is solid terrain.
+    /// A level with the given solid cells which respawns the player at
+    /// `spawn`, without loading anything from LDtk.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn from_walls(walls: impl IntoIterator<Item = IVec2>, spawn: IVec2) -> Self {
+        Self {
+            walls: WallGrid::new(walls),
+            last_checkpoint: spawn,
+            spawn_known: true,
+            ..default()
+        }
+    }
+
+    /// The cells spanned by the walls of the level, which surround everything
+    /// else.
+    pub fn bounds(&self) -> Option<IRect> {
+        self.walls.bounds()
+    }
+
+    /// Check whether the position is solid terrain. Doors count as solid
+    /// until their key is picked up.
     pub fn is_solid(&self, pos: IVec2) -> bool {
-        self.walls.contains(&pos)
+        let locked = |id| !self.held_keys.contains(id);
+        self.walls.contains(pos) || self.doors.get(&pos).is_some_and(locked)
+    }
+
+    /// Check whether moving in `dir` into the position is blocked, which
+    /// includes one-way walls.
+    pub fn is_solid_from(&self, pos: IVec2, dir: IVec2) -> bool {
+        self.is_solid(pos) || self.one_way.get(&pos).is_some_and(|mask| mask.blocks(dir))
+    }
+
+    /// Whether the command is unlocked and not banned in this level.
+    pub fn allows(&self, command: ScriptCommand) -> bool {
+        let usable = |key| self.unlocked.contains(&key) && !self.banned.contains(&key);
+        match command {
+            // Both kinds of loops are closed with the same bracket.
+            ScriptCommand::CloseBracket => {
+                usable(ScriptCommand::OpenBracket) || usable(ScriptCommand::Repeat(DEFAULT_REPEAT))
+            }
+            _ => usable(command.unlock_key()),
+        }
+    }
+
+    /// Check whether the position is a goal.
+    pub fn is_goal(&self, pos: IVec2) -> bool {
+        self.goals.contains(&pos)
+    }
+
+    /// Whether there is a collectible at the position that was not picked up
+    /// yet.
+    pub fn has_collectible(&self, pos: IVec2) -> bool {
+        self.collectibles.contains(&pos) && !self.collected.contains(&pos)
+    }
+
+    /// Whether every collectible was picked up, so that goals count.
+    pub fn all_collected(&self) -> bool {
+        self.collected.len() == self.collectibles.len()
+    }
+
+    /// Where moving onto the position teleports the player, if anywhere.
+    pub fn teleport_destination(&self, pos: IVec2) -> Option<IVec2> {
+        self.teleporters.get(&pos).copied()
     }
 
     /// Check whether the position is a checkpoint.
@@ -318,8 +1200,77 @@ impl Level {
         self.unlocks.contains_key(&pos)
     }
 
-    pub fn get_spawn(&self) -> IVec2 {
-        self.last_checkpoint
+    /// Where the player respawns, or `None` until a level is loaded.
+    pub fn get_spawn(&self) -> Option<IVec2> {
+        self.spawn_known.then_some(self.last_checkpoint)
+    }
+
+    /// Mark the checkpoint as visited. Returns whether this completed the
+    /// level.
+    pub fn visit(&mut self, pos: IVec2) -> bool {
+        let was_complete = self.is_complete();
+        self.visited.insert(pos);
+        !was_complete && self.is_complete()
+    }
+
+    /// Whether the objective of the level is fulfilled. Linear levels are
+    /// never complete.
+    pub fn is_complete(&self) -> bool {
+        self.objective == Objective::AllCheckpoints
+            && !self.unlocks.is_empty()
+            && self.unlocks.keys().all(|pos| self.visited.contains(pos))
+    }
+
+    /// The level as an ASCII map with the top row first: `.` is empty, `#` a
+    /// wall, `C` a checkpoint, `H` one of `hazards` and `@` the spawn point.
+    #[cfg(feature = "dev")]
+    pub fn to_ascii(&self, hazards: &[IVec2]) -> String {
+        let spawn = self.get_spawn();
+        let cells: Vec<_> = self
+            .walls
+            .cells()
+            .chain(self.unlocks.keys().copied())
+            .chain(hazards.iter().copied())
+            .chain(spawn)
+            .collect();
+        let Some(&first) = cells.first() else {
+            return String::new();
+        };
+        let (min, max) = cells
+            .iter()
+            .fold((first, first), |(min, max), &p| (min.min(p), max.max(p)));
+
+        let mut map = String::new();
+        for y in (min.y..=max.y).rev() {
+            for x in min.x..=max.x {
+                let pos = IVec2::new(x, y);
+                map.push(if spawn == Some(pos) {
+                    '@'
+                } else if hazards.contains(&pos) {
+                    'H'
+                } else if self.is_checkpoint(pos) {
+                    'C'
+                } else if self.is_solid(pos) {
+                    '#'
+                } else {
+                    '.'
+                });
+            }
+            map.push('\n');
+        }
+        map
+    }
+
+    /// How many commands are unlocked, out of all commands that can be
+    /// unlocked in this level.
+    pub fn unlock_progress(&self) -> (usize, usize) {
+        let reachable: HashSet<_> = self
+            .unlocks
+            .values()
+            .filter_map(|(unlock, _)| *unlock)
+            .chain(self.unlocked.iter().copied())
+            .collect();
+        (self.unlocked.len(), reachable.len())
     }
 }
 
@@ -329,12 +1280,43 @@ pub struct WorldGrid {
     size: Vec2,
 }
 
+impl Default for WorldGrid {
+    fn default() -> Self {
+        Self {
+            origin: Vec2::splat(8.),
+            size: Vec2::splat(16.),
+        }
+    }
+}
+
 impl WorldGrid {
     pub fn project_to_world(&self, coord: Vec2) -> Vec2 {
         coord.mul_add(self.size, self.origin)
     }
+
+    /// The inverse of [`WorldGrid::project_to_world`], keeping sub-cell
+    /// precision.
+    pub fn world_to_grid_f32(&self, world: Vec2) -> Vec2 {
+        (world - self.origin) / self.size
+    }
+
+    /// The grid cell containing the world position.
+    pub fn world_to_grid(&self, world: Vec2) -> IVec2 {
+        // Cell coordinates are at the center of the cell.
+        self.world_to_grid_f32(world).round().as_ivec2()
+    }
+
+    /// The size of a single grid cell in world units.
+    pub fn cell_size(&self) -> Vec2 {
+        self.size
+    }
 }
 
+/// Sprites are drawn in this order above the level tiles, so that the player
+/// is never hidden behind a hazard in the same cell.
+pub const OBSTACLE_Z: f32 = 10.0;
+pub const PLAYER_Z: f32 = 11.0;
+
 #[derive(Component)]
 pub struct GridTransform(pub IVec2);
 
@@ -344,16 +1326,34 @@ pub struct NextGridTransform(pub IVec2);
 #[derive(Resource)]
 pub struct AnimationTick(pub Timer);
 
+/// Number of ticks since the last respawn.
+#[derive(Resource, Debug, Default)]
+pub struct TickCount(pub usize);
+
 pub fn update_tick_timer(
     time: Res<Time>,
+    settings: Res<Settings>,
     mut tick: ResMut<AnimationTick>,
-    mut q: Query<(&mut GridTransform, &NextGridTransform)>,
+    level: Res<Level>,
+    mut q: Query<(&mut GridTransform, &mut NextGridTransform, Has<Player>)>,
 ) {
-    tick.0.tick(time.delta());
+    // Slowing down the tick slows down the player and hazards alike,
+    // so the puzzles stay the same.
+    tick.0
+        .tick(time.delta().mul_f32(settings.difficulty.time_scale()));
 
     if tick.0.just_finished() {
-        for (mut old, new) in &mut q {
+        for (mut old, mut new, is_player) in &mut q {
+            let moved = old.0 != new.0;
             old.0 = new.0;
+            // Only moving onto a teleporter uses it, so neither arriving on
+            // another teleporter nor respawning on one teleports again.
+            if let Some(destination) = level.teleport_destination(new.0) {
+                if is_player && moved {
+                    old.0 = destination;
+                    new.0 = destination;
+                }
+            }
         }
     }
 }
@@ -363,3 +1363,157 @@ pub struct TickStart;
 
 #[derive(Event)]
 pub struct Reset;
+
+/// Sent when a run completes the level, by reaching a goal or by visiting
+/// every checkpoint.
+#[derive(Event)]
+pub struct LevelComplete;
+
+/// Undo the last tick when stepping back through a run.
+#[derive(Event)]
+pub struct Rewind;
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::world::Command as _;
+
+    use super::*;
+    use crate::demo::{obstacle::Patrol, testing::run_script};
+
+    #[test]
+    fn wall_grid_contains_only_walls() {
+        let walls = [IVec2::new(-2, 0), IVec2::new(3, 0), IVec2::new(3, 5)];
+        let grid = WallGrid::new(walls);
+        for wall in walls {
+            assert!(grid.contains(wall));
+        }
+        assert!(!grid.contains(IVec2::new(0, 0)));
+        assert!(!grid.contains(IVec2::new(-2, 5)));
+        // Outside of the bounds.
+        assert!(!grid.contains(IVec2::new(-3, 0)));
+        assert!(!grid.contains(IVec2::new(3, 6)));
+    }
+
+    #[test]
+    fn wall_grid_bounds() {
+        let grid = WallGrid::new([IVec2::new(-2, 1), IVec2::new(3, -4)]);
+        assert_eq!(
+            grid.bounds(),
+            Some(IRect::from_corners(IVec2::new(-2, -4), IVec2::new(3, 1)))
+        );
+        assert_eq!(WallGrid::new([]).bounds(), None);
+        assert!(!WallGrid::new([]).contains(IVec2::ZERO));
+    }
+
+    #[test]
+    fn any_goal_counts() {
+        let mut level = Level::default();
+        level.goals.extend([IVec2::new(1, 1), IVec2::new(5, 1)]);
+        assert!(level.is_goal(IVec2::new(1, 1)));
+        assert!(level.is_goal(IVec2::new(5, 1)));
+        assert!(!level.is_goal(IVec2::new(3, 1)));
+    }
+
+    #[test]
+    fn all_collected_needs_every_collectible() {
+        let mut level = Level::default();
+        assert!(level.all_collected());
+        level
+            .collectibles
+            .extend([IVec2::new(1, 1), IVec2::new(2, 1)]);
+        level.collected.insert(IVec2::new(1, 1));
+        assert!(!level.has_collectible(IVec2::new(1, 1)));
+        assert!(level.has_collectible(IVec2::new(2, 1)));
+        assert!(!level.all_collected());
+        level.collected.insert(IVec2::new(2, 1));
+        assert!(level.all_collected());
+    }
+
+    #[test]
+    fn doors_open_for_their_key() {
+        let mut level = Level::default();
+        level.doors.insert(IVec2::new(2, 1), 7);
+        level.doors.insert(IVec2::new(4, 1), 8);
+        assert!(level.is_solid(IVec2::new(2, 1)));
+        level.held_keys.insert(7);
+        assert!(!level.is_solid(IVec2::new(2, 1)));
+        assert!(level.is_solid(IVec2::new(4, 1)));
+    }
+
+    #[test]
+    fn teleporter_moves_player_once_per_tick() {
+        let floor = (-2..=12).map(|x| IVec2::new(x, 0));
+        let mut level = Level::from_walls(floor, IVec2::new(0, 1));
+        // Arriving on the other end of the pair does not teleport back.
+        level.teleporters.insert(IVec2::new(2, 1), IVec2::new(8, 1));
+        level.teleporters.insert(IVec2::new(8, 1), IVec2::new(2, 1));
+        let end = run_script(&mut World::new(), level, vec![ScriptCommand::Walk], 2);
+        assert_eq!(end, IVec2::new(8, 1));
+    }
+
+    /// Stand-ins for the player and hazard that `load_level` spawns.
+    fn spawn_level_entities(world: &mut World) {
+        world.spawn(Player);
+        SpawnObstacle {
+            pos: IVec2::new(2, 1),
+            pattern: MovementPattern::PingPong(IVec2::new(4, 1)),
+            kind: HazardKind::Spikes,
+            animation: default(),
+            timing: default(),
+        }
+        .apply(world);
+    }
+
+    #[test]
+    fn switching_levels_does_not_leak_entities() {
+        let mut world = World::new();
+        world.insert_resource(PlayerAssets::new(
+            default(),
+            default(),
+            &mut Assets::default(),
+        ));
+        world.insert_resource(LevelSelection::index(0));
+        world.init_resource::<Level>();
+        let despawn = world.register_system(despawn_previous_level);
+
+        spawn_level_entities(&mut world);
+        world.run_system(despawn).unwrap();
+        let count = world.entities().len();
+        for index in [1, 0, 2] {
+            world.insert_resource(LevelSelection::index(index));
+            world.run_system(despawn).unwrap();
+            assert!(!world.resource::<Level>().spawn_known);
+            spawn_level_entities(&mut world);
+            assert_eq!(world.entities().len(), count);
+        }
+    }
+
+    #[test]
+    fn vertical_move_to_patrols_between_endpoints() {
+        // In LDtk the target is two cells above the hazard, with y pointing
+        // down.
+        let spawn = point_field_to_grid(IVec2::new(3, 5), 10);
+        let to = point_field_to_grid(IVec2::new(3, 3), 10);
+        assert_eq!(to, spawn + IVec2::new(0, 2));
+        let mut patrol = Patrol::new(&MovementPattern::PingPong(to), spawn);
+        assert_eq!(patrol.advance(), to);
+        assert_eq!(patrol.advance(), spawn);
+    }
+
+    #[test]
+    fn world_to_grid_inverts_project_to_world() {
+        let grid = WorldGrid {
+            origin: Vec2::new(-120.0, 36.0),
+            size: Vec2::splat(16.0),
+        };
+        for x in -3..=3 {
+            for y in -3..=3 {
+                let cell = IVec2::new(x, y);
+                let world = grid.project_to_world(cell.as_vec2());
+                assert_eq!(grid.world_to_grid(world), cell);
+                // Anywhere within the cell.
+                assert_eq!(grid.world_to_grid(world + grid.cell_size() * 0.4), cell);
+            }
+        }
+    }
+}
@@ -7,10 +7,24 @@ use bevy::prelude::*;
 
 pub mod action;
 pub mod animation;
+pub mod dialogue;
 pub mod editor;
 pub mod level;
-mod obstacle;
+pub mod obstacle;
+mod palette;
 pub mod player;
+#[cfg(not(target_family = "wasm"))]
+mod playtest;
+pub mod progress;
+pub mod replay;
+pub mod run_stats;
+pub mod simulation;
+pub mod solutions;
+pub mod stats;
+pub mod style;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod tutorial;
 
 pub(super) fn plugin(app: &mut App) {
     app.add_plugins((
@@ -19,5 +33,16 @@ pub(super) fn plugin(app: &mut App) {
         level::plugin,
         obstacle::plugin,
         editor::plugin,
+        dialogue::plugin,
+        solutions::plugin,
+        replay::plugin,
+        style::plugin,
+        palette::plugin,
+        stats::plugin,
+        progress::plugin,
+        run_stats::plugin,
+        tutorial::plugin,
     ));
+    #[cfg(not(target_family = "wasm"))]
+    app.add_plugins(playtest::plugin);
 }
@@ -1,24 +1,217 @@
+use std::iter;
+
 use bevy::{
     ecs::{system::RunSystemOnce as _, world::Command},
     prelude::*,
 };
 
-use super::{animation::PlayerAssets, level::GridTransform};
+use super::{animation::PlayerAssets, level::GridTransform, player::Player};
 use crate::{
-    demo::level::{AnimationTick, NextGridTransform, Reset, TickStart, WorldGrid},
+    demo::level::{
+        AnimationTick,
+        NextGridTransform,
+        Reset,
+        Rewind,
+        TickCount,
+        TickStart,
+        WorldGrid,
+        OBSTACLE_Z,
+    },
     screens::Screen,
     AppSet,
 };
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_systems(Update, movement.in_set(AppSet::Update));
+    app.add_systems(
+        Update,
+        // Resetting last overrides a tick started in the same frame.
+        (
+            movement,
+            carry_player,
+            rewind_obstacles.run_if(on_event::<Rewind>()),
+            reset_obstacles.run_if(on_event::<Reset>()),
+        )
+            .chain()
+            .in_set(AppSet::Update),
+    );
 }
 
 /// A command to spawn the player character.
 #[derive(Debug, Clone)]
 pub struct SpawnObstacle {
     pub pos: IVec2,
-    pub dir: IVec2,
+    pub pattern: MovementPattern,
+    pub kind: HazardKind,
+    pub animation: HazardAnimation,
+    pub timing: HazardTiming,
+}
+
+/// How a hazard moves, one step per tick.
+#[derive(Reflect, Debug, Clone, PartialEq, Eq)]
+pub enum MovementPattern {
+    /// Back and forth between the spawn and the given cell.
+    PingPong(IVec2),
+    /// A cell at a time from the spawn to the given cell, then straight back
+    /// to the spawn within a single tick.
+    Wrap(IVec2),
+    /// A cell at a time to each of the cells in turn, then back to the spawn.
+    Waypoints(Vec<IVec2>),
+}
+
+impl MovementPattern {
+    /// The cells visited in order, starting at `spawn`, before starting over.
+    fn route(&self, spawn: IVec2) -> Vec<IVec2> {
+        match self {
+            MovementPattern::PingPong(to) if *to == spawn => vec![spawn],
+            MovementPattern::PingPong(to) => vec![spawn, *to],
+            MovementPattern::Wrap(to) => {
+                let mut route = vec![spawn];
+                walk_to(&mut route, *to);
+                route
+            }
+            MovementPattern::Waypoints(points) => {
+                let mut route = vec![spawn];
+                for &point in points.iter().chain(iter::once(&spawn)) {
+                    walk_to(&mut route, point);
+                }
+                // The walk back ends where the route starts over.
+                if route.len() > 1 {
+                    route.pop();
+                }
+                route
+            }
+        }
+    }
+}
+
+/// Extend the route a cell at a time from its last cell to `to`. Diagonal
+/// steps cover whatever is not in a straight line.
+fn walk_to(route: &mut Vec<IVec2>, to: IVec2) {
+    let mut pos = *route.last().expect("routes start at the spawn");
+    while pos != to {
+        pos += (to - pos).signum();
+        route.push(pos);
+    }
+}
+
+/// How far an obstacle is along the route of its movement pattern.
+#[derive(Reflect, Debug, Clone, PartialEq, Eq)]
+pub struct Patrol {
+    route: Vec<IVec2>,
+    step: usize,
+}
+
+impl Patrol {
+    pub fn new(pattern: &MovementPattern, spawn: IVec2) -> Self {
+        Self {
+            route: pattern.route(spawn),
+            step: 0,
+        }
+    }
+
+    pub fn pos(&self) -> IVec2 {
+        self.route[self.step]
+    }
+
+    /// Go to the next cell of the route and return it.
+    pub fn advance(&mut self) -> IVec2 {
+        self.step = (self.step + 1) % self.route.len();
+        self.pos()
+    }
+
+    /// Go back to the previous cell of the route and return it.
+    fn retreat(&mut self) -> IVec2 {
+        self.step = (self.step + self.route.len() - 1) % self.route.len();
+        self.pos()
+    }
+}
+
+/// How the sprite of a hazard animates.
+#[derive(Reflect, Debug, Clone, Copy, PartialEq)]
+pub struct HazardAnimation {
+    /// Number of frames in the hazard sheet to cycle through, where a single
+    /// frame is a static hazard.
+    pub frames: usize,
+    /// Animation cycles per tick.
+    pub speed: f32,
+}
+
+impl Default for HazardAnimation {
+    fn default() -> Self {
+        Self {
+            frames: HAZARD_FRAMES,
+            speed: 1.0,
+        }
+    }
+}
+
+impl HazardAnimation {
+    /// The frame to show `fraction` of the way through a tick, `ticks` ticks
+    /// after respawning.
+    fn frame(&self, ticks: usize, fraction: f32) -> usize {
+        let frames = self.frames.clamp(1, HAZARD_FRAMES);
+        // Whole ticks are counted so that slow animations continue across
+        // ticks. The end of a tick is still the last frame, not the first.
+        let progress = (ticks as f32 + fraction.min(0.999)) * self.speed;
+        ((progress.fract() * frames as f32) as usize).min(frames - 1)
+    }
+}
+
+/// On which ticks a hazard is dangerous.
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HazardTiming {
+    /// Number of ticks after which the pattern repeats.
+    pub period: usize,
+    /// The tick in each period on which the hazard is active.
+    pub active_phase: usize,
+}
+
+impl Default for HazardTiming {
+    fn default() -> Self {
+        Self {
+            period: 1,
+            active_phase: 0,
+        }
+    }
+}
+
+impl HazardTiming {
+    /// Whether the hazard is active during the tick with the given number,
+    /// counted since respawning.
+    pub fn is_active(&self, ticks: usize) -> bool {
+        let period = self.period.max(1);
+        ticks % period == self.active_phase % period
+    }
+}
+
+/// Sprite color of hazards that are not active this tick.
+const INACTIVE_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.35);
+
+/// Number of frames in the hazard sheet.
+pub const HAZARD_FRAMES: usize = 4;
+
+/// What kind of hazard an obstacle is, which decides what happens when the
+/// player shares a cell with it.
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HazardKind {
+    #[default]
+    Spikes,
+    Crusher,
+    Enemy,
+    /// Purely visual, the player passes through it.
+    Decoration,
+    /// Carries the player along when they stay in its cell.
+    Platform,
+}
+
+impl HazardKind {
+    /// Whether touching this hazard sends the player back to the checkpoint.
+    pub fn is_lethal(self) -> bool {
+        match self {
+            HazardKind::Spikes | HazardKind::Crusher | HazardKind::Enemy => true,
+            HazardKind::Decoration | HazardKind::Platform => false,
+        }
+    }
 }
 
 impl Command for SpawnObstacle {
@@ -29,8 +222,34 @@ impl Command for SpawnObstacle {
 
 #[derive(Component)]
 pub struct Obstacle {
-    dir: IVec2,
+    patrol: Patrol,
     spawn: SpawnObstacle,
+    pub kind: HazardKind,
+    animation: HazardAnimation,
+    pub timing: HazardTiming,
+}
+
+impl Obstacle {
+    /// Where the obstacle is in its movement pattern.
+    pub fn patrol(&self) -> &Patrol {
+        &self.patrol
+    }
+}
+
+/// Where a platform carries a player who starts a tick at `pos` and moves by
+/// `offset` on their own. Walking off a platform leaves it behind.
+pub fn ride_platforms(
+    pos: IVec2,
+    offset: IVec2,
+    platforms: impl IntoIterator<Item = (IVec2, IVec2)>,
+) -> IVec2 {
+    if offset != IVec2::ZERO {
+        return pos + offset;
+    }
+    platforms
+        .into_iter()
+        .find(|&(platform, _)| platform == pos)
+        .map_or(pos, |(platform, next)| pos + next - platform)
 }
 
 fn spawn_obstacle(
@@ -41,12 +260,15 @@ fn spawn_obstacle(
     commands.spawn((
         Name::new("Obstacle"),
         Obstacle {
-            dir: config.dir,
+            patrol: Patrol::new(&config.pattern, config.pos),
             spawn: config.clone(),
+            kind: config.kind,
+            animation: config.animation,
+            timing: config.timing,
         },
         SpriteBundle {
             texture: player_assets.hazard_texture.clone(),
-            // transform: Transform::from_scale(Vec2::splat(4.0).extend(1.0)),
+            transform: Transform::from_xyz(0.0, 0.0, OBSTACLE_Z),
             sprite: Sprite::default(),
             ..Default::default()
         },
@@ -60,37 +282,223 @@ fn spawn_obstacle(
     ));
 }
 
-fn movement(
+/// Put all obstacles back where they spawned.
+fn reset_obstacles(
+    mut reset: EventReader<Reset>,
+    mut o: Query<(
+        &mut GridTransform,
+        &mut NextGridTransform,
+        &mut Transform,
+        &mut Obstacle,
+    )>,
+    proj: Res<WorldGrid>,
+) {
+    reset.clear();
+    for (mut grid, mut next_grid, mut world, mut obstacle) in &mut o {
+        obstacle.patrol = Patrol::new(&obstacle.spawn.pattern, obstacle.spawn.pos);
+        grid.0 = obstacle.spawn.pos;
+        next_grid.0 = obstacle.spawn.pos;
+        let pos = proj.project_to_world(grid.0.as_vec2());
+        world.translation = pos.extend(OBSTACLE_Z);
+    }
+}
+
+/// Step every obstacle back to where it was before the last tick.
+fn rewind_obstacles(
+    mut rewind: EventReader<Rewind>,
     mut o: Query<(
         &mut GridTransform,
         &mut NextGridTransform,
         &mut Transform,
         &mut Obstacle,
+    )>,
+    proj: Res<WorldGrid>,
+) {
+    for _ in rewind.read() {
+        for (mut grid, mut next_grid, mut world, mut obstacle) in &mut o {
+            grid.0 = obstacle.patrol.retreat();
+            next_grid.0 = grid.0;
+            let pos = proj.project_to_world(grid.0.as_vec2());
+            world.translation = pos.extend(OBSTACLE_Z);
+        }
+    }
+}
+
+fn movement(
+    mut o: Query<(
+        &GridTransform,
+        &mut NextGridTransform,
+        &mut Transform,
+        &mut Obstacle,
         &mut TextureAtlas,
+        &mut Sprite,
     )>,
     tick: Res<AnimationTick>,
+    tick_count: Res<TickCount>,
     proj: Res<WorldGrid>,
     mut tick_start: EventReader<TickStart>,
-    mut reset: EventReader<Reset>,
 ) {
-    let reset = reset.read().count() != 0;
     let ticks = tick_start.read().count();
-    for (mut grid, mut next_grid, mut world, mut obstacle, mut atlas) in &mut o {
-        if ticks % 2 == 1 {
-            next_grid.0 = grid.0 + obstacle.dir;
-            obstacle.dir = -obstacle.dir;
-        }
-        if reset {
-            obstacle.dir = obstacle.spawn.dir;
-            grid.0 = obstacle.spawn.pos;
-            next_grid.0 = obstacle.spawn.pos;
+    for (grid, mut next_grid, mut world, mut obstacle, mut atlas, mut sprite) in &mut o {
+        for _ in 0..ticks {
+            next_grid.0 = obstacle.patrol.advance();
         }
 
         let old = grid.0.as_vec2();
         let new = next_grid.0.as_vec2();
-        let pos = old.lerp(new, tick.0.fraction());
-        world.translation = proj.project_to_world(pos).extend(world.translation.z);
+        // Wrapping back to the spawn is a jump, not a slide across the level.
+        let pos = match (next_grid.0 - grid.0).abs().max_element() > 1 {
+            true => new,
+            false => old.lerp(new, tick.0.fraction()),
+        };
+        world.translation = proj.project_to_world(pos).extend(OBSTACLE_Z);
+
+        // Inactive hazards are retracted, holding the first frame.
+        let active = obstacle.timing.is_active(tick_count.0);
+        atlas.index = match active {
+            true => obstacle.animation.frame(tick_count.0, tick.0.fraction()),
+            false => 0,
+        };
+        let color = match active {
+            true => Color::WHITE,
+            false => INACTIVE_COLOR,
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+/// Move the player along with the platform they stand on, after both picked
+/// where they go this tick. Being pushed into a wall kills the player in
+/// `respawn`.
+fn carry_player(
+    mut tick_start: EventReader<TickStart>,
+    obstacles: Query<(&GridTransform, &NextGridTransform, &Obstacle), Without<Player>>,
+    mut player: Query<(&GridTransform, &mut NextGridTransform), With<Player>>,
+) {
+    if tick_start.read().count() == 0 {
+        return;
+    }
+    let Ok((pos, mut next_pos)) = player.get_single_mut() else {
+        return;
+    };
+    let platforms = obstacles
+        .iter()
+        .filter(|(_, _, obstacle)| obstacle.kind == HazardKind::Platform)
+        .map(|(grid, next_grid, _)| (grid.0, next_grid.0));
+    next_pos.0 = ride_platforms(pos.0, next_pos.0 - pos.0, platforms);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn platform_carries_player_standing_on_it() {
+        let platforms = [(IVec2::new(2, 1), IVec2::new(3, 1))];
+        assert_eq!(
+            ride_platforms(IVec2::new(2, 1), IVec2::ZERO, platforms),
+            IVec2::new(3, 1)
+        );
+    }
 
-        atlas.index = ((tick.0.fraction() * 4.) as usize).min(3);
+    #[test]
+    fn walking_off_a_platform_leaves_it_behind() {
+        let platforms = [(IVec2::new(2, 1), IVec2::new(3, 1))];
+        let pos = ride_platforms(IVec2::new(2, 1), IVec2::new(-1, 0), platforms);
+        assert_eq!(pos, IVec2::new(1, 1));
+        // Nothing happens away from platforms.
+        assert_eq!(
+            ride_platforms(IVec2::new(5, 1), IVec2::ZERO, platforms),
+            IVec2::new(5, 1)
+        );
+    }
+
+    #[test]
+    fn wrap_restarts_after_reaching_the_end() {
+        let mut patrol = Patrol::new(&MovementPattern::Wrap(IVec2::new(2, 1)), IVec2::new(0, 1));
+        let path: Vec<_> = (0..4).map(|_| patrol.advance()).collect();
+        let expected = [(1, 1), (2, 1), (0, 1), (1, 1)].map(IVec2::from);
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn waypoints_cycle_through_every_point() {
+        let spawn = IVec2::new(0, 1);
+        let points = vec![IVec2::new(3, 1), IVec2::new(3, 4), IVec2::new(0, 4)];
+        let mut patrol = Patrol::new(&MovementPattern::Waypoints(points.clone()), spawn);
+        let cycle: Vec<_> = points.into_iter().chain([spawn]).collect();
+        // The corners of a 3 by 3 square, walked twice.
+        let path: Vec<_> = (0..24).map(|_| patrol.advance()).collect();
+        let mut corners = path.iter().filter(|pos| cycle.contains(pos));
+        assert!(corners.by_ref().take(4).eq(&cycle));
+        assert!(corners.eq(&cycle));
+    }
+
+    #[test]
+    fn waypoints_move_one_cell_per_tick() {
+        let spawn = IVec2::new(0, 1);
+        let points = vec![IVec2::new(3, 1), IVec2::new(1, 5)];
+        let mut patrol = Patrol::new(&MovementPattern::Waypoints(points), spawn);
+        let mut pos = spawn;
+        for _ in 0..20 {
+            let next = patrol.advance();
+            assert_eq!((next - pos).abs().max_element(), 1);
+            pos = next;
+        }
+    }
+
+    #[test]
+    fn wrap_moves_up_one_cell_per_tick() {
+        let spawn = IVec2::new(3, 4);
+        let mut patrol = Patrol::new(&MovementPattern::Wrap(IVec2::new(3, 6)), spawn);
+        assert_eq!(patrol.advance(), IVec2::new(3, 5));
+        assert_eq!(patrol.advance(), IVec2::new(3, 6));
+        assert_eq!(patrol.advance(), spawn);
+    }
+
+    #[test]
+    fn reset_returns_obstacles_to_spawn() {
+        let mut world = World::new();
+        world.insert_resource(PlayerAssets::new(
+            default(),
+            default(),
+            &mut Assets::default(),
+        ));
+        world.init_resource::<WorldGrid>();
+        world.init_resource::<Events<Reset>>();
+        let patterns = [
+            MovementPattern::PingPong(IVec2::new(4, 1)),
+            MovementPattern::Wrap(IVec2::new(2, 5)),
+            MovementPattern::Waypoints(vec![IVec2::new(6, 3), IVec2::new(6, 4)]),
+        ];
+        for pattern in patterns {
+            SpawnObstacle {
+                pos: IVec2::new(2, 1),
+                pattern,
+                kind: HazardKind::Spikes,
+                animation: default(),
+                timing: default(),
+            }
+            .apply(&mut world);
+        }
+
+        // Move every obstacle partway along its route.
+        let mut obstacles =
+            world.query::<(&mut GridTransform, &mut NextGridTransform, &mut Obstacle)>();
+        for (mut grid, mut next_grid, mut obstacle) in obstacles.iter_mut(&mut world) {
+            grid.0 = obstacle.patrol.advance();
+            next_grid.0 = obstacle.patrol.advance();
+        }
+        world.send_event(Reset);
+        world.run_system_once(reset_obstacles);
+
+        for (grid, next_grid, obstacle) in obstacles.iter(&world) {
+            let spawn = &obstacle.spawn;
+            assert_eq!(grid.0, spawn.pos);
+            assert_eq!(next_grid.0, spawn.pos);
+            assert_eq!(obstacle.patrol, Patrol::new(&spawn.pattern, spawn.pos));
+        }
     }
 }
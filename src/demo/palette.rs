@@ -0,0 +1,122 @@
+//! The palette of unlocked commands. Its order can be customized by clicking
+//! an icon to move it left, or right-clicking to move it right. The order is
+//! kept in [`Settings::palette_order`], and commands missing from it come last
+//! in the order they were unlocked.
+//!
+//! Commands banned in the current level are struck through.
+
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+
+use super::{action::ScriptCommand, level::Level};
+use crate::{screens::gameplay::UnlockedList, settings::Settings, AppSet};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            reorder_palette,
+            sort_palette.run_if(resource_changed::<Settings>.or_else(palette_item_added)),
+            mark_banned.run_if(resource_changed::<Level>.or_else(palette_item_added)),
+        )
+            .chain()
+            .in_set(AppSet::Update),
+    );
+}
+
+/// An icon in the palette, showing an unlocked command.
+#[derive(Component, Debug)]
+pub struct PaletteItem(pub ScriptCommand);
+
+fn palette_item_added(items: Query<(), Added<PaletteItem>>) -> bool {
+    !items.is_empty()
+}
+
+fn reorder_palette(
+    mouse: Res<ButtonInput<MouseButton>>,
+    list: Query<&Children, With<UnlockedList>>,
+    items: Query<(&PaletteItem, &RelativeCursorPosition)>,
+    mut settings: ResMut<Settings>,
+) {
+    let step = match (
+        mouse.just_pressed(MouseButton::Left),
+        mouse.just_pressed(MouseButton::Right),
+    ) {
+        (true, false) => -1,
+        (false, true) => 1,
+        _ => return,
+    };
+    let Ok(children) = list.get_single() else {
+        return;
+    };
+    let mut order: Vec<(ScriptCommand, bool)> = children
+        .iter()
+        .filter_map(|&child| items.get(child).ok())
+        .map(|(item, cursor)| (item.0, cursor.mouse_over()))
+        .collect();
+    let Some(index) = order.iter().position(|&(_, hovered)| hovered) else {
+        return;
+    };
+    let Some(target) = index.checked_add_signed(step).filter(|&i| i < order.len()) else {
+        return;
+    };
+    order.swap(index, target);
+    settings.palette_order = order.into_iter().map(|(command, _)| command).collect();
+}
+
+/// Put the palette icons in the order from the settings.
+fn sort_palette(
+    settings: Res<Settings>,
+    list: Query<(Entity, &Children), With<UnlockedList>>,
+    items: Query<&PaletteItem>,
+    mut commands: Commands,
+) {
+    let Ok((list, children)) = list.get_single() else {
+        return;
+    };
+    let rank = |entity: &Entity| {
+        items
+            .get(*entity)
+            .ok()
+            .and_then(|item| settings.palette_order.iter().position(|&c| c == item.0))
+            .unwrap_or(usize::MAX)
+    };
+    let mut sorted = children.to_vec();
+    // The sort is stable, so unordered commands keep their unlock order.
+    sorted.sort_by_key(rank);
+    if sorted[..] != children[..] {
+        commands.entity(list).replace_children(&sorted);
+    }
+}
+
+#[derive(Component)]
+struct BannedMark;
+
+fn mark_banned(
+    level: Res<Level>,
+    items: Query<(Entity, &PaletteItem)>,
+    marks: Query<Entity, With<BannedMark>>,
+    mut commands: Commands,
+) {
+    for mark in &marks {
+        commands.entity(mark).despawn_recursive();
+    }
+    for (entity, item) in &items {
+        if !level.banned.contains(&item.0.unlock_key()) {
+            continue;
+        }
+        commands.entity(entity).with_children(|children| {
+            children.spawn((Name::new("Banned Mark"), BannedMark, NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(0.0),
+                    right: Val::Percent(0.0),
+                    top: Val::Percent(45.0),
+                    height: Val::Percent(10.0),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgb(0.8, 0.1, 0.1)),
+                ..default()
+            }));
+        });
+    }
+}
@@ -7,46 +7,107 @@ use std::time::Duration;
 use bevy::{
     ecs::{system::RunSystemOnce as _, world::Command},
     prelude::*,
+    ui::RelativeCursorPosition,
+    utils::{HashMap, HashSet},
 };
+use bevy_ecs_ldtk::LevelSelection;
 
 use super::{
-    action::ScriptCommand,
+    action::{Facing, ScriptCommand, DOWN},
     animation::{AnimationResource, PlayerAssets},
     editor::EditorState,
-    level::{AnimationTick, GridTransform, Level},
+    level::{AnimationTick, GridTransform, Level, Objective, WorldGrid, PLAYER_Z},
 };
 use crate::{
     asset_tracking::LoadResource,
     demo::{
-        editor::{EditorAssets, ShowEditor},
-        level::{NextGridTransform, Reset, TickStart},
+        editor::{CommandTooltip, EditorAssets, ShowEditor},
+        level::{
+            level_index,
+            LevelComplete,
+            NextGridTransform,
+            Reset,
+            Rewind,
+            TickCount,
+            TickStart,
+        },
         obstacle::Obstacle,
+        palette::PaletteItem,
+        progress::Progress,
+        replay::ReplayViewer,
+    },
+    key_bindings::{action_just_pressed, KeyAction, KeyBindings},
+    persistence,
+    screens::{
+        gameplay::{
+            AutoplayLabel,
+            CheckpointProgressLabel,
+            LoopCounterLabel,
+            ScriptFinishedLabel,
+            UnlockProgressLabel,
+            UnlockedList,
+        },
+        Screen,
+    },
+    settings::{Difficulty, PlaybackSpeed, Settings, SubmitMode},
+    theme::{
+        interaction::{text_input_focused, SilentInteraction},
+        palette::LABEL_TEXT,
+        prelude::*,
     },
-    screens::gameplay::{AutoplayLabel, UnlockedList},
-    theme::palette::LABEL_TEXT,
     AppSet,
+    CameraPanned,
 };
 
 pub(super) fn plugin(app: &mut App) {
     app.register_type::<Player>();
     app.load_resource::<PlayerAssets>();
+    app.add_event::<RunOutcome>();
+    app.add_event::<ScriptFinished>();
 
     // Record directional input as movement controls.
     app.add_systems(
         Update,
         (
-            respawn,
+            respawn.run_if(not(resource_exists::<ReplayViewer>)),
             update_animation.in_set(AppSet::RecordInput),
-            camera_follow_player.in_set(AppSet::UpdateCamera),
+            step_back
+                .before(update_animation)
+                .in_set(AppSet::RecordInput)
+                .run_if(not(text_input_focused)),
+            cycle_playback_speed
+                .before(update_animation)
+                .in_set(AppSet::RecordInput)
+                .run_if(
+                    action_just_pressed(KeyAction::CyclePlaybackSpeed)
+                        .and_then(not(text_input_focused)),
+                ),
+            show_autoplay_mode.in_set(AppSet::Update),
+            animate_unlock.in_set(AppSet::Update),
+            (update_unlock_progress, update_checkpoint_progress)
+                .in_set(AppSet::Update)
+                .run_if(resource_changed::<Level>),
+            update_loop_counter
+                .in_set(AppSet::Update)
+                .run_if(resource_changed::<PlayerState>),
+            show_script_finished.in_set(AppSet::Update),
+            celebrate_level_complete
+                .in_set(AppSet::Update)
+                .run_if(on_event::<LevelComplete>()),
+            fade_celebration.in_set(AppSet::Update),
+            animate_return
+                .after(super::animation::apply_animation)
+                .in_set(AppSet::ApplyAnimation),
+            (tick_respawning, flash_invulnerable).in_set(AppSet::TickTimers),
+            fade_dying
+                .after(super::animation::apply_animation)
+                .in_set(AppSet::ApplyAnimation),
+            camera_follow_player
+                .in_set(AppSet::UpdateCamera)
+                .run_if(not(resource_exists::<CameraPanned>)),
         ),
     );
-    app.insert_resource(PlayerState {
-        x_dir: 1,
-        animation: None,
-        sequence: vec![],
-        cursor: 0,
-        autoplay: true,
-    });
+    app.insert_resource(PlayerState::new(Vec::new()));
 }
 
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
@@ -62,34 +123,146 @@ pub struct PlayerState {
     pub sequence: Vec<ScriptCommand>,
     pub cursor: usize,
     pub autoplay: bool,
+    /// How many times each command was executed during the current run.
+    pub executed: HashMap<ScriptCommand, usize>,
+    /// How many times each loop jumped back to its start since it was
+    /// entered, keyed by the index of its `OpenBracket`.
+    pub loop_iterations: HashMap<usize, usize>,
+    /// Indices of the script items reached during the current run. Items in a
+    /// skipped scope are never reached.
+    pub visited: HashSet<usize>,
+    /// How many times the cursor went past the end of the script and started
+    /// over during the current run.
+    pub completed_passes: usize,
+    /// Cells remembered by `Mark`, the most recent last.
+    pub marks: Vec<IVec2>,
+    /// The state before each executed tick of the current run, the most
+    /// recent last, to step back through the run.
+    pub trace: Vec<TraceStep>,
 }
 
-fn debug_actions(input: &ButtonInput<KeyCode>, state: &mut PlayerState) -> Option<ScriptCommand> {
-    let pressed_or_held = |key: KeyCode| input.pressed(key);
+#[derive(Debug, Clone)]
+pub struct TraceStep {
+    pub pos: IVec2,
+    pub cursor: usize,
+    pub x_dir: i32,
+    pub marks: Vec<IVec2>,
+    pub loop_iterations: HashMap<usize, usize>,
+    pub collected: HashSet<IVec2>,
+    pub held_keys: HashSet<i32>,
+}
+
+/// How many ticks can be stepped back.
+const MAX_TRACE: usize = 256;
+
+impl PlayerState {
+    /// Run `sequence` from the start, facing right.
+    pub fn new(sequence: Vec<ScriptCommand>) -> Self {
+        Self {
+            x_dir: 1,
+            animation: None,
+            sequence,
+            cursor: 0,
+            autoplay: true,
+            executed: HashMap::default(),
+            loop_iterations: HashMap::default(),
+            visited: HashSet::default(),
+            completed_passes: 0,
+            marks: Vec::new(),
+            trace: Vec::new(),
+        }
+    }
+
+    /// The commands executed during the current run, with how often each ran.
+    pub fn executed_commands(&self) -> impl Iterator<Item = (ScriptCommand, usize)> + '_ {
+        self.executed
+            .iter()
+            .map(|(&command, &count)| (command, count))
+    }
+
+    /// The iteration count of the innermost loop around the cursor, if any.
+    pub fn innermost_loop_iterations(&self) -> Option<usize> {
+        let mut depth = 0;
+        for index in (0..self.cursor.min(self.sequence.len())).rev() {
+            match self.sequence[index] {
+                ScriptCommand::CloseBracket => depth += 1,
+                command if command.opens_scope() && depth == 0 => {
+                    return Some(self.loop_iterations.get(&index).copied().unwrap_or(0));
+                }
+                command if command.opens_scope() => depth -= 1,
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Sent the first time a run reaches the end of its script.
+#[derive(Event, Debug)]
+pub struct ScriptFinished;
+
+/// Sent whenever a submitted run ends.
+#[derive(Event, Debug, Clone)]
+pub struct RunOutcome {
+    pub result: RunResult,
+    /// The submitted script, including added brackets.
+    pub script: Vec<ScriptCommand>,
+    /// How many ticks the run took.
+    pub ticks: usize,
+    /// How many commands the script was allowed to have.
+    pub command_limit: usize,
+    /// How many times each command was executed.
+    pub executed: HashMap<ScriptCommand, usize>,
+    /// Whether the run reached the last checkpoint needed to complete the
+    /// level.
+    pub completed_level: bool,
+    /// Whether the run was helped by assist mode, so that it does not count
+    /// for records.
+    pub assisted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunResult {
+    Died,
+    Checkpoint(IVec2),
+    /// Reached a goal, which completes the level.
+    Goal(IVec2),
+    /// Respawned or went back to editing before the run ended.
+    Aborted,
+}
+
+/// Dev-only manual movement. Unlike the editor keys, these are read as held
+/// keys at the start of every tick, so holding a key keeps moving.
+fn debug_actions(
+    input: &ButtonInput<KeyCode>,
+    bindings: &KeyBindings,
+    state: &mut PlayerState,
+) -> Option<ScriptCommand> {
+    let pressed_or_held = |action: KeyAction| input.pressed(bindings.key(action));
 
     // Collect directional input.
     let mut action = None;
 
     let mut facing = 0;
-    if pressed_or_held(KeyCode::KeyA) || pressed_or_held(KeyCode::ArrowLeft) {
+    if pressed_or_held(KeyAction::DevLeft) {
         facing -= 1;
     }
-    if pressed_or_held(KeyCode::KeyD) || pressed_or_held(KeyCode::ArrowRight) {
+    if pressed_or_held(KeyAction::DevRight) {
         facing += 1;
     }
     if facing != 0 {
         if state.x_dir != facing {
-            return Some(ScriptCommand::Turn);
+            return Some(ScriptCommand::Face(Facing::from_x_dir(facing)));
         }
         action = Some(ScriptCommand::Walk)
     }
-    if pressed_or_held(KeyCode::KeyW) || pressed_or_held(KeyCode::ArrowUp) {
+    if pressed_or_held(KeyAction::DevClimb) {
         action = Some(ScriptCommand::Climb)
     }
-    if pressed_or_held(KeyCode::KeyS) || pressed_or_held(KeyCode::ArrowDown) {
+    if pressed_or_held(KeyAction::DevDrop) {
         action = Some(ScriptCommand::Drop)
     }
-    if pressed_or_held(KeyCode::Space) {
+    if pressed_or_held(KeyAction::DevIdle) {
         action = Some(ScriptCommand::Idle)
     }
     action
@@ -127,31 +300,270 @@ fn add_unlock(
                     layout: editor_assets.atlas.clone(),
                     index: EditorAssets::get_atlas_index(&config.command),
                 },
+                UnlockAnimation::default(),
+                PaletteItem(config.command),
+                CommandTooltip(config.command),
+                Interaction::default(),
+                SilentInteraction,
+                // Used to reorder the palette with the mouse.
+                RelativeCursorPosition::default(),
             ));
         });
 }
 
+fn update_unlock_progress(
+    level: Res<Level>,
+    mut label: Query<&mut Text, With<UnlockProgressLabel>>,
+) {
+    let (unlocked, total) = level.unlock_progress();
+    // Levels that start with everything unlocked count as complete.
+    let text = match unlocked >= total {
+        true => "All commands unlocked ".to_string(),
+        false => format!("{unlocked}/{total} commands unlocked "),
+    };
+    for mut label in &mut label {
+        label.sections[0].value.clone_from(&text);
+    }
+}
+
+fn update_checkpoint_progress(
+    level: Res<Level>,
+    mut label: Query<&mut Text, With<CheckpointProgressLabel>>,
+) {
+    // Linear levels only ever move on to the next checkpoint.
+    let text = match level.objective {
+        Objective::Linear => String::new(),
+        Objective::AllCheckpoints => format!(
+            "{}/{} checkpoints ",
+            level.visited.len(),
+            level.unlocks.len()
+        ),
+    };
+    for mut label in &mut label {
+        label.sections[0].value.clone_from(&text);
+    }
+}
+
+/// Pop-in animation with a short glow for a newly unlocked command.
+#[derive(Component)]
+struct UnlockAnimation(Timer);
+
+impl Default for UnlockAnimation {
+    fn default() -> Self {
+        Self(Timer::from_seconds(0.6, TimerMode::Once))
+    }
+}
+
+fn animate_unlock(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut icons: Query<(
+        Entity,
+        &mut UnlockAnimation,
+        &mut Transform,
+        &mut BackgroundColor,
+    )>,
+    mut commands: Commands,
+) {
+    for (entity, mut animation, mut transform, mut background) in &mut icons {
+        animation.0.tick(time.delta());
+        let t = animation.0.fraction();
+
+        // Scale up from nothing with a slight overshoot (ease out back).
+        const C1: f32 = 1.70158;
+        const C3: f32 = C1 + 1.0;
+        let scale = 1.0 + C3 * (t - 1.0).powi(3) + C1 * (t - 1.0).powi(2);
+        transform.scale = match settings.reduce_motion {
+            true => Vec3::ONE,
+            false => Vec2::splat(scale).extend(1.0),
+        };
+        // Glow behind the icon which fades out.
+        *background = BackgroundColor(Color::srgba(1.0, 0.9, 0.4, 1.0 - t));
+
+        if animation.0.finished() {
+            transform.scale = Vec3::ONE;
+            *background = BackgroundColor(Color::NONE);
+            commands.entity(entity).remove::<UnlockAnimation>();
+        }
+    }
+}
+
+/// The player flies back from where the run ended to the checkpoint, fading
+/// out on the way. This is only visual, the player is already at the
+/// checkpoint.
+#[derive(Component)]
+struct ReturnToCheckpoint {
+    from: IVec2,
+    timer: Timer,
+}
+
+impl ReturnToCheckpoint {
+    const DURATION: f32 = 0.4;
+    /// Lowest sprite alpha, reached halfway.
+    const MIN_ALPHA: f32 = 0.2;
+}
+
+/// The player touched a hazard and plays the death animation. Nothing moves
+/// and input is ignored until the timer runs out and the player respawns.
+#[derive(Component)]
+pub struct Respawning(pub Timer);
+
+impl Respawning {
+    pub const DURATION: f32 = 0.5;
+}
+
+/// Hazards cannot kill the player for a moment after respawning, which is
+/// shown by blinking.
+#[derive(Component)]
+struct Invulnerable(Timer);
+
+impl Invulnerable {
+    /// Blinks per second.
+    const BLINK_RATE: f32 = 8.0;
+    const DURATION: f32 = 1.0;
+}
+
+fn tick_respawning(time: Res<Time>, mut player: Query<&mut Respawning>) {
+    for mut respawning in &mut player {
+        respawning.0.tick(time.delta());
+    }
+}
+
+fn fade_dying(mut player: Query<(&Respawning, &mut Sprite)>) {
+    for (respawning, mut sprite) in &mut player {
+        sprite.color.set_alpha(1.0 - respawning.0.fraction());
+    }
+}
+
+fn flash_invulnerable(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut player: Query<
+        (Entity, &mut Invulnerable, &mut Sprite),
+        (With<Player>, Without<ReturnToCheckpoint>),
+    >,
+    mut commands: Commands,
+) {
+    for (entity, mut invulnerable, mut sprite) in &mut player {
+        invulnerable.0.tick(time.delta());
+        if invulnerable.0.finished() {
+            sprite.color.set_alpha(1.0);
+            commands.entity(entity).remove::<Invulnerable>();
+            continue;
+        }
+        let blink = (invulnerable.0.elapsed_secs() * Invulnerable::BLINK_RATE) as u32 % 2 == 1;
+        let alpha = match blink && !settings.reduce_motion {
+            true => 0.3,
+            false => 1.0,
+        };
+        sprite.color.set_alpha(alpha);
+    }
+}
+
+fn animate_return(
+    time: Res<Time>,
+    input: Res<ButtonInput<KeyCode>>,
+    state: Res<PlayerState>,
+    grid: Res<WorldGrid>,
+    mut player: Query<
+        (
+            Entity,
+            &mut ReturnToCheckpoint,
+            &GridTransform,
+            &mut Transform,
+            &mut Sprite,
+        ),
+        With<Player>,
+    >,
+    mut commands: Commands,
+) {
+    let Ok((entity, mut ret, pos, mut transform, mut sprite)) = player.get_single_mut() else {
+        return;
+    };
+    ret.timer.tick(time.delta());
+    // Skip with any key, or when the next run already started.
+    let skip = input.get_just_pressed().next().is_some() || state.animation.is_some();
+    if ret.timer.finished() || skip {
+        sprite.color.set_alpha(1.0);
+        commands.entity(entity).remove::<ReturnToCheckpoint>();
+        return;
+    }
+
+    // Ease in and out.
+    let t = ret.timer.fraction();
+    let eased = t * t * (3.0 - 2.0 * t);
+    let from = grid.project_to_world(ret.from.as_vec2());
+    let to = grid.project_to_world(pos.0.as_vec2());
+    transform.translation = from.lerp(to, eased).extend(PLAYER_Z);
+    let dip = 1.0 - (2.0 * t - 1.0).abs();
+    sprite
+        .color
+        .set_alpha(1.0 - dip * (1.0 - ReturnToCheckpoint::MIN_ALPHA));
+}
+
 fn respawn(
     mut state: ResMut<PlayerState>,
-    mut player: Query<(&mut GridTransform, &mut NextGridTransform), With<Player>>,
-    obstacles: Query<&GridTransform, (With<Obstacle>, Without<Player>)>,
+    mut player: Query<
+        (
+            Entity,
+            &mut GridTransform,
+            &mut NextGridTransform,
+            Option<&Respawning>,
+            Has<Invulnerable>,
+        ),
+        With<Player>,
+    >,
+    obstacles: Query<(&GridTransform, &Obstacle), Without<Player>>,
     input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
     mut level: ResMut<Level>,
     mut reset: EventWriter<Reset>,
+    mut outcome: EventWriter<RunOutcome>,
+    mut level_complete: EventWriter<LevelComplete>,
     mut editor_state: ResMut<EditorState>,
+    tick_count: Res<TickCount>,
+    settings: Res<Settings>,
+    mut progress: ResMut<Progress>,
+    selection: Res<LevelSelection>,
     mut commands: Commands,
 ) {
-    let Ok((mut pos, mut new_pos)) = player.get_single_mut() else {
+    let Ok((entity, mut pos, mut new_pos, respawning, invulnerable)) = player.get_single_mut()
+    else {
         return;
     };
+    // Wait for the death animation, ignoring input.
+    if respawning.is_some_and(|respawning| !respawning.0.finished()) {
+        return;
+    }
+    // Nowhere to respawn before the level is loaded.
+    if level.get_spawn().is_none() {
+        return;
+    }
+    let running = !editor_state.enabled;
+    let command_limit = level.command_count;
+    let mut result = RunResult::Aborted;
+    let mut completed_level = false;
 
     let mut collided = false;
-    for o_pos in &obstacles {
-        collided |= o_pos.0 == pos.0;
+    for (o_pos, obstacle) in &obstacles {
+        let active = obstacle.timing.is_active(tick_count.0);
+        if o_pos.0 == pos.0 && obstacle.kind.is_lethal() && active && !invulnerable {
+            log::info!("Killed by {:?}", obstacle.kind);
+            collided = true;
+            result = RunResult::Died;
+        }
+    }
+    if level.is_solid(pos.0) {
+        log::info!("Pushed into a wall");
+        collided = true;
+        result = RunResult::Died;
     }
 
     if level.is_checkpoint(pos.0) && level.last_checkpoint != pos.0 {
         level.last_checkpoint = pos.0;
+        if settings.difficulty == Difficulty::Assist {
+            level.assisted.insert(pos.0);
+        }
 
         let (new_unlock, command_count) = *level.unlocks.get(&pos.0).expect("unknown checkpoint");
         if let Some(script_command) = new_unlock {
@@ -163,17 +575,117 @@ fn respawn(
             }
         }
         level.command_count = level.command_count.max(command_count);
+        progress.reach(level_index(&selection), pos.0, &level);
+        completed_level = level.visit(pos.0);
+        log::info!(
+            "Reached checkpoint using {:?}",
+            state.executed_commands().collect::<Vec<_>>()
+        );
+
+        collided = true;
+        result = RunResult::Checkpoint(pos.0);
+    }
+
+    if level.has_collectible(pos.0) {
+        level.collected.insert(pos.0);
+        log::info!(
+            "Collected {} of {} items",
+            level.collected.len(),
+            level.collectibles.len()
+        );
+    }
+
+    if let Some(&id) = level.keys.get(&pos.0) {
+        if !level.held_keys.contains(&id) {
+            log::info!("Picked up key {id}");
+            level.held_keys.insert(id);
+        }
+    }
 
+    if level.is_goal(pos.0) && level.all_collected() {
+        log::info!(
+            "Reached goal using {:?}",
+            state.executed_commands().collect::<Vec<_>>()
+        );
+        completed_level = true;
         collided = true;
+        result = RunResult::Goal(pos.0);
+    }
+
+    if respawning.is_some() {
+        collided = true;
+        result = RunResult::Died;
+    } else if result == RunResult::Died {
+        // Reaching a checkpoint or goal wins over dying.
+        // Respawn once the death animation is over.
+        commands
+            .entity(entity)
+            .insert(Respawning(Timer::from_seconds(
+                Respawning::DURATION,
+                TimerMode::Once,
+            )));
+        return;
+    }
+
+    // A level completed by visiting every checkpoint counts as assisted if
+    // any of them was reached in assist mode.
+    let assisted = settings.difficulty == Difficulty::Assist
+        || (completed_level && !level.assisted.is_empty());
+    if completed_level {
+        progress.complete(level_index(&selection), state.sequence.len(), assisted);
+        level_complete.send(LevelComplete);
+    }
+
+    // Abort the run and go back to the script as it was typed.
+    let abort = input.just_pressed(bindings.key(KeyAction::Edit)) && !editor_state.enabled;
+    if abort {
+        editor_state.undo_submit();
     }
 
-    if input.just_pressed(KeyCode::KeyR) || collided {
+    if input.just_pressed(bindings.key(KeyAction::Respawn)) || collided || abort {
+        if running {
+            outcome.send(RunOutcome {
+                result,
+                script: state.sequence.clone(),
+                ticks: tick_count.0,
+                command_limit,
+                executed: state.executed.clone(),
+                completed_level,
+                assisted,
+            });
+        }
+
+        if respawning.is_some() {
+            commands
+                .entity(entity)
+                .remove::<Respawning>()
+                .insert(Invulnerable(Timer::from_seconds(
+                    Invulnerable::DURATION,
+                    TimerMode::Once,
+                )));
+        }
+        if pos.0 != level.last_checkpoint && !settings.reduce_motion {
+            commands.entity(entity).insert(ReturnToCheckpoint {
+                from: pos.0,
+                timer: Timer::from_seconds(ReturnToCheckpoint::DURATION, TimerMode::Once),
+            });
+        }
+
         // respawn, reset all properties
         pos.0 = level.last_checkpoint;
         new_pos.0 = level.last_checkpoint;
         state.x_dir = 1;
         state.cursor = 0;
         state.animation = None;
+        state.executed.clear();
+        state.loop_iterations.clear();
+        state.visited.clear();
+        state.completed_passes = 0;
+        state.marks.clear();
+        state.trace.clear();
+        // Every retry has to collect everything again.
+        level.collected.clear();
+        level.held_keys.clear();
         // allow editing again
         editor_state.enabled = true;
         reset.send(Reset);
@@ -181,16 +693,127 @@ fn respawn(
     }
 }
 
-fn update_animation(
+fn cycle_playback_speed(mut settings: ResMut<Settings>) {
+    settings.playback_speed = settings.playback_speed.next();
+    persistence::save(Settings::PLAYBACK_SPEED_KEY, &settings.playback_speed);
+}
+
+/// Show whether autoplay is on, which can change with G or when submitting,
+/// and how fast it runs.
+fn show_autoplay_mode(
+    state: Res<PlayerState>,
+    settings: Res<Settings>,
+    mut label: Query<&mut Text, With<AutoplayLabel>>,
+    new_label: Query<(), Added<AutoplayLabel>>,
+    mut shown: Local<(bool, PlaybackSpeed)>,
+) {
+    let mode = (state.autoplay, settings.playback_speed);
+    if *shown == mode && new_label.is_empty() {
+        return;
+    }
+    *shown = mode;
+
+    let default_style = TextStyle {
+        font_size: 24.0,
+        color: LABEL_TEXT,
+        ..Default::default()
+    };
+    let big_style = TextStyle {
+        font_size: 48.0,
+        color: LABEL_TEXT,
+        ..Default::default()
+    };
+    for mut label in &mut label {
+        *label = match state.autoplay {
+            true => Text::from_sections([
+                TextSection::new(AutoplayLabel::ENABLED, default_style.clone()),
+                TextSection::new(
+                    format!(" (speed X: {})", settings.playback_speed.name()),
+                    default_style.clone(),
+                ),
+            ])
+            .with_no_wrap(),
+            false => Text::from_sections([
+                TextSection::new(AutoplayLabel::DISABLED_BIG, big_style.clone()),
+                TextSection::new(AutoplayLabel::DISABLED, default_style.clone()),
+            ])
+            .with_no_wrap(),
+        };
+    }
+}
+
+/// Go back to the state before the last tick in manual mode, including the
+/// hazards.
+fn step_back(
     input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    editor_state: Res<EditorState>,
+    tick: Res<AnimationTick>,
+    mut tick_count: ResMut<TickCount>,
+    mut state: ResMut<PlayerState>,
+    mut level: ResMut<Level>,
+    mut player: Query<
+        (&mut GridTransform, &mut NextGridTransform),
+        (With<Player>, Without<Respawning>),
+    >,
+    mut rewind: EventWriter<Rewind>,
+    mut commands: Commands,
+) {
+    if !input.just_pressed(bindings.key(KeyAction::StepBack)) {
+        return;
+    }
+    // Only between ticks, so that nothing is halfway through a move.
+    if editor_state.enabled || state.autoplay || !tick.0.finished() {
+        return;
+    }
+    let Ok((mut pos, mut next_pos)) = player.get_single_mut() else {
+        return;
+    };
+    let Some(step) = state.trace.pop() else {
+        return;
+    };
+    pos.0 = step.pos;
+    next_pos.0 = step.pos;
+    state.cursor = step.cursor;
+    state.x_dir = step.x_dir;
+    state.marks = step.marks;
+    state.loop_iterations = step.loop_iterations;
+    state.animation = None;
+    // Put back what was picked up on the way.
+    level.collected = step.collected;
+    level.held_keys = step.held_keys;
+    tick_count.0 = tick_count.0.saturating_sub(1);
+    rewind.send(Rewind);
+    // Nothing ran yet from the restored state.
+    commands.add(ShowEditor {
+        active: Some((usize::MAX, true)),
+    });
+}
+
+/// How many commands Shift+F steps through.
+const MULTI_STEP: usize = 5;
+/// Duration multiplier for the ticks of a multi-step.
+const MULTI_STEP_SPEED: f32 = 0.1;
+
+pub(super) fn update_animation(
+    input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
     mut tick: ResMut<AnimationTick>,
+    mut tick_count: ResMut<TickCount>,
     mut state: ResMut<PlayerState>,
-    mut player: Query<(&GridTransform, &mut NextGridTransform), With<Player>>,
+    // Input is ignored while dying.
+    mut player: Query<
+        (&GridTransform, &mut NextGridTransform),
+        (With<Player>, Without<Respawning>),
+    >,
     assets: Option<Res<PlayerAssets>>,
     level: Res<Level>,
     editor_state: Res<EditorState>,
+    settings: Res<Settings>,
     mut tick_start: EventWriter<TickStart>,
-    mut autoplay_label: Query<&mut Text, With<AutoplayLabel>>,
+    mut finished: EventWriter<ScriptFinished>,
+    mut queued_steps: Local<usize>,
+    mut step_requested: Local<bool>,
     mut commands: Commands,
 ) {
     let Ok((pos, mut next_pos)) = player.get_single_mut() else {
@@ -198,35 +821,27 @@ fn update_animation(
     };
 
     // toggle autoplay
-    if input.just_pressed(KeyCode::KeyG) {
-        let mut autoplay_label = autoplay_label.single_mut();
-
+    if input.just_pressed(bindings.key(KeyAction::ToggleAutoplay)) {
         state.autoplay = !state.autoplay;
-        let default_style = TextStyle {
-            font_size: 24.0,
-            color: LABEL_TEXT,
-            ..Default::default()
-        };
-        let big_style = TextStyle {
-            font_size: 48.0,
-            color: LABEL_TEXT,
-            ..Default::default()
-        };
-        *autoplay_label = match state.autoplay {
-            true => Text::from_section(AutoplayLabel::ENABLED, default_style).with_no_wrap(),
-            false => Text::from_sections([
-                TextSection::new(AutoplayLabel::DISABLED_BIG, big_style),
-                TextSection::new(AutoplayLabel::DISABLED, default_style),
-            ])
-            .with_no_wrap(),
-        };
     }
 
     // make sure that the editor is disabled before allowing any movement
     if editor_state.enabled {
+        *queued_steps = 0;
+        *step_requested = false;
         return;
     }
 
+    // Shift+F steps through several commands in quick succession.
+    let shift = input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let step = bindings.key(KeyAction::Step);
+    if !state.autoplay && shift && input.just_pressed(step) {
+        *queued_steps = MULTI_STEP;
+    } else if input.just_pressed(step) {
+        // Remember presses during an animation for when it is over.
+        *step_requested = true;
+    }
+
     if !tick.0.finished() {
         return;
     }
@@ -235,54 +850,102 @@ fn update_animation(
 
     // check if we have manual controls to execute
     if cfg!(feature = "dev") {
-        state.animation = debug_actions(&input, &mut state).and_then(|action| {
-            if let ScriptCommand::Turn = action {
-                state.x_dir *= -1;
-            };
+        state.animation = debug_actions(&input, &bindings, &mut state).and_then(|action| {
+            state.x_dir = action.apply_facing(state.x_dir);
             let assets = assets.as_ref().unwrap();
             level.check_valid(pos.0, action, state.x_dir, assets)
         })
     };
 
     // check if we have script to execute
-    if input.pressed(KeyCode::KeyF) || state.autoplay {
-        let (script_index, animation) =
-            action_interpreter(&mut state, pos, &level, assets.unwrap());
-        state.animation = animation;
-        commands.add(ShowEditor {
-            active: Some((script_index, state.animation.is_some())),
-        });
+    let queued = *queued_steps > 0;
+    // Stop at the end of each animation until the next press if requested.
+    let single_step = settings.pause_between_steps || settings.on_submit == SubmitMode::Step;
+    let stepping = match single_step && !state.autoplay {
+        true => *step_requested,
+        false => input.pressed(step),
+    };
+    *step_requested = false;
+    if stepping || state.autoplay || queued {
+        let passes = state.completed_passes;
+        let before = TraceStep {
+            pos: pos.0,
+            cursor: state.cursor,
+            x_dir: state.x_dir,
+            marks: state.marks.clone(),
+            loop_iterations: state.loop_iterations.clone(),
+            collected: level.collected.clone(),
+            held_keys: level.held_keys.clone(),
+        };
+        // There is nothing to run before a script is submitted.
+        let step = action_interpreter(&mut state, pos.0, tick_count.0, &level, &assets.unwrap());
+        if matches!(step, Some((_, Some(_)))) {
+            if state.trace.len() == MAX_TRACE {
+                state.trace.remove(0);
+            }
+            state.trace.push(before);
+        }
+        if passes == 0 && state.completed_passes > 0 {
+            finished.send(ScriptFinished);
+        }
+        if let Some((script_index, animation)) = step {
+            state.animation = animation;
+            commands.add(ShowEditor {
+                active: Some((script_index, state.animation.is_some())),
+            });
+            // Stop early when a command fails.
+            *queued_steps = match state.animation {
+                Some(_) => queued_steps.saturating_sub(1),
+                None => 0,
+            };
+        } else {
+            *queued_steps = 0;
+        }
     }
 
-    let multiplier = if state.autoplay && input.pressed(KeyCode::KeyF) {
+    let multiplier = if queued {
+        MULTI_STEP_SPEED
+    } else if state.autoplay && input.pressed(step) {
         0.25
     } else {
         1.0
     };
+    // The sticky speed applies to all of autoplay, including fast forward.
+    let multiplier = match state.autoplay {
+        true => multiplier * settings.playback_speed.duration_scale(),
+        false => multiplier,
+    };
 
     if let Some(animation) = &state.animation {
         tick_start.send(TickStart);
+        tick_count.0 += 1;
         tick.0.set_duration(animation.duration.mul_f32(multiplier));
         next_pos.0 = pos.0 + animation.final_offset(state.x_dir);
         tick.0.reset();
     } else if state.autoplay {
+        // Nothing moves while waiting, so this only sets how soon the next
+        // command is tried.
+        let idle = settings.idle_cadence.seconds();
         tick.0
-            .set_duration(Duration::from_secs_f32(0.25).mul_f32(multiplier));
+            .set_duration(Duration::from_secs_f32(idle).mul_f32(multiplier));
         tick.0.reset();
     }
 }
 
+/// How many cells `Mark` remembers before forgetting the oldest one.
+const MAX_MARKS: usize = 16;
+
 /// Returns the index of the script item that should be highlighted and maybe
-/// the animation that should be played.
-fn action_interpreter(
+/// the animation that should be played, or `None` if the script is empty.
+pub(super) fn action_interpreter(
     state: &mut PlayerState,
-    pos: &GridTransform,
+    pos: IVec2,
+    ticks: usize,
     level: &Level,
-    assets: Res<PlayerAssets>,
-) -> (usize, Option<AnimationResource>) {
+    assets: &PlayerAssets,
+) -> Option<(usize, Option<AnimationResource>)> {
     if state.sequence.is_empty() {
-        log::error!("The sequence should never be empty!");
-        return (0, None);
+        return None;
     }
 
     // Rename for convenience.
@@ -296,10 +959,10 @@ fn action_interpreter(
         for i in 1..=cursor {
             match sequence[cursor - i] {
                 ScriptCommand::CloseBracket => count += 1,
-                ScriptCommand::OpenBracket if count == 0 => {
+                command if command.opens_scope() && count == 0 => {
                     return cursor - i;
                 }
-                ScriptCommand::OpenBracket => count -= 1,
+                command if command.opens_scope() => count -= 1,
                 _ => {}
             }
         }
@@ -309,7 +972,7 @@ fn action_interpreter(
         let mut count = 0;
         for (i, cmd) in sequence.iter().enumerate().skip(cursor) {
             match cmd {
-                ScriptCommand::OpenBracket => count += 1,
+                cmd if cmd.opens_scope() => count += 1,
                 ScriptCommand::CloseBracket if count == 0 => {
                     return (i + 1) % sequence.len();
                 }
@@ -323,28 +986,101 @@ fn action_interpreter(
     // Prevent infinite loops by limiting the number of iterations.
     for _ in 0..sequence.len() {
         let item_index = *cursor;
+        state.visited.insert(item_index);
         match sequence[*cursor] {
-            ScriptCommand::OpenBracket => {}
+            ScriptCommand::OpenBracket | ScriptCommand::Repeat(_) => {
+                // Jumping back skips the open bracket, so this is a fresh entry.
+                state.loop_iterations.insert(*cursor, 0);
+            }
             ScriptCommand::CloseBracket => {
-                // Go back to matching open bracket.
-                *cursor = find_matching_open_bracket(*cursor);
+                let open = find_matching_open_bracket(*cursor);
+                let iterations = state.loop_iterations.get(&open).copied().unwrap_or(0);
+                match sequence[open] {
+                    // The body ran often enough, so move on past the bracket.
+                    ScriptCommand::Repeat(count) if iterations + 1 >= count as usize => {}
+                    _ => {
+                        // Go back to matching open bracket.
+                        *cursor = open;
+                        *state.loop_iterations.entry(open).or_default() += 1;
+                    }
+                }
+            }
+            ScriptCommand::Sync(parity) if !parity.matches(ticks) => {
+                // Wait a tick without moving on.
+                match level.check_valid(pos, ScriptCommand::Idle, state.x_dir, assets) {
+                    Some(anim) => return Some((item_index, Some(anim))),
+                    None => {
+                        *cursor = find_matching_close_bracket(*cursor);
+                        return Some((item_index, None));
+                    }
+                }
+            }
+            ScriptCommand::Sync(parity) => {
+                // The right tick, so move on right away.
+                *state
+                    .executed
+                    .entry(ScriptCommand::Sync(parity))
+                    .or_default() += 1;
+            }
+            ScriptCommand::Face(facing) if facing.x_dir() == state.x_dir => {
+                // Already facing that way, so move on without spending a tick.
+                *state
+                    .executed
+                    .entry(ScriptCommand::Face(facing))
+                    .or_default() += 1;
+            }
+            ScriptCommand::Breakpoint if state.autoplay => {
+                // Pause and continue after the breakpoint once resumed.
+                state.autoplay = false;
+                *cursor = (*cursor + 1) % sequence.len();
+                if *cursor == 0 {
+                    state.completed_passes += 1;
+                }
+                return Some((item_index, None));
+            }
+            ScriptCommand::Breakpoint => {
+                // Manual steps go straight past it.
+            }
+            ScriptCommand::Mark => {
+                // Remember the cell and move on without spending a tick.
+                if state.marks.len() == MAX_MARKS {
+                    state.marks.remove(0);
+                }
+                state.marks.push(pos);
+                *state.executed.entry(ScriptCommand::Mark).or_default() += 1;
             }
             command => {
-                match level.check_valid(pos.0, command, state.x_dir, &assets) {
+                let anim = match command {
+                    ScriptCommand::Return => state
+                        .marks
+                        .last()
+                        .filter(|&&mark| !level.is_solid(mark) && level.is_solid(mark + DOWN))
+                        .map(|&mark| assets.idle.teleport(mark - pos, state.x_dir)),
+                    command => level.check_valid(pos, command, state.x_dir, assets),
+                };
+                match anim {
                     Some(anim) => {
+                        if command == ScriptCommand::Return {
+                            state.marks.pop();
+                        }
                         // Update the cursor.
                         *cursor = (*cursor + 1) % sequence.len();
+                        if *cursor == 0 {
+                            state.completed_passes += 1;
+                        }
                         // Set the animation.
-                        if let ScriptCommand::Turn = command {
-                            state.x_dir *= -1
-                        };
+                        state.x_dir = command.apply_facing(state.x_dir);
+                        *state.executed.entry(command).or_default() += 1;
 
-                        return (item_index, Some(anim.clone()));
+                        return Some((item_index, Some(anim.clone())));
                     }
                     None => {
                         // Skip to the end of scope.
                         *cursor = find_matching_close_bracket(*cursor);
-                        return (item_index, None);
+                        if *cursor == 0 {
+                            state.completed_passes += 1;
+                        }
+                        return Some((item_index, None));
                     }
                 }
             }
@@ -352,10 +1088,85 @@ fn action_interpreter(
         // Try the next command.
         *cursor += 1;
         *cursor %= sequence.len();
+        if *cursor == 0 {
+            state.completed_passes += 1;
+        }
     }
 
     // No action from the script was possible.
-    (*cursor, None)
+    Some((*cursor, None))
+}
+
+fn show_script_finished(
+    mut finished: EventReader<ScriptFinished>,
+    mut reset: EventReader<Reset>,
+    editor_state: Res<EditorState>,
+    mut label: Query<&mut Text, With<ScriptFinishedLabel>>,
+) {
+    let text = if finished.read().count() > 0 {
+        "Script finished - no checkpoint reached"
+    } else if reset.read().count() > 0 || editor_state.enabled {
+        ""
+    } else {
+        return;
+    };
+    for mut label in &mut label {
+        if label.sections[0].value != text {
+            label.sections[0].value = text.to_string();
+        }
+    }
+}
+
+/// Covers the screen for a moment after the level was completed.
+#[derive(Component)]
+struct Celebration(Timer);
+
+impl Celebration {
+    const SECONDS: f32 = 2.5;
+}
+
+fn celebrate_level_complete(mut complete: EventReader<LevelComplete>, mut commands: Commands) {
+    complete.clear();
+    commands
+        .ui_root()
+        .insert((
+            Name::new("Celebration"),
+            Celebration(Timer::from_seconds(Celebration::SECONDS, TimerMode::Once)),
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.4)),
+            ZIndex::Global(20),
+            StateScoped(Screen::Gameplay),
+        ))
+        .with_children(|children| {
+            children.header("Level complete!");
+        });
+}
+
+fn fade_celebration(
+    time: Res<Time>,
+    mut celebrations: Query<(Entity, &mut Celebration)>,
+    mut commands: Commands,
+) {
+    for (entity, mut celebration) in &mut celebrations {
+        if celebration.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn update_loop_counter(
+    state: Res<PlayerState>,
+    editor_state: Res<EditorState>,
+    mut label: Query<&mut Text, With<LoopCounterLabel>>,
+) {
+    let text = match state.innermost_loop_iterations() {
+        Some(iterations) if !editor_state.enabled => format!("Loop repeated {iterations}x"),
+        _ => String::new(),
+    };
+    for mut label in &mut label {
+        if label.sections[0].value != text {
+            label.sections[0].value.clone_from(&text);
+        }
+    }
 }
 
 fn camera_follow_player(
@@ -373,3 +1184,210 @@ fn camera_follow_player(
         camera.translation = target.lerp(camera.translation, old_part);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo::{
+        obstacle::{HazardKind, HazardTiming, MovementPattern, SpawnObstacle},
+        testing::{assets, floor, run_script},
+    };
+
+    fn player_pos(world: &mut World) -> IVec2 {
+        let mut player = world.query_filtered::<&GridTransform, With<Player>>();
+        player.single(world).0
+    }
+
+    /// Check where the player ended up, like after every tick.
+    fn respawn_once(world: &mut World) {
+        world.init_resource::<Events<Reset>>();
+        world.init_resource::<Events<RunOutcome>>();
+        world.init_resource::<Events<LevelComplete>>();
+        world.run_system_once(respawn);
+    }
+
+    #[test]
+    fn reaching_a_goal_completes_the_level() {
+        let mut world = World::new();
+        let mut level = floor();
+        level.goals.insert(IVec2::new(2, 1));
+        assert_eq!(
+            run_script(&mut world, level, vec![ScriptCommand::Walk], 2),
+            IVec2::new(2, 1)
+        );
+
+        respawn_once(&mut world);
+        assert_eq!(world.resource::<Events<LevelComplete>>().len(), 1);
+        assert_eq!(player_pos(&mut world), IVec2::new(0, 1));
+    }
+
+    #[test]
+    fn respawning_puts_collectibles_back() {
+        let mut world = World::new();
+        let mut level = floor();
+        level.collectibles.insert(IVec2::new(1, 1));
+        run_script(&mut world, level, vec![ScriptCommand::Walk], 1);
+        respawn_once(&mut world);
+        assert!(world.resource::<Level>().all_collected());
+
+        world
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyR);
+        respawn_once(&mut world);
+        assert!(!world.resource::<Level>().all_collected());
+        assert_eq!(world.resource::<Events<Reset>>().len(), 1);
+    }
+
+    #[test]
+    fn picking_up_a_key_unlocks_its_door() {
+        let mut world = World::new();
+        let mut level = floor();
+        level.keys.insert(IVec2::new(1, 1), 7);
+        level.doors.insert(IVec2::new(3, 1), 7);
+        run_script(&mut world, level, vec![ScriptCommand::Walk], 1);
+        respawn_once(&mut world);
+        assert!(!world.resource::<Level>().is_solid(IVec2::new(3, 1)));
+    }
+
+    #[test]
+    fn stepping_back_returns_to_earlier_ticks() {
+        let mut world = World::new();
+        assert_eq!(
+            run_script(&mut world, floor(), vec![ScriptCommand::Walk], 3),
+            IVec2::new(3, 1)
+        );
+        world.resource_mut::<PlayerState>().autoplay = false;
+        world.init_resource::<Events<Rewind>>();
+
+        for (ticks, pos) in [
+            (2, IVec2::new(2, 1)),
+            (1, IVec2::new(1, 1)),
+            (0, IVec2::new(0, 1)),
+        ] {
+            let mut input = world.resource_mut::<ButtonInput<KeyCode>>();
+            input.reset_all();
+            input.press(KeyCode::KeyZ);
+            world.run_system_once(step_back);
+            assert_eq!(player_pos(&mut world), pos);
+            assert_eq!(world.resource::<TickCount>().0, ticks);
+            assert_eq!(world.resource::<PlayerState>().cursor, 0);
+        }
+        assert!(world.resource::<PlayerState>().trace.is_empty());
+    }
+
+    #[test]
+    fn inactive_hazard_does_not_respawn() {
+        let mut world = World::new();
+        run_script(&mut world, floor(), vec![ScriptCommand::Walk], 1);
+        SpawnObstacle {
+            pos: IVec2::new(1, 1),
+            pattern: MovementPattern::PingPong(IVec2::new(1, 1)),
+            kind: HazardKind::Spikes,
+            animation: default(),
+            timing: HazardTiming {
+                period: 2,
+                active_phase: 0,
+            },
+        }
+        .apply(&mut world);
+
+        respawn_once(&mut world);
+        let mut player = world.query_filtered::<Has<Respawning>, With<Player>>();
+        assert!(!player.single(&world));
+        assert_eq!(player_pos(&mut world), IVec2::new(1, 1));
+    }
+
+    #[test]
+    fn hazard_starts_dying_before_respawning() {
+        let mut world = World::new();
+        run_script(&mut world, floor(), vec![ScriptCommand::Walk], 2);
+        SpawnObstacle {
+            pos: IVec2::new(2, 1),
+            pattern: MovementPattern::PingPong(IVec2::new(2, 1)),
+            kind: HazardKind::Spikes,
+            animation: default(),
+            timing: default(),
+        }
+        .apply(&mut world);
+
+        respawn_once(&mut world);
+        assert_eq!(player_pos(&mut world), IVec2::new(2, 1));
+        assert!(world.resource::<Events<Reset>>().is_empty());
+
+        // Only respawn once the death animation is over.
+        let mut player = world.query_filtered::<&mut Respawning, With<Player>>();
+        let mut respawning = player.single_mut(&mut world);
+        respawning
+            .0
+            .tick(Duration::from_secs_f32(Respawning::DURATION));
+        respawn_once(&mut world);
+        assert_eq!(player_pos(&mut world), IVec2::new(0, 1));
+        assert_eq!(world.resource::<Events<Reset>>().len(), 1);
+    }
+
+    #[test]
+    fn interpreter_returns_none_for_empty_script() {
+        let step = action_interpreter(
+            &mut PlayerState::new(Vec::new()),
+            IVec2::new(0, 1),
+            0,
+            &floor(),
+            &assets(),
+        );
+        assert!(step.is_none());
+    }
+
+    #[test]
+    fn interpreter_repeats_a_single_command() {
+        let mut state = PlayerState::new(vec![ScriptCommand::Walk]);
+        for pass in 1..=2 {
+            let step = action_interpreter(&mut state, IVec2::new(0, 1), 0, &floor(), &assets());
+            let (index, animation) = step.expect("the script is not empty");
+            assert_eq!(index, 0);
+            assert!(animation.is_some());
+            assert_eq!(state.cursor, 0);
+            assert_eq!(state.completed_passes, pass);
+        }
+    }
+
+    #[test]
+    fn interpreter_reports_a_failing_single_command() {
+        // A wall right in front of the player.
+        let level = Level::from_walls(
+            [IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(1, 1)],
+            IVec2::new(0, 1),
+        );
+        let mut state = PlayerState::new(vec![ScriptCommand::Walk]);
+        let step = action_interpreter(&mut state, IVec2::new(0, 1), 0, &level, &assets());
+        let (index, animation) = step.expect("the script is not empty");
+        assert_eq!(index, 0);
+        assert!(animation.is_none());
+    }
+
+    #[test]
+    fn autoplay_stops_at_a_breakpoint() {
+        let script = vec![
+            ScriptCommand::Walk,
+            ScriptCommand::Breakpoint,
+            ScriptCommand::Walk,
+        ];
+        let mut world = World::new();
+        let end = run_script(&mut world, floor(), script, 10);
+        assert_eq!(end, IVec2::new(1, 1));
+        let state = world.resource::<PlayerState>();
+        assert!(!state.autoplay);
+        // Resuming continues after the breakpoint.
+        assert_eq!(state.cursor, 2);
+    }
+
+    #[test]
+    fn manual_steps_go_past_a_breakpoint() {
+        let mut state = PlayerState::new(vec![ScriptCommand::Breakpoint, ScriptCommand::Walk]);
+        state.autoplay = false;
+        let step = action_interpreter(&mut state, IVec2::new(0, 1), 0, &floor(), &assets());
+        let (index, animation) = step.expect("the script is not empty");
+        assert_eq!(index, 1);
+        assert!(animation.is_some());
+        assert!(!state.autoplay);
+    }
+}
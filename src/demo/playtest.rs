@@ -0,0 +1,83 @@
+//! Opt-in run summaries for playtesting. When enabled in the settings, every
+//! run is appended as one JSON line to `runs.jsonl` in the local data
+//! directory. Nothing is ever sent anywhere.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_ldtk::LevelSelection;
+use serde::Serialize;
+
+use super::{
+    action::ScriptCommand,
+    level::level_index,
+    player::{RunOutcome, RunResult},
+};
+use crate::{persistence, screens::Screen, settings::Settings, AppSet};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        record_runs
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::Gameplay).and_then(on_event::<RunOutcome>())),
+    );
+}
+
+const LOG_NAME: &str = "runs";
+
+#[derive(Serialize)]
+struct RunSummary<'a> {
+    level: usize,
+    script: &'a [ScriptCommand],
+    /// One of `died`, `checkpoint`, `goal` or `aborted`.
+    outcome: &'static str,
+    ticks: usize,
+    /// Deaths in this level so far this session, including this run.
+    deaths: usize,
+    /// Seconds since the Unix epoch.
+    timestamp: u64,
+}
+
+fn record_runs(
+    mut outcomes: EventReader<RunOutcome>,
+    settings: Res<Settings>,
+    selection: Res<LevelSelection>,
+    mut deaths: Local<HashMap<usize, usize>>,
+) {
+    let level = level_index(&selection);
+    for outcome in outcomes.read() {
+        let deaths = deaths.entry(level).or_default();
+        let result = match outcome.result {
+            RunResult::Died => {
+                *deaths += 1;
+                "died"
+            }
+            RunResult::Checkpoint(_) => "checkpoint",
+            RunResult::Goal(_) => "goal",
+            RunResult::Aborted => "aborted",
+        };
+        if !settings.record_runs {
+            continue;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        let summary = RunSummary {
+            level,
+            script: &outcome.script,
+            outcome: result,
+            ticks: outcome.ticks,
+            deaths: *deaths,
+            timestamp,
+        };
+        match serde_json::to_string(&summary) {
+            Ok(line) => {
+                persistence::append_line(LOG_NAME, &line);
+            }
+            Err(err) => log::error!("Could not serialize run summary: {err}"),
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Which checkpoints were reached in each level, saved so that returning
+//! players keep their unlocks.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::LevelSelection;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    action::ScriptCommand,
+    level::{level_index, Level},
+};
+use crate::{persistence, screens::Screen};
+
+pub(super) fn plugin(app: &mut App) {
+    app.insert_resource(Progress::load());
+    app.add_systems(OnEnter(Screen::Gameplay), restore_progress);
+}
+
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct Progress {
+    /// Keyed by level index.
+    levels: BTreeMap<usize, LevelProgress>,
+    /// Whether the last tutorial level was completed, after which the tutorial
+    /// is no longer started on its own.
+    #[serde(default)]
+    tutorial_complete: bool,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct LevelProgress {
+    checkpoints: BTreeSet<(i32, i32)>,
+    unlocked: Vec<ScriptCommand>,
+    command_count: usize,
+    /// Length of the shortest script that completed the level.
+    #[serde(default)]
+    best_length: Option<usize>,
+    /// Fewest ticks of a run that completed the level.
+    #[serde(default)]
+    best_moves: Option<usize>,
+    /// Shortest time of a run that completed the level.
+    #[serde(default)]
+    best_time: Option<Duration>,
+    /// Whether the level was only ever completed in assist mode.
+    #[serde(default)]
+    assisted: bool,
+}
+
+impl Progress {
+    const KEY: &'static str = "progress";
+
+    /// A missing or corrupt save counts as no progress.
+    fn load() -> Self {
+        persistence::load(Self::KEY).unwrap_or_default()
+    }
+
+    /// Remember that the checkpoint was reached, together with everything the
+    /// level has unlocked so far.
+    pub fn reach(&mut self, level_index: usize, checkpoint: IVec2, level: &Level) {
+        let progress = self.levels.entry(level_index).or_default();
+        progress.checkpoints.insert((checkpoint.x, checkpoint.y));
+        progress.unlocked.clone_from(&level.unlocked);
+        progress.command_count = level.command_count;
+        persistence::save(Self::KEY, self);
+    }
+
+    /// Remember the length of a script that completed the level, including
+    /// brackets, if it is the shortest so far. Assisted solves only mark the
+    /// level as completed in assist mode.
+    pub fn complete(&mut self, level_index: usize, length: usize, assisted: bool) {
+        let progress = self.levels.entry(level_index).or_default();
+        if assisted {
+            progress.assisted = progress.best_length.is_none();
+        } else {
+            if progress.best_length.is_some_and(|best| best <= length) {
+                return;
+            }
+            progress.assisted = false;
+            progress.best_length = Some(length);
+        }
+        persistence::save(Self::KEY, self);
+    }
+
+    /// Whether the level was completed, with or without assist mode.
+    pub fn completed(&self, level_index: usize) -> bool {
+        self.levels
+            .get(&level_index)
+            .is_some_and(|progress| progress.best_length.is_some() || progress.assisted)
+    }
+
+    pub fn tutorial_complete(&self) -> bool {
+        self.tutorial_complete
+    }
+
+    pub fn complete_tutorial(&mut self) {
+        if !self.tutorial_complete {
+            self.tutorial_complete = true;
+            persistence::save(Self::KEY, self);
+        }
+    }
+
+    /// Whether the level was completed, but only in assist mode.
+    pub fn assisted(&self, level_index: usize) -> bool {
+        self.levels
+            .get(&level_index)
+            .is_some_and(|progress| progress.assisted)
+    }
+
+    /// Length of the shortest script that completed the level, if any.
+    pub fn best_length(&self, level_index: usize) -> Option<usize> {
+        self.levels.get(&level_index)?.best_length
+    }
+
+    /// Remember the moves and time of a run that completed the level, each
+    /// if it is the best so far.
+    pub fn record_run(&mut self, level_index: usize, moves: usize, time: Duration) {
+        let progress = self.levels.entry(level_index).or_default();
+        progress.best_moves = Some(progress.best_moves.map_or(moves, |best| best.min(moves)));
+        progress.best_time = Some(progress.best_time.map_or(time, |best| best.min(time)));
+        persistence::save(Self::KEY, self);
+    }
+
+    pub fn best_moves(&self, level_index: usize) -> Option<usize> {
+        self.levels.get(&level_index)?.best_moves
+    }
+
+    pub fn best_time(&self, level_index: usize) -> Option<Duration> {
+        self.levels.get(&level_index)?.best_time
+    }
+
+    /// Whether any checkpoint of the level was reached.
+    pub fn reached_any(&self, level_index: usize) -> bool {
+        self.levels
+            .get(&level_index)
+            .is_some_and(|progress| !progress.checkpoints.is_empty())
+    }
+
+    /// Give the level the unlocks and command count saved for it, or the
+    /// defaults of a fresh level if nothing was saved.
+    pub fn restore(&self, level_index: usize, level: &mut Level) {
+        let fresh = Level::default();
+        level.unlocked = fresh.unlocked;
+        level.command_count = fresh.command_count;
+        let Some(progress) = self.levels.get(&level_index) else {
+            return;
+        };
+        for &command in &progress.unlocked {
+            if !level.unlocked.contains(&command) {
+                level.unlocked.push(command);
+            }
+        }
+        level.command_count = level.command_count.max(progress.command_count);
+    }
+}
+
+pub(super) fn restore_progress(
+    progress: Res<Progress>,
+    selection: Res<LevelSelection>,
+    mut level: ResMut<Level>,
+) {
+    progress.restore(level_index(&selection), &mut level);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Round trip through the same format the save uses.
+    fn saved_and_loaded(progress: &Progress) -> Progress {
+        let json = serde_json::to_string(progress).unwrap();
+        persistence::parse(Progress::KEY, &json).unwrap_or_default()
+    }
+
+    #[test]
+    fn restore_carries_over_unlocks_and_command_count() {
+        let mut level = Level::default();
+        level.unlocked.push(ScriptCommand::Climb);
+        level.command_count = 7;
+        let mut progress = Progress::default();
+        progress.reach(3, IVec2::new(1, 2), &level);
+
+        let progress = saved_and_loaded(&progress);
+        assert!(progress.reached_any(3));
+        let mut rebuilt = Level::default();
+        progress.restore(3, &mut rebuilt);
+        assert_eq!(rebuilt.unlocked, vec![
+            ScriptCommand::Walk,
+            ScriptCommand::Climb
+        ]);
+        assert_eq!(rebuilt.command_count, 7);
+
+        // Other levels start fresh, even when restored into a used level.
+        progress.restore(4, &mut rebuilt);
+        assert_eq!(rebuilt.unlocked, Level::default().unlocked);
+        assert_eq!(rebuilt.command_count, Level::default().command_count);
+    }
+
+    #[test]
+    fn corrupt_save_counts_as_no_progress() {
+        let loaded = persistence::parse::<Progress>(Progress::KEY, r#"{"levels": {"3": "#);
+        assert!(loaded.is_none());
+        let progress = loaded.unwrap_or_default();
+        assert!(!progress.reached_any(3));
+        let mut level = Level::default();
+        level.command_count = 7;
+        progress.restore(3, &mut level);
+        assert_eq!(level.unlocked, Level::default().unlocked);
+        assert_eq!(level.command_count, Level::default().command_count);
+    }
+}
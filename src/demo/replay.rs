@@ -0,0 +1,392 @@
+//! Replays of the last submitted run. After the run is over, press V to open
+//! a timeline which can be dragged to any tick to see the exact positions of
+//! the player and the hazards at that point.
+//!
+//! Pressing P while editing previews the current script the same way, playing
+//! it once without submitting it.
+
+use bevy::{
+    ecs::{system::RunSystemOnce as _, world::Command},
+    prelude::*,
+    ui::{RelativeCursorPosition, Val::*},
+};
+
+use super::{
+    action::ScriptCommand,
+    animation::PlayerAssets,
+    editor::{EditorState, ShowEditor},
+    level::{GridTransform, Level, NextGridTransform, TickCount},
+    obstacle::Obstacle,
+    player::{Player, PlayerState},
+    simulation::{simulate, SimEnd, SimObstacle, SimRun, SimState},
+};
+use crate::{
+    key_bindings::{KeyAction, KeyBindings},
+    screens::Screen,
+    theme::{
+        interaction::text_input_focused,
+        palette::{BUTTON_PRESSED_BACKGROUND, NODE_BACKGROUND},
+        prelude::*,
+    },
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            (toggle_replay_viewer, start_preview).run_if(not(text_input_focused)),
+            (play_preview, scrub_timeline).run_if(resource_exists::<ReplayViewer>),
+            show_replay_tick.run_if(resource_exists_and_changed::<ReplayViewer>),
+        )
+            .chain()
+            .in_set(AppSet::RecordInput)
+            .run_if(in_state(Screen::Gameplay)),
+    );
+    app.add_systems(OnExit(Screen::Gameplay), |mut commands: Commands| {
+        commands.remove_resource::<Replay>();
+        commands.remove_resource::<ReplayViewer>();
+    });
+}
+
+/// Runs longer than this are cut off in the replay.
+const MAX_REPLAY_TICKS: usize = 1000;
+/// Seconds per tick when playing a preview.
+const PREVIEW_TICK: f32 = 0.15;
+
+/// The last submitted run, simulated tick by tick.
+#[derive(Resource, Debug)]
+pub struct Replay {
+    start: SimState,
+    run: SimRun,
+    /// Obstacle entities, in the same order as in [`SimState::obstacles`].
+    obstacles: Vec<Entity>,
+    sequence: Vec<ScriptCommand>,
+}
+
+impl Replay {
+    /// The state after `tick` ticks, where tick 0 is the start of the run.
+    fn state_at(&self, tick: usize) -> &SimState {
+        match tick.checked_sub(1) {
+            Some(index) => &self.run.ticks[index].state,
+            None => &self.start,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.run.ticks.len()
+    }
+}
+
+/// The replay timeline is open and showing the given tick.
+/// Editing and respawning are paused while it exists.
+#[derive(Resource, Debug)]
+pub struct ReplayViewer {
+    tick: usize,
+    /// Advances the tick when playing a preview.
+    playback: Option<Timer>,
+}
+
+#[derive(Component)]
+struct ReplayTimeline;
+
+#[derive(Component)]
+struct TimelineHandle;
+
+#[derive(Component)]
+struct TimelineLabel;
+
+/// Simulate a run of `sequence` from the current state and keep it for
+/// replaying. Previews are played back right away.
+pub struct RecordReplay {
+    pub sequence: Vec<ScriptCommand>,
+    pub preview: bool,
+}
+
+impl Command for RecordReplay {
+    fn apply(self, world: &mut World) {
+        world.run_system_once_with(self, record_replay);
+    }
+}
+
+fn record_replay(
+    In(config): In<RecordReplay>,
+    player: Query<&GridTransform, With<Player>>,
+    obstacles: Query<(Entity, &GridTransform, &Obstacle)>,
+    state: Res<PlayerState>,
+    tick_count: Res<TickCount>,
+    level: Res<Level>,
+    assets: Res<PlayerAssets>,
+    mut commands: Commands,
+) {
+    let Ok(pos) = player.get_single() else {
+        return;
+    };
+    let (entities, sim_obstacles) = obstacles
+        .iter()
+        .map(|(entity, pos, obstacle)| {
+            (entity, SimObstacle {
+                pos: pos.0,
+                patrol: obstacle.patrol().clone(),
+                kind: obstacle.kind,
+                timing: obstacle.timing,
+            })
+        })
+        .unzip();
+    let start = SimState {
+        player: pos.0,
+        x_dir: state.x_dir,
+        cursor: 0,
+        ticks: tick_count.0,
+        obstacles: sim_obstacles,
+    };
+    let run = simulate(
+        &level,
+        &assets,
+        &config.sequence,
+        start.clone(),
+        MAX_REPLAY_TICKS,
+    );
+    commands.insert_resource(Replay {
+        start,
+        run,
+        obstacles: entities,
+        sequence: config.sequence,
+    });
+
+    if config.preview {
+        commands.insert_resource(ReplayViewer {
+            tick: 0,
+            playback: Some(Timer::from_seconds(PREVIEW_TICK, TimerMode::Repeating)),
+        });
+        spawn_timeline(&mut commands);
+    }
+}
+
+/// Close the replay viewer and go back to how things were before the run.
+struct CloseReplayViewer;
+
+impl Command for CloseReplayViewer {
+    fn apply(self, world: &mut World) {
+        let Some(mut viewer) = world.get_resource_mut::<ReplayViewer>() else {
+            return;
+        };
+        viewer.tick = 0;
+        world.run_system_once(show_replay_tick);
+        world.remove_resource::<ReplayViewer>();
+        world.run_system_once(despawn_timeline);
+    }
+}
+
+fn despawn_timeline(timeline: Query<Entity, With<ReplayTimeline>>, mut commands: Commands) {
+    for entity in &timeline {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn toggle_replay_viewer(
+    input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    viewer: Option<Res<ReplayViewer>>,
+    replay: Option<Res<Replay>>,
+    editor_state: Res<EditorState>,
+    mut commands: Commands,
+) {
+    // Ctrl+V pastes in the editor.
+    let ctrl = input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !input.just_pressed(bindings.key(KeyAction::ToggleReplay)) || ctrl {
+        return;
+    }
+
+    if viewer.is_some() {
+        commands.add(CloseReplayViewer);
+        return;
+    }
+
+    // Only replay once the run is over.
+    if replay.is_none() || !editor_state.enabled {
+        return;
+    }
+    commands.insert_resource(ReplayViewer {
+        tick: 0,
+        playback: None,
+    });
+    spawn_timeline(&mut commands);
+}
+
+fn start_preview(
+    input: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    viewer: Option<Res<ReplayViewer>>,
+    editor_state: Res<EditorState>,
+    level: Res<Level>,
+    mut commands: Commands,
+) {
+    let preview = input.just_pressed(bindings.key(KeyAction::Preview));
+    if !preview || viewer.is_some() || !editor_state.enabled {
+        return;
+    }
+    let sequence = editor_state.balanced_script();
+    if sequence.is_empty() || sequence.len() > level.command_count {
+        return;
+    }
+    commands.add(RecordReplay {
+        sequence,
+        preview: true,
+    });
+}
+
+/// Step through a preview and go back to editing once it is over.
+fn play_preview(
+    time: Res<Time>,
+    replay: Res<Replay>,
+    mut viewer: ResMut<ReplayViewer>,
+    mut commands: Commands,
+) {
+    // Only the tick is of interest to `show_replay_tick`, not the timer.
+    let Some(playback) = &mut viewer.bypass_change_detection().playback else {
+        return;
+    };
+    if !playback.tick(time.delta()).just_finished() {
+        return;
+    }
+    match viewer.tick < replay.len() {
+        true => viewer.tick += 1,
+        false => commands.add(CloseReplayViewer),
+    }
+}
+
+fn spawn_timeline(commands: &mut Commands) {
+    commands
+        .spawn((
+            Name::new("Replay Timeline"),
+            ReplayTimeline,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Percent(60.0),
+                    left: Percent(20.0),
+                    bottom: Percent(15.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Px(5.0),
+                    ..default()
+                },
+                ..default()
+            },
+            StateScoped(Screen::Gameplay),
+        ))
+        .with_children(|children| {
+            children.label("").insert(TimelineLabel);
+            children
+                .spawn((
+                    Name::new("Timeline Bar"),
+                    NodeBundle {
+                        style: Style {
+                            width: Percent(100.0),
+                            height: Px(20.0),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(NODE_BACKGROUND),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    RelativeCursorPosition::default(),
+                ))
+                .with_children(|children| {
+                    children.spawn((Name::new("Timeline Handle"), TimelineHandle, NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            width: Px(6.0),
+                            height: Percent(100.0),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(BUTTON_PRESSED_BACKGROUND),
+                        ..default()
+                    }));
+                });
+        });
+}
+
+/// Drag along the bar to pick a tick, or step one tick with the arrow keys.
+fn scrub_timeline(
+    input: Res<ButtonInput<KeyCode>>,
+    bar: Query<(&Interaction, &RelativeCursorPosition)>,
+    replay: Res<Replay>,
+    mut viewer: ResMut<ReplayViewer>,
+) {
+    let mut tick = viewer.tick;
+    for (interaction, cursor) in &bar {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(pos) = cursor.normalized {
+            tick = (pos.x.clamp(0.0, 1.0) * replay.len() as f32).round() as usize;
+        }
+    }
+    if input.just_pressed(KeyCode::ArrowLeft) {
+        tick = tick.saturating_sub(1);
+    }
+    if input.just_pressed(KeyCode::ArrowRight) {
+        tick = (tick + 1).min(replay.len());
+    }
+    // Avoid triggering change detection when nothing moved.
+    if tick != viewer.tick {
+        viewer.tick = tick;
+    }
+}
+
+fn show_replay_tick(
+    viewer: Res<ReplayViewer>,
+    replay: Res<Replay>,
+    editor_state: Res<EditorState>,
+    mut player_state: ResMut<PlayerState>,
+    mut player: Query<(&mut GridTransform, &mut NextGridTransform), With<Player>>,
+    mut obstacles: Query<(&mut GridTransform, &mut NextGridTransform), Without<Player>>,
+    mut handle: Query<&mut Style, With<TimelineHandle>>,
+    mut label: Query<&mut Text, With<TimelineLabel>>,
+    mut commands: Commands,
+) {
+    let state = replay.state_at(viewer.tick);
+
+    for (mut pos, mut next_pos) in &mut player {
+        pos.0 = state.player;
+        next_pos.0 = state.player;
+    }
+    player_state.x_dir = state.x_dir;
+    for (entity, obstacle) in replay.obstacles.iter().zip(&state.obstacles) {
+        if let Ok((mut pos, mut next_pos)) = obstacles.get_mut(*entity) {
+            pos.0 = obstacle.pos;
+            next_pos.0 = obstacle.pos;
+        }
+    }
+
+    let fraction = match replay.len() {
+        0 => 0.0,
+        len => viewer.tick as f32 / len as f32,
+    };
+    for mut style in &mut handle {
+        style.left = Percent(100.0 * fraction);
+    }
+    let end = match replay.run.end {
+        _ if viewer.tick < replay.len() => "",
+        Some(SimEnd::Died) => " (died)",
+        Some(SimEnd::Checkpoint(_)) => " (checkpoint)",
+        Some(SimEnd::Goal(_)) => " (goal)",
+        Some(SimEnd::Stuck) => " (stuck)",
+        None => " (cut off)",
+    };
+    for mut label in &mut label {
+        label.sections[0].value = format!("Tick {} / {}{end}", viewer.tick, replay.len());
+    }
+
+    // Highlight the executed command, unless the script was edited since.
+    let active = viewer
+        .tick
+        .checked_sub(1)
+        .map(|index| replay.run.ticks[index].script_index)
+        .filter(|_| editor_state.script() == replay.sequence);
+    commands.add(ShowEditor {
+        active: active.map(|index| (index, true)),
+    });
+}
@@ -0,0 +1,133 @@
+//! Moves and time of the current run, for players who want to go fast.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::LevelSelection;
+
+use super::{
+    editor::EditorState,
+    level::{level_index, Reset, TickStart},
+    player::RunOutcome,
+    progress::Progress,
+};
+use crate::{
+    screens::{gameplay::RunStatsLabel, Screen},
+    AppSet,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<RunStats>();
+    app.add_systems(
+        Update,
+        (update_run_stats, show_run_stats)
+            .chain()
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::Gameplay)),
+    );
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct RunStats {
+    /// Ticks executed since the last reset.
+    pub moves: usize,
+    /// Time spent running the script since the last reset.
+    pub time: Duration,
+    /// Set when a run completed the level, so that its stats stay visible
+    /// until the next run starts moving.
+    pub frozen: bool,
+}
+
+fn update_run_stats(
+    mut ticks: EventReader<TickStart>,
+    mut resets: EventReader<Reset>,
+    mut outcomes: EventReader<RunOutcome>,
+    mut stats: ResMut<RunStats>,
+    mut progress: ResMut<Progress>,
+    editor_state: Res<EditorState>,
+    selection: Res<LevelSelection>,
+    time: Res<Time>,
+) {
+    // Completing the level resets the player in the same frame, so the
+    // outcome has to be handled before the reset.
+    for outcome in outcomes.read() {
+        if outcome.completed_level && !stats.frozen {
+            stats.frozen = true;
+            // Assisted runs are shown, but do not count as records.
+            if !outcome.assisted {
+                progress.record_run(level_index(&selection), stats.moves, stats.time);
+            }
+        }
+    }
+    if resets.read().count() > 0 && !stats.frozen {
+        *stats = RunStats::default();
+    }
+
+    let moves = ticks.read().count();
+    if moves > 0 && stats.frozen {
+        *stats = RunStats::default();
+    }
+    if stats.frozen {
+        return;
+    }
+    stats.moves += moves;
+    if !editor_state.enabled {
+        stats.time += time.delta();
+    }
+}
+
+fn show_run_stats(
+    stats: Res<RunStats>,
+    progress: Res<Progress>,
+    selection: Res<LevelSelection>,
+    mut label: Query<&mut Text, With<RunStatsLabel>>,
+) {
+    let level = level_index(&selection);
+    let mut text = format!("Moves: {}", stats.moves);
+    if let Some(best) = progress.best_moves(level) {
+        text += &format!(" (best {best})");
+    }
+    text += &format!("  Time: {:.1}s", stats.time.as_secs_f32());
+    if let Some(best) = progress.best_time(level) {
+        text += &format!(" (best {:.1}s)", best.as_secs_f32());
+    }
+    for mut label in &mut label {
+        if label.sections[0].value != text {
+            label.sections[0].value.clone_from(&text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_tick_is_a_move_until_reset() {
+        let mut world = World::new();
+        world.init_resource::<Events<TickStart>>();
+        world.init_resource::<Events<Reset>>();
+        world.init_resource::<Events<RunOutcome>>();
+        world.init_resource::<RunStats>();
+        world.init_resource::<Progress>();
+        world.init_resource::<EditorState>();
+        world.insert_resource(LevelSelection::index(0));
+        world.init_resource::<Time>();
+        // Registered once, so that events are only read once.
+        let update = world.register_system(update_run_stats);
+
+        for _ in 0..3 {
+            world.send_event(TickStart);
+        }
+        world.run_system(update).unwrap();
+        assert_eq!(world.resource::<RunStats>().moves, 3);
+        world.send_event(TickStart);
+        world.send_event(TickStart);
+        world.run_system(update).unwrap();
+        assert_eq!(world.resource::<RunStats>().moves, 5);
+
+        world.send_event(Reset);
+        world.run_system(update).unwrap();
+        assert_eq!(world.resource::<RunStats>().moves, 0);
+    }
+}
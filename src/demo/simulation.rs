@@ -0,0 +1,279 @@
+//! A headless simulation of a run. It advances in whole ticks rather than
+//! following frame timing, so the same script always plays out the same way.
+
+use bevy::prelude::*;
+
+use super::{
+    action::ScriptCommand,
+    animation::PlayerAssets,
+    level::Level,
+    obstacle::{ride_platforms, HazardKind, HazardTiming, Patrol},
+    player::{action_interpreter, PlayerState},
+};
+
+/// Everything that changes over the course of a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimState {
+    pub player: IVec2,
+    pub x_dir: i32,
+    pub cursor: usize,
+    /// Ticks since respawning.
+    pub ticks: usize,
+    pub obstacles: Vec<SimObstacle>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimObstacle {
+    pub pos: IVec2,
+    pub patrol: Patrol,
+    pub kind: HazardKind,
+    pub timing: HazardTiming,
+}
+
+/// Why a simulated run stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimEnd {
+    /// The player touched a lethal hazard.
+    Died,
+    /// The player reached a new checkpoint.
+    Checkpoint(IVec2),
+    /// The player reached a goal after picking up every collectible.
+    Goal(IVec2),
+    /// No command in the script can be executed anymore.
+    Stuck,
+}
+
+/// The outcome of a single tick.
+#[derive(Debug, Clone)]
+pub struct SimTick {
+    /// Index of the script command executed during the tick.
+    pub script_index: usize,
+    /// The state at the end of the tick.
+    pub state: SimState,
+}
+
+#[derive(Debug, Clone)]
+pub struct SimRun {
+    pub ticks: Vec<SimTick>,
+    /// `None` when the run was cut off after the maximum number of ticks.
+    pub end: Option<SimEnd>,
+    /// Indices of the script commands that could not be executed, in the
+    /// order they failed.
+    pub failures: Vec<usize>,
+}
+
+/// Run `sequence` from `start` for at most `max_ticks` ticks, using the same
+/// interpreter as the real game. Keys and collectibles are picked up in a copy
+/// of `level`.
+pub fn simulate(
+    level: &Level,
+    assets: &PlayerAssets,
+    sequence: &[ScriptCommand],
+    start: SimState,
+    max_ticks: usize,
+) -> SimRun {
+    let mut player = PlayerState {
+        x_dir: start.x_dir,
+        cursor: start.cursor,
+        // Breakpoints only pause the real game.
+        autoplay: false,
+        ..PlayerState::new(sequence.to_vec())
+    };
+    let mut level = level.clone();
+    let mut state = start;
+    let mut ticks = Vec::new();
+    let mut failures = Vec::new();
+    // Nothing moves while the interpreter finds nothing to do, so once it has
+    // tried every position in the script the run cannot continue.
+    let mut failed_attempts = 0;
+
+    let end = loop {
+        if ticks.len() >= max_ticks {
+            break None;
+        }
+        if failed_attempts > sequence.len() {
+            break Some(SimEnd::Stuck);
+        }
+
+        let Some((script_index, animation)) =
+            action_interpreter(&mut player, state.player, state.ticks, &level, assets)
+        else {
+            // The script is empty.
+            break Some(SimEnd::Stuck);
+        };
+        let Some(animation) = animation else {
+            failures.push(script_index);
+            failed_attempts += 1;
+            continue;
+        };
+        failed_attempts = 0;
+
+        let before = state.obstacles.clone();
+        for obstacle in &mut state.obstacles {
+            obstacle.pos = obstacle.patrol.advance();
+        }
+        let platforms = before
+            .iter()
+            .zip(&state.obstacles)
+            .filter(|(obstacle, _)| obstacle.kind == HazardKind::Platform)
+            .map(|(old, new)| (old.pos, new.pos));
+        let start = state.player;
+        state.player = ride_platforms(start, animation.final_offset(player.x_dir), platforms);
+        if state.player != start {
+            state.player = level
+                .teleport_destination(state.player)
+                .unwrap_or(state.player);
+        }
+        state.x_dir = player.x_dir;
+        state.cursor = player.cursor;
+        state.ticks += 1;
+        ticks.push(SimTick {
+            script_index,
+            state: state.clone(),
+        });
+
+        let pos = state.player;
+        if level.is_solid(pos) {
+            break Some(SimEnd::Died);
+        }
+        if state.obstacles.iter().any(|obstacle| {
+            obstacle.pos == pos
+                && obstacle.kind.is_lethal()
+                && obstacle.timing.is_active(state.ticks)
+        }) {
+            break Some(SimEnd::Died);
+        }
+        if level.is_checkpoint(pos) && level.last_checkpoint != pos {
+            break Some(SimEnd::Checkpoint(pos));
+        }
+        if let Some(&id) = level.keys.get(&pos) {
+            level.held_keys.insert(id);
+        }
+        if level.has_collectible(pos) {
+            level.collected.insert(pos);
+        }
+        if level.is_goal(pos) && level.all_collected() {
+            break Some(SimEnd::Goal(pos));
+        }
+    };
+
+    SimRun {
+        ticks,
+        end,
+        failures,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo::{
+        obstacle::MovementPattern,
+        testing::{assets, floor},
+    };
+
+    fn start(obstacles: Vec<SimObstacle>) -> SimState {
+        SimState {
+            player: IVec2::new(0, 1),
+            x_dir: 1,
+            cursor: 0,
+            ticks: 0,
+            obstacles,
+        }
+    }
+
+    fn walk(level: &Level, max_ticks: usize) -> SimRun {
+        let script = [ScriptCommand::Walk];
+        simulate(level, &assets(), &script, start(Vec::new()), max_ticks)
+    }
+
+    #[test]
+    fn run_ends_at_goal() {
+        let mut level = floor();
+        level.goals.insert(IVec2::new(3, 1));
+        let run = walk(&level, 10);
+        assert_eq!(run.end, Some(SimEnd::Goal(IVec2::new(3, 1))));
+        assert_eq!(run.ticks.len(), 3);
+    }
+
+    #[test]
+    fn goal_counts_once_everything_is_collected() {
+        let mut level = floor();
+        level.goals.insert(IVec2::new(3, 1));
+        level
+            .collectibles
+            .extend([IVec2::new(1, 1), IVec2::new(2, 1)]);
+        assert_eq!(walk(&level, 10).end, Some(SimEnd::Goal(IVec2::new(3, 1))));
+
+        // One collectible is behind the player, so they walk past the goal.
+        level.collectibles.insert(IVec2::new(-1, 1));
+        assert_eq!(walk(&level, 6).end, None);
+    }
+
+    #[test]
+    fn key_opens_door_ahead() {
+        let mut level = floor();
+        level.doors.insert(IVec2::new(2, 1), 7);
+        assert_eq!(walk(&level, 10).end, Some(SimEnd::Stuck));
+
+        level.keys.insert(IVec2::new(1, 1), 7);
+        let run = walk(&level, 4);
+        assert_eq!(run.ticks.last().unwrap().state.player, IVec2::new(4, 1));
+    }
+
+    #[test]
+    fn repeat_runs_its_body_as_often_as_its_count() {
+        let script = [
+            ScriptCommand::Repeat(3),
+            ScriptCommand::Walk,
+            ScriptCommand::CloseBracket,
+            ScriptCommand::Turn,
+        ];
+        let run = simulate(&floor(), &assets(), &script, start(Vec::new()), 4);
+        let executed: Vec<_> = run.ticks.iter().map(|tick| tick.script_index).collect();
+        assert_eq!(executed, [1, 1, 1, 3]);
+        assert_eq!(run.ticks.last().unwrap().state.player, IVec2::new(3, 1));
+    }
+
+    #[test]
+    fn idling_on_a_platform_moves_with_it() {
+        let spawn = IVec2::new(0, 1);
+        let platform = SimObstacle {
+            pos: spawn,
+            patrol: Patrol::new(&MovementPattern::PingPong(IVec2::new(2, 1)), spawn),
+            kind: HazardKind::Platform,
+            timing: default(),
+        };
+        let script = [ScriptCommand::Idle];
+        let run = simulate(&floor(), &assets(), &script, start(vec![platform]), 2);
+        let path: Vec<_> = run.ticks.iter().map(|tick| tick.state.player).collect();
+        assert_eq!(path, [IVec2::new(2, 1), IVec2::new(0, 1)]);
+    }
+
+    /// Static spikes one cell ahead of the player, which are active on every
+    /// other tick.
+    fn spikes(active_phase: usize) -> SimObstacle {
+        let pos = IVec2::new(1, 1);
+        SimObstacle {
+            pos,
+            patrol: Patrol::new(&MovementPattern::PingPong(pos), pos),
+            kind: HazardKind::Spikes,
+            timing: HazardTiming {
+                period: 2,
+                active_phase,
+            },
+        }
+    }
+
+    #[test]
+    fn inactive_hazard_is_harmless() {
+        let script = [ScriptCommand::Walk];
+        // The player is on the spikes after the first tick.
+        let run = simulate(&floor(), &assets(), &script, start(vec![spikes(0)]), 3);
+        assert_eq!(run.end, None);
+        assert_eq!(run.ticks.last().unwrap().state.player, IVec2::new(3, 1));
+
+        let run = simulate(&floor(), &assets(), &script, start(vec![spikes(1)]), 3);
+        assert_eq!(run.end, Some(SimEnd::Died));
+    }
+}
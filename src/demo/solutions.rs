@@ -0,0 +1,242 @@
+//! Named solution slots, so players can keep several scripts per level
+//! (e.g. "fewest commands" and "fastest"), and the script last submitted in
+//! each level.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::LevelSelection;
+use bevy_simple_text_input::TextInputValue;
+use serde::{Deserialize, Serialize};
+
+use super::{action::ScriptCommand, editor::ShowEditor};
+use crate::{
+    demo::{
+        editor::EditorState,
+        level::{level_index, Level},
+    },
+    persistence,
+    screens::Screen,
+    theme::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<SolutionSlots>();
+    app.add_systems(OnEnter(Screen::Gameplay), load_solution_slots);
+    app.add_systems(
+        Update,
+        update_slot_list
+            .run_if(in_state(Screen::Gameplay).and_then(resource_changed::<SolutionSlots>)),
+    );
+}
+
+/// Maximum number of saved solutions per level.
+const MAX_SLOTS: usize = 8;
+/// Maximum length of a slot name in characters.
+const MAX_NAME_LEN: usize = 24;
+
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct SolutionSlots {
+    #[serde(skip)]
+    level: usize,
+    slots: BTreeMap<String, Vec<ScriptCommand>>,
+    /// Feedback for the last slot operation.
+    #[serde(skip)]
+    status: String,
+}
+
+impl SolutionSlots {
+    fn key(level: usize) -> String {
+        format!("solutions-{level}")
+    }
+
+    fn store(&self) -> bool {
+        persistence::save(&Self::key(self.level), self)
+    }
+}
+
+/// Key of the script that was last submitted in a level.
+fn submitted_key(level: usize) -> String {
+    format!("submitted-{level}")
+}
+
+/// Remember the script that was submitted in a level, so that it is restored
+/// in the next session.
+pub fn save_submitted(level: usize, script: &[ScriptCommand]) {
+    persistence::save(&submitted_key(level), &script);
+}
+
+/// The script that was last submitted in a level, without the commands that
+/// are not available in `current`.
+pub fn load_submitted(level: usize, current: &Level) -> Vec<ScriptCommand> {
+    let script: Vec<ScriptCommand> = persistence::load(&submitted_key(level)).unwrap_or_default();
+    allowed_in(current, script)
+}
+
+/// Drop the commands of a saved script that are not unlocked yet or banned in
+/// `level`.
+fn allowed_in(
+    level: &Level,
+    script: impl IntoIterator<Item = ScriptCommand>,
+) -> Vec<ScriptCommand> {
+    script
+        .into_iter()
+        .filter(|&command| level.allows(command))
+        .collect()
+}
+
+/// Text input for the name of the slot to save, load, or delete.
+#[derive(Component)]
+pub struct SlotNameInput;
+
+/// Label listing the saved slots.
+#[derive(Component)]
+pub struct SlotList;
+
+/// Check that a slot name is short and readable, returning the trimmed name.
+fn validate_slot_name(name: &str) -> Result<&str, &'static str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Enter a name first");
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err("Name is too long");
+    }
+    let allowed = |c: char| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_');
+    if !name.chars().all(allowed) {
+        return Err("Use only letters, digits, spaces, - and _");
+    }
+    Ok(name)
+}
+
+fn load_solution_slots(mut slots: ResMut<SolutionSlots>, selection: Res<LevelSelection>) {
+    let level = level_index(&selection);
+    *slots = persistence::load(&SolutionSlots::key(level)).unwrap_or_default();
+    slots.level = level;
+}
+
+fn update_slot_list(slots: Res<SolutionSlots>, mut list: Query<&mut Text, With<SlotList>>) {
+    let names: Vec<_> = slots.slots.keys().map(String::as_str).collect();
+    let mut text = match names.is_empty() {
+        true => "No saved solutions".to_string(),
+        false => names.join("\n"),
+    };
+    if !slots.status.is_empty() {
+        text = format!("{}\n\n{text}", slots.status);
+    }
+    for mut list in &mut list {
+        list.sections[0].value.clone_from(&text);
+    }
+}
+
+pub fn save_slot(
+    _trigger: Trigger<OnPress>,
+    name_input: Query<&TextInputValue, With<SlotNameInput>>,
+    editor_state: Res<EditorState>,
+    mut slots: ResMut<SolutionSlots>,
+) {
+    let name = match validate_slot_name(&name_input.single().0) {
+        Ok(name) => name.to_string(),
+        Err(err) => {
+            slots.status = err.to_string();
+            return;
+        }
+    };
+    if !slots.slots.contains_key(&name) && slots.slots.len() >= MAX_SLOTS {
+        slots.status = format!("At most {MAX_SLOTS} slots, delete one first");
+        return;
+    }
+
+    let previous = slots
+        .slots
+        .insert(name.clone(), editor_state.script().to_vec());
+    slots.status = match slots.store() {
+        true => format!("Saved \"{name}\""),
+        false => {
+            // Keep the in-memory slots consistent with what is stored.
+            match previous {
+                Some(previous) => slots.slots.insert(name, previous),
+                None => slots.slots.remove(&name),
+            };
+            "Could not save, storage may be full".to_string()
+        }
+    };
+}
+
+pub fn load_slot(
+    _trigger: Trigger<OnPress>,
+    name_input: Query<&TextInputValue, With<SlotNameInput>>,
+    mut editor_state: ResMut<EditorState>,
+    mut slots: ResMut<SolutionSlots>,
+    level: Res<Level>,
+    mut commands: Commands,
+) {
+    if !editor_state.enabled {
+        slots.status = "Cannot load while running".to_string();
+        return;
+    }
+    let name = name_input.single().0.trim().to_string();
+    let Some(script) = slots.slots.get(&name) else {
+        slots.status = format!("No slot named \"{name}\"");
+        return;
+    };
+
+    editor_state.set_script(allowed_in(&level, script.iter().copied()));
+    slots.status = format!("Loaded \"{name}\"");
+    commands.add(ShowEditor::default());
+}
+
+pub fn delete_slot(
+    _trigger: Trigger<OnPress>,
+    name_input: Query<&TextInputValue, With<SlotNameInput>>,
+    mut slots: ResMut<SolutionSlots>,
+) {
+    let name = name_input.single().0.trim().to_string();
+    if slots.slots.remove(&name).is_none() {
+        slots.status = format!("No slot named \"{name}\"");
+        return;
+    }
+    slots.status = match slots.store() {
+        true => format!("Deleted \"{name}\""),
+        false => "Could not save, storage may be full".to_string(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo::action::{Facing, Parity};
+
+    #[test]
+    fn submitted_script_survives_saving() {
+        let script = vec![
+            ScriptCommand::Walk,
+            ScriptCommand::Repeat(3),
+            ScriptCommand::Face(Facing::Left),
+            ScriptCommand::Sync(Parity::Odd),
+            ScriptCommand::CloseBracket,
+        ];
+        // The same format that `persistence` stores.
+        let json = serde_json::to_string(&script).unwrap();
+        let loaded: Vec<ScriptCommand> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, script);
+    }
+
+    #[test]
+    fn locked_commands_are_dropped_when_loading() {
+        let mut level = Level::default();
+        level.unlocked.push(ScriptCommand::Idle);
+        let script = [
+            ScriptCommand::Walk,
+            ScriptCommand::Jump,
+            ScriptCommand::Idle,
+            ScriptCommand::OpenBracket,
+            ScriptCommand::Walk,
+        ];
+        assert_eq!(allowed_in(&level, script), [
+            ScriptCommand::Walk,
+            ScriptCommand::Idle,
+            ScriptCommand::Walk
+        ]);
+    }
+}
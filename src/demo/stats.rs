@@ -0,0 +1,70 @@
+//! Lifetime statistics about how the player solves levels.
+
+use std::cmp::Reverse;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    action::ScriptCommand,
+    player::{RunOutcome, RunResult},
+};
+use crate::{persistence, screens::Screen, AppSet};
+
+pub(super) fn plugin(app: &mut App) {
+    app.insert_resource(CommandStats::load());
+    app.add_systems(
+        Update,
+        count_commands
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::Gameplay).and_then(on_event::<RunOutcome>())),
+    );
+}
+
+/// How often each command was executed in runs that reached a checkpoint.
+/// This is saved so that it adds up over all sessions.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct CommandStats {
+    /// Commands cannot be JSON object keys, so this is a list of pairs.
+    counts: Vec<(ScriptCommand, usize)>,
+}
+
+impl CommandStats {
+    const KEY: &'static str = "command-stats";
+
+    fn load() -> Self {
+        persistence::load(Self::KEY).unwrap_or_default()
+    }
+
+    fn add(&mut self, command: ScriptCommand, count: usize) {
+        match self.counts.iter_mut().find(|(known, _)| *known == command) {
+            Some((_, total)) => *total += count,
+            None => self.counts.push((command, count)),
+        }
+    }
+
+    /// Each command with how often it was executed, most used first.
+    pub fn most_used(&self) -> Vec<(ScriptCommand, usize)> {
+        let mut counts = self.counts.clone();
+        counts.sort_by_key(|&(_, count)| Reverse(count));
+        counts
+    }
+
+    /// Forget all statistics.
+    pub fn reset(&mut self) {
+        self.counts.clear();
+        persistence::save(Self::KEY, self);
+    }
+}
+
+fn count_commands(mut outcomes: EventReader<RunOutcome>, mut stats: ResMut<CommandStats>) {
+    for outcome in outcomes.read() {
+        if !matches!(outcome.result, RunResult::Checkpoint(_)) || outcome.assisted {
+            continue;
+        }
+        for (&command, &count) in &outcome.executed {
+            stats.add(command, count);
+        }
+        persistence::save(CommandStats::KEY, &*stats);
+    }
+}
@@ -0,0 +1,203 @@
+//! A style grade for every reached checkpoint, rewarding short scripts,
+//! loops, and getting there without dying. The run is also compared to the
+//! best run to the same checkpoint.
+
+use std::{cmp::Ordering, fmt};
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::LevelSelection;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    dialogue::ShowDialogue,
+    level::level_index,
+    player::{RunOutcome, RunResult},
+};
+use crate::{persistence, screens::Screen, AppSet};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<StyleTracker>();
+    app.add_systems(OnEnter(Screen::Gameplay), load_best_grade);
+    app.add_systems(
+        Update,
+        grade_runs
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::Gameplay).and_then(on_event::<RunOutcome>())),
+    );
+}
+
+/// How much each part contributes to the score. They should add up to 1.
+const WEIGHT_CONCISE: f32 = 0.5;
+const WEIGHT_LOOPS: f32 = 0.2;
+const WEIGHT_NO_DEATHS: f32 = 0.3;
+
+/// Minimum score for each grade, the rest get [`Grade::C`].
+const SCORE_S: f32 = 0.9;
+const SCORE_A: f32 = 0.7;
+const SCORE_B: f32 = 0.5;
+
+/// Ordered from worst to best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Grade {
+    C,
+    B,
+    A,
+    S,
+}
+
+impl Grade {
+    fn from_score(score: f32) -> Self {
+        match score {
+            s if s >= SCORE_S => Grade::S,
+            s if s >= SCORE_A => Grade::A,
+            s if s >= SCORE_B => Grade::B,
+            _ => Grade::C,
+        }
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+struct StyleTracker {
+    level: usize,
+    /// Deaths since the last checkpoint.
+    deaths: usize,
+    best: Option<Grade>,
+}
+
+fn best_grade_key(level: usize) -> String {
+    format!("best-grade-{level}")
+}
+
+/// The best grade ever reached in the level, if any checkpoint was reached.
+pub fn best_grade(level: usize) -> Option<Grade> {
+    persistence::load(&best_grade_key(level))
+}
+
+/// The best run to a checkpoint. Fewer commands are better, with fewer ticks
+/// breaking ties, so the script itself is what gets optimized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct BestRun {
+    commands: usize,
+    ticks: usize,
+}
+
+impl BestRun {
+    fn key(level: usize, checkpoint: IVec2) -> String {
+        format!("best-run-{level}-{}-{}", checkpoint.x, checkpoint.y)
+    }
+
+    /// `Less` means that `self` is better.
+    fn compare(&self, other: &Self) -> Ordering {
+        (self.commands, self.ticks).cmp(&(other.commands, other.ticks))
+    }
+}
+
+fn comparison(new: usize, best: usize) -> String {
+    let verdict = match new.cmp(&best) {
+        Ordering::Less => "better",
+        Ordering::Equal => "tie",
+        Ordering::Greater => "worse",
+    };
+    format!("{new} (best {best}, {verdict})")
+}
+
+/// Lines comparing the run to the previous best, which is replaced if the
+/// run was better.
+fn compare_to_best(level: usize, checkpoint: IVec2, run: BestRun) -> String {
+    let key = BestRun::key(level, checkpoint);
+    let Some(best) = persistence::load::<BestRun>(&key) else {
+        persistence::save(&key, &run);
+        return format!(
+            "Commands: {}
+Ticks: {}",
+            run.commands, run.ticks
+        );
+    };
+    if run.compare(&best).is_lt() {
+        persistence::save(&key, &run);
+    }
+    format!(
+        "Commands: {}
+Ticks: {}",
+        comparison(run.commands, best.commands),
+        comparison(run.ticks, best.ticks)
+    )
+}
+
+/// Score a run that reached a checkpoint, between 0 and 1.
+fn style_score(outcome: &RunOutcome, deaths: usize) -> f32 {
+    // Using a single command is perfect, using the whole limit is not.
+    let limit = outcome.command_limit.max(1) as f32;
+    let used = outcome.script.len().max(1) as f32;
+    let concise = (1.0 - (used - 1.0) / limit).clamp(0.0, 1.0);
+    // Running more commands than were written means something was reused.
+    let executed: usize = outcome.executed.values().sum();
+    let loops = if executed > outcome.script.len() {
+        1.0
+    } else {
+        0.0
+    };
+    let no_deaths = if deaths == 0 { 1.0 } else { 0.0 };
+    WEIGHT_CONCISE * concise + WEIGHT_LOOPS * loops + WEIGHT_NO_DEATHS * no_deaths
+}
+
+fn load_best_grade(mut tracker: ResMut<StyleTracker>, selection: Res<LevelSelection>) {
+    let level = level_index(&selection);
+    *tracker = StyleTracker {
+        level,
+        deaths: 0,
+        best: best_grade(level),
+    };
+}
+
+fn grade_runs(
+    mut outcomes: EventReader<RunOutcome>,
+    mut tracker: ResMut<StyleTracker>,
+    mut commands: Commands,
+) {
+    for outcome in outcomes.read() {
+        match outcome.result {
+            RunResult::Died => tracker.deaths += 1,
+            RunResult::Aborted | RunResult::Goal(_) => {}
+            RunResult::Checkpoint(_) if outcome.assisted => {
+                tracker.deaths = 0;
+                let mut page =
+                    "Checkpoint reached in assist mode, so it is not graded.".to_string();
+                if outcome.completed_level {
+                    page.push_str("\nLevel complete, every checkpoint was visited!");
+                }
+                commands.add(ShowDialogue { pages: vec![page] });
+            }
+            RunResult::Checkpoint(checkpoint) => {
+                let grade = Grade::from_score(style_score(outcome, tracker.deaths));
+                tracker.deaths = 0;
+
+                let new_best = !matches!(tracker.best, Some(best) if best >= grade);
+                if new_best {
+                    tracker.best = Some(grade);
+                    persistence::save(&best_grade_key(tracker.level), &grade);
+                }
+                let best = match new_best {
+                    true => " (new best)",
+                    false => "",
+                };
+                let run = BestRun {
+                    commands: outcome.script.len(),
+                    ticks: outcome.ticks,
+                };
+                let compared = compare_to_best(tracker.level, checkpoint, run);
+                let mut page = format!("Checkpoint reached!\nStyle: {grade}{best}\n{compared}");
+                if outcome.completed_level {
+                    page.push_str("\nLevel complete, every checkpoint was visited!");
+                }
+                commands.add(ShowDialogue { pages: vec![page] });
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Headless helpers for driving a run from tests. Only the systems that move
+//! the player are run, without a window, rendering, or loaded assets.
+
+use bevy::{
+    ecs::system::RunSystemOnce as _,
+    input::{
+        keyboard::{Key, KeyboardInput, NativeKey},
+        mouse::MouseWheel,
+        ButtonState,
+    },
+    prelude::*,
+};
+use bevy_ecs_ldtk::LevelSelection;
+
+pub use super::{action::ScriptCommand, level::Level};
+use super::{
+    animation::PlayerAssets,
+    editor::{edit_script, EditorAssets, EditorState, EditorUI},
+    level::{
+        update_tick_timer,
+        AnimationTick,
+        GridTransform,
+        NextGridTransform,
+        TickCount,
+        TickStart,
+    },
+    player::{update_animation, Player, PlayerState, ScriptFinished},
+    progress::Progress,
+};
+use crate::{key_bindings::KeyBindings, settings::Settings};
+
+/// A flat floor from `x = -5` to `x = 12`, with the spawn point on it at
+/// `(0, 1)`.
+pub fn floor() -> Level {
+    floor_with([])
+}
+
+/// A flat floor like `floor`, with the given extra walls.
+pub fn floor_with(extra: impl IntoIterator<Item = IVec2>) -> Level {
+    let floor = (-5..=12).map(|x| IVec2::new(x, 0));
+    Level::from_walls(floor.chain(extra), IVec2::new(0, 1))
+}
+
+/// Player assets without any loaded textures.
+pub fn assets() -> PlayerAssets {
+    PlayerAssets::new(default(), default(), &mut Assets::default())
+}
+
+/// Set up `level` in an empty `world`, spawn the player at its spawn point and
+/// submit `script`. Then run until the player made `ticks` moves, or until the
+/// script cannot make any more progress, and return where the player ended up.
+pub fn run_script(
+    world: &mut World,
+    level: Level,
+    script: Vec<ScriptCommand>,
+    ticks: usize,
+) -> IVec2 {
+    let start = level.get_spawn().unwrap_or_default();
+    insert_resources(world, level, script);
+    let player = world
+        .spawn((Player, GridTransform(start), NextGridTransform(start)))
+        .id();
+
+    // Like in `simulate`, the run is stuck once every command was tried
+    // without moving.
+    let max_idle = world.resource::<PlayerState>().sequence.len() + 1;
+    let mut idle = 0;
+    while world.resource::<TickCount>().0 < ticks && idle <= max_idle {
+        let moves = world.resource::<TickCount>().0;
+        finish_tick(world);
+        world.run_system_once(update_animation);
+        idle = match world.resource::<TickCount>().0 == moves {
+            true => idle + 1,
+            false => 0,
+        };
+    }
+    // Commit the last move.
+    finish_tick(world);
+
+    world.get::<GridTransform>(player).unwrap().0
+}
+
+/// Set up `level` in an empty `world` with the editor open, press `keys` in
+/// that order within a single frame and return the typed script.
+pub fn type_keys(world: &mut World, level: Level, keys: &[KeyCode]) -> Vec<ScriptCommand> {
+    insert_resources(world, level, Vec::new());
+    world.resource_mut::<EditorState>().enabled = true;
+    world.init_resource::<Events<KeyboardInput>>();
+    world.init_resource::<Events<MouseWheel>>();
+    press_keys(world, keys)
+}
+
+/// Release the keys of the previous frame, press `keys` in that order within
+/// a single frame and return the typed script. The world has to be set up
+/// with [`type_keys`] first.
+pub fn press_keys(world: &mut World, keys: &[KeyCode]) -> Vec<ScriptCommand> {
+    let mut input = world.resource_mut::<ButtonInput<KeyCode>>();
+    input.release_all();
+    input.clear();
+    world.resource_mut::<Events<KeyboardInput>>().clear();
+    for &key in keys {
+        world.resource_mut::<ButtonInput<KeyCode>>().press(key);
+        world.send_event(KeyboardInput {
+            key_code: key,
+            logical_key: Key::Unidentified(NativeKey::Unidentified),
+            state: ButtonState::Pressed,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+    world.run_system_once(edit_script);
+    world.resource::<EditorState>().script().to_vec()
+}
+
+/// Advance the time until the current tick is over.
+fn finish_tick(world: &mut World) {
+    let time_scale = world.resource::<Settings>().difficulty.time_scale();
+    let remaining = world.resource::<AnimationTick>().0.remaining();
+    world
+        .resource_mut::<Time>()
+        .advance_by(remaining.div_f32(time_scale));
+    world.run_system_once(update_tick_timer);
+}
+
+fn insert_resources(world: &mut World, level: Level, script: Vec<ScriptCommand>) {
+    let mut layouts = Assets::<TextureAtlasLayout>::default();
+    world.insert_resource(PlayerAssets::new(default(), default(), &mut layouts));
+    world.insert_resource(layouts);
+    world.insert_resource(EditorAssets {
+        icons: default(),
+        atlas: default(),
+        cursor: default(),
+    });
+    world.spawn((EditorUI, NodeBundle::default()));
+
+    let mut editor_state = EditorState::default();
+    editor_state.set_script(script.clone());
+    editor_state.enabled = false;
+    world.insert_resource(editor_state);
+    world.insert_resource(PlayerState::new(script));
+    world.insert_resource(level);
+    // Nothing is loaded from or saved to disk.
+    world.init_resource::<Progress>();
+    world.insert_resource(LevelSelection::index(0));
+
+    world.insert_resource(Settings::default());
+    world.init_resource::<KeyBindings>();
+    world.insert_resource(AnimationTick(Timer::from_seconds(0.2, TimerMode::Once)));
+    world.init_resource::<TickCount>();
+    world.init_resource::<Time>();
+    world.init_resource::<ButtonInput<KeyCode>>();
+    world.init_resource::<Events<TickStart>>();
+    world.init_resource::<Events<ScriptFinished>>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_script_stays_at_spawn() {
+        let level = Level::from_walls([IVec2::new(0, 0)], IVec2::new(0, 1));
+        assert_eq!(
+            run_script(&mut World::new(), level, Vec::new(), 5),
+            IVec2::new(0, 1)
+        );
+    }
+}
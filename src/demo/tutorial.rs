@@ -0,0 +1,210 @@
+//! The tutorial is a sequence of levels at the start of the map, each teaching
+//! one command. Completing a tutorial level continues with the next level, and
+//! the tutorial is only started on its own until it was completed once.
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::raw_level_accessor::RawLevelAccessor, prelude::*};
+
+use super::{
+    level::{level_index, Level, LevelAssets, LevelComplete},
+    palette::PaletteItem,
+    player::AddUnlockedCommand,
+    progress::Progress,
+};
+use crate::{screens::Screen, AppSet};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            queue_next_level.run_if(on_event::<LevelComplete>()),
+            go_to_next_level.run_if(resource_exists::<NextLevel>),
+        )
+            .chain()
+            .in_set(AppSet::Update)
+            .run_if(in_state(Screen::Gameplay)),
+    );
+    app.add_systems(OnExit(Screen::Gameplay), |mut commands: Commands| {
+        commands.remove_resource::<NextLevel>();
+    });
+}
+
+/// Whether the level with this index is part of the tutorial. The `Tutorial`
+/// field is optional and defaults to a regular level.
+pub fn is_tutorial(levels: &impl RawLevelAccessor, index: usize) -> bool {
+    levels
+        .iter_raw_levels()
+        .nth(index)
+        .is_some_and(|level| matches!(level.get_bool_field("Tutorial"), Ok(true)))
+}
+
+/// The level to continue with once the completed level was celebrated.
+#[derive(Resource, Debug)]
+struct NextLevel {
+    index: usize,
+    delay: Timer,
+}
+
+impl NextLevel {
+    /// Long enough to see that the level was completed.
+    const DELAY_SECONDS: f32 = 2.5;
+}
+
+fn queue_next_level(
+    mut complete: EventReader<LevelComplete>,
+    selection: Res<LevelSelection>,
+    level_assets: Res<LevelAssets>,
+    ldtk_projects: Res<Assets<LdtkProject>>,
+    mut progress: ResMut<Progress>,
+    mut commands: Commands,
+) {
+    complete.clear();
+    let Some(project) = ldtk_projects.get(&level_assets.ldtk_project) else {
+        return;
+    };
+    let index = level_index(&selection);
+    if !is_tutorial(project, index) {
+        return;
+    }
+    let next = index + 1;
+    if !is_tutorial(project, next) {
+        progress.complete_tutorial();
+    }
+    if next < project.iter_raw_levels().count() {
+        commands.insert_resource(NextLevel {
+            index: next,
+            delay: Timer::from_seconds(NextLevel::DELAY_SECONDS, TimerMode::Once),
+        });
+    }
+}
+
+/// Switch to the next level without leaving the gameplay screen, starting with
+/// the unlocks of the new level.
+fn go_to_next_level(
+    time: Res<Time>,
+    mut next_level: ResMut<NextLevel>,
+    mut selection: ResMut<LevelSelection>,
+    progress: Res<Progress>,
+    mut level: ResMut<Level>,
+    palette: Query<Entity, With<PaletteItem>>,
+    mut commands: Commands,
+) {
+    if !next_level.delay.tick(time.delta()).finished() {
+        return;
+    }
+    commands.remove_resource::<NextLevel>();
+    *selection = LevelSelection::index(next_level.index);
+
+    progress.restore(next_level.index, &mut level);
+    for entity in &palette {
+        commands.entity(entity).despawn_recursive();
+    }
+    for &command in &level.unlocked {
+        commands.add(AddUnlockedCommand { command });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashMap;
+    use bevy_ecs_ldtk::ldtk::{LdtkJson, Level as RawLevel};
+
+    use super::*;
+    use crate::demo::{
+        action::ScriptCommand,
+        animation::PlayerAssets,
+        level::{load_raw_level, GridTransform},
+        obstacle::Obstacle,
+        simulation::{simulate, SimEnd, SimObstacle, SimState},
+    };
+
+    fn map() -> LdtkJson {
+        let json = std::fs::read_to_string("assets/map.ldtk").unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    /// The solution written next to each checkpoint of the level.
+    fn solutions(raw_level: &RawLevel) -> HashMap<IVec2, Vec<ScriptCommand>> {
+        let mut solutions = HashMap::default();
+        for layer in raw_level.layer_instances.iter().flatten() {
+            let checkpoints = layer
+                .entity_instances
+                .iter()
+                .filter(|entity| entity.identifier == "Checkpoint");
+            for checkpoint in checkpoints {
+                let GridCoords { x, y } = GridCoords::from_entity_info(checkpoint, layer);
+                let solution = checkpoint.get_maybe_string_field("Solution").unwrap();
+                let solution = solution.iter().flat_map(|solution| solution.chars());
+                let script = solution.map(|letter| match letter {
+                    'W' => ScriptCommand::Walk,
+                    'T' => ScriptCommand::Turn,
+                    'C' => ScriptCommand::Climb,
+                    _ => panic!("unexpected command `{letter}` in a tutorial solution"),
+                });
+                solutions.insert(IVec2::new(x, y), script.collect());
+            }
+        }
+        solutions
+    }
+
+    #[test]
+    fn tutorial_comes_first() {
+        let map = map();
+        let count = map.iter_raw_levels().count();
+        let tutorials = (0..count)
+            .take_while(|&index| is_tutorial(&map, index))
+            .count();
+        assert!(tutorials > 0);
+        assert!((tutorials..count).all(|index| !is_tutorial(&map, index)));
+    }
+
+    #[test]
+    fn tutorial_levels_are_solvable() {
+        let map = map();
+        let assets = PlayerAssets::new(default(), default(), &mut Assets::default());
+        let tutorials = map
+            .iter_raw_levels()
+            .take_while(|raw_level| matches!(raw_level.get_bool_field("Tutorial"), Ok(true)));
+        for raw_level in tutorials {
+            let mut app = load_raw_level(raw_level);
+            let world = app.world_mut();
+            let obstacles: Vec<SimObstacle> = world
+                .query::<(&GridTransform, &Obstacle)>()
+                .iter(world)
+                .map(|(pos, obstacle)| SimObstacle {
+                    pos: pos.0,
+                    patrol: obstacle.patrol().clone(),
+                    kind: obstacle.kind,
+                    timing: obstacle.timing,
+                })
+                .collect();
+            let mut level = world.resource::<Level>().clone();
+            assert!(
+                level.get_spawn().is_some(),
+                "`{}` has no start",
+                raw_level.identifier
+            );
+            let solutions = solutions(raw_level);
+            // Levels start with `Walk` only.
+            let mut script = vec![ScriptCommand::Walk];
+            loop {
+                let start = SimState {
+                    player: level.last_checkpoint,
+                    x_dir: 1,
+                    cursor: 0,
+                    ticks: 0,
+                    obstacles: obstacles.clone(),
+                };
+                let run = simulate(&level, &assets, &script, start, 100);
+                match run.end {
+                    Some(SimEnd::Goal(_)) => break,
+                    Some(SimEnd::Checkpoint(pos)) => {
+                        level.last_checkpoint = pos;
+                        script.clone_from(&solutions[&pos]);
+                    }
+                    end => panic!("`{}` ended with {end:?}", raw_level.identifier),
+                }
+            }
+        }
+    }
+}
@@ -1,15 +1,44 @@
 //! Development tools for the game. This plugin is only enabled in dev builds.
 
+use std::{
+    collections::VecDeque,
+    fmt::{Debug, Write as _},
+    sync::{Arc, Mutex},
+};
+
 use bevy::{
     dev_tools::{
         states::log_transitions,
         ui_debug_overlay::{DebugUiPlugin, UiDebugOptions},
     },
     input::common_conditions::input_just_pressed,
+    log::{
+        tracing_subscriber::{layer::Context, Layer},
+        BoxedLayer,
+    },
     prelude::*,
+    render::texture::ImageSampler,
+    utils::tracing::{
+        self,
+        field::{Field, Visit},
+        Level,
+        Subscriber,
+    },
+    window::PrimaryWindow,
 };
 
-use crate::screens::Screen;
+use crate::{
+    demo::{
+        animation::PlayerAssets,
+        editor::{EditorAssets, EditorState},
+        level::{self, GridTransform, NextGridTransform, WorldGrid},
+        obstacle::Obstacle,
+        player::Player,
+    },
+    screens::Screen,
+    theme::palette::NODE_BACKGROUND,
+    AppSet,
+};
 
 pub(super) fn plugin(app: &mut App) {
     // Log `Screen` state transitions.
@@ -21,10 +50,362 @@ pub(super) fn plugin(app: &mut App) {
         Update,
         toggle_debug_ui.run_if(input_just_pressed(TOGGLE_KEY)),
     );
+
+    // Show recent log lines in game, since the console is awkward to reach on web.
+    app.init_resource::<LogViewer>();
+    app.add_systems(
+        Update,
+        (
+            toggle_log_viewer.run_if(input_just_pressed(LOG_TOGGLE_KEY)),
+            cycle_log_level.run_if(input_just_pressed(LOG_LEVEL_KEY)),
+            update_log_viewer.run_if(resource_exists::<LogBuffer>),
+        )
+            .chain(),
+    );
+
+    // Show what is in the cell under the cursor.
+    app.add_systems(
+        Update,
+        (
+            toggle_cell_readout.run_if(input_just_pressed(CELL_READOUT_KEY)),
+            update_cell_readout,
+        )
+            .chain()
+            .run_if(in_state(Screen::Gameplay)),
+    );
+
+    // Compare the pixel art with smooth sampling.
+    app.add_systems(
+        Update,
+        toggle_smooth_sampling.run_if(input_just_pressed(SAMPLING_TOGGLE_KEY)),
+    );
+
+    // Log where everything is at every tick boundary.
+    app.init_resource::<TickLog>();
+    app.add_systems(
+        Update,
+        (
+            toggle_tick_log.run_if(input_just_pressed(TICK_LOG_KEY)),
+            log_tick_positions
+                .after(level::update_tick_timer)
+                .in_set(AppSet::TickTimers)
+                .run_if(|log: Res<TickLog>| log.0),
+        )
+            .run_if(in_state(Screen::Gameplay)),
+    );
+
+    // Teleport the player to a clicked cell.
+    app.add_systems(
+        Update,
+        teleport_player
+            .run_if(in_state(Screen::Gameplay).and_then(input_just_pressed(TELEPORT_BUTTON))),
+    );
 }
 
 const TOGGLE_KEY: KeyCode = KeyCode::Backquote;
 
+const TICK_LOG_KEY: KeyCode = KeyCode::F6;
+
+/// Whether positions are logged at every tick, off by default to avoid spam.
+#[derive(Resource, Default)]
+struct TickLog(bool);
+
+fn toggle_tick_log(mut log: ResMut<TickLog>) {
+    log.0 = !log.0;
+    log::info!("Tick log: {}", log.0);
+}
+
+/// Log the cells of the player and the obstacles when a tick ends, right
+/// after `update_tick_timer` committed their moves.
+fn log_tick_positions(
+    tick: Res<level::AnimationTick>,
+    tick_count: Res<level::TickCount>,
+    player: Query<&GridTransform, With<Player>>,
+    obstacles: Query<(Entity, &GridTransform, &Obstacle)>,
+) {
+    if !tick.0.just_finished() {
+        return;
+    }
+    for pos in &player {
+        log::info!("[tick {}] Player at {}", tick_count.0, pos.0);
+    }
+    for (entity, pos, obstacle) in &obstacles {
+        log::info!(
+            "[tick {}] {:?} {entity} at {}",
+            tick_count.0,
+            obstacle.kind,
+            pos.0
+        );
+    }
+}
+
 fn toggle_debug_ui(mut options: ResMut<UiDebugOptions>) {
     options.toggle();
 }
+
+const SAMPLING_TOGGLE_KEY: KeyCode = KeyCode::F5;
+
+/// Switch the sprites between the default `nearest` sampling and `linear`
+/// sampling, without reloading them.
+fn toggle_smooth_sampling(
+    player_assets: Option<Res<PlayerAssets>>,
+    editor_assets: Option<Res<EditorAssets>>,
+    mut images: ResMut<Assets<Image>>,
+    mut smooth: Local<bool>,
+) {
+    *smooth = !*smooth;
+    let sampler = match *smooth {
+        true => ImageSampler::linear(),
+        false => ImageSampler::nearest(),
+    };
+    let player_images = player_assets
+        .iter()
+        .flat_map(|assets| [assets.texture.clone(), assets.hazard_texture.clone()]);
+    let editor_images = editor_assets
+        .iter()
+        .flat_map(|assets| [assets.icons.clone(), assets.cursor.clone()]);
+    for handle in player_images.chain(editor_images) {
+        if let Some(image) = images.get_mut(&handle) {
+            image.sampler = sampler.clone();
+        }
+    }
+    log::info!("Smooth sampling: {}", *smooth);
+}
+
+/// Has to be held while clicking, to avoid teleporting by accident.
+const TELEPORT_MODIFIERS: [KeyCode; 2] = [KeyCode::ControlLeft, KeyCode::ControlRight];
+const TELEPORT_BUTTON: MouseButton = MouseButton::Left;
+
+fn teleport_player(
+    input: Res<ButtonInput<KeyCode>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<IsDefaultUiCamera>>,
+    grid: Res<WorldGrid>,
+    level: Res<level::Level>,
+    editor_state: Res<EditorState>,
+    mut player: Query<(&mut GridTransform, &mut NextGridTransform), With<Player>>,
+) {
+    // Moving the player in the middle of a run would break its animation.
+    if !input.any_pressed(TELEPORT_MODIFIERS) || !editor_state.enabled {
+        return;
+    }
+    let Some(cell) = hovered_cell(&window, &camera, &grid) else {
+        return;
+    };
+    log::info!("Teleporting to {cell} (solid: {})", level.is_solid(cell));
+    for (mut pos, mut next_pos) in &mut player {
+        pos.0 = cell;
+        next_pos.0 = cell;
+    }
+}
+
+/// The grid cell under the mouse cursor.
+fn hovered_cell(
+    window: &Query<&Window, With<PrimaryWindow>>,
+    camera: &Query<(&Camera, &GlobalTransform), With<IsDefaultUiCamera>>,
+    grid: &WorldGrid,
+) -> Option<IVec2> {
+    let window = window.get_single().ok()?;
+    let (camera, camera_transform) = camera.get_single().ok()?;
+    let world = camera.viewport_to_world_2d(camera_transform, window.cursor_position()?)?;
+    Some(grid.world_to_grid(world))
+}
+
+const CELL_READOUT_KEY: KeyCode = KeyCode::F4;
+
+#[derive(Component)]
+struct CellReadout;
+
+fn toggle_cell_readout(readout: Query<Entity, With<CellReadout>>, mut commands: Commands) {
+    if let Ok(readout) = readout.get_single() {
+        commands.entity(readout).despawn_recursive();
+        return;
+    }
+    commands.spawn((
+        Name::new("Cell Readout"),
+        CellReadout,
+        TextBundle::from_section("", TextStyle {
+            font_size: 14.0,
+            ..default()
+        })
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            padding: UiRect::all(Val::Px(5.0)),
+            ..default()
+        })
+        .with_background_color(NODE_BACKGROUND.with_alpha(0.8)),
+        StateScoped(Screen::Gameplay),
+    ));
+}
+
+fn update_cell_readout(
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<IsDefaultUiCamera>>,
+    grid: Res<WorldGrid>,
+    level: Res<level::Level>,
+    obstacles: Query<(&GridTransform, &Obstacle)>,
+    mut readout: Query<&mut Text, With<CellReadout>>,
+) {
+    let Ok(mut text) = readout.get_single_mut() else {
+        return;
+    };
+    let value = match hovered_cell(&window, &camera, &grid) {
+        Some(cell) => {
+            let mut contents = Vec::new();
+            if level.is_solid(cell) {
+                contents.push("solid".to_string());
+            }
+            if level.is_checkpoint(cell) {
+                contents.push("checkpoint".to_string());
+            }
+            for (pos, obstacle) in &obstacles {
+                if pos.0 == cell {
+                    contents.push(format!("{:?}", obstacle.kind));
+                }
+            }
+            if contents.is_empty() {
+                contents.push("empty".to_string());
+            }
+            format!("Cell {cell}: {}", contents.join(", "))
+        }
+        None => "Cell: -".to_string(),
+    };
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+const LOG_TOGGLE_KEY: KeyCode = KeyCode::F2;
+const LOG_LEVEL_KEY: KeyCode = KeyCode::F3;
+
+/// How many log lines are kept around.
+const LOG_CAPACITY: usize = 200;
+/// How many of the most recent matching lines are shown.
+const LOG_LINES_SHOWN: usize = 20;
+
+/// Install as the custom layer of the `LogPlugin` to capture log lines for
+/// the in-game log viewer.
+pub fn log_layer(app: &mut App) -> Option<BoxedLayer> {
+    let buffer = LogBuffer::default();
+    app.insert_resource(buffer.clone());
+    Some(Box::new(LogBufferLayer(buffer)))
+}
+
+struct LogLine {
+    level: Level,
+    message: String,
+}
+
+/// Recent log lines, shared between the tracing layer and the app.
+#[derive(Resource, Clone, Default)]
+struct LogBuffer(Arc<Mutex<VecDeque<LogLine>>>);
+
+struct LogBufferLayer(LogBuffer);
+
+impl<S: Subscriber> Layer<S> for LogBufferLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let mut message = String::new();
+        event.record(&mut MessageVisitor(&mut message));
+        let Ok(mut lines) = self.0 .0.lock() else {
+            return;
+        };
+        if lines.len() >= LOG_CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(LogLine {
+            level: *event.metadata().level(),
+            message,
+        });
+    }
+}
+
+struct MessageVisitor<'a>(&'a mut String);
+
+impl Visit for MessageVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{value:?}");
+        }
+    }
+}
+
+#[derive(Resource)]
+struct LogViewer {
+    /// Only lines at least this severe are shown.
+    min_level: Level,
+}
+
+impl Default for LogViewer {
+    fn default() -> Self {
+        Self {
+            min_level: Level::INFO,
+        }
+    }
+}
+
+#[derive(Component)]
+struct LogPanel;
+
+fn toggle_log_viewer(panel: Query<Entity, With<LogPanel>>, mut commands: Commands) {
+    if let Ok(panel) = panel.get_single() {
+        commands.entity(panel).despawn_recursive();
+        return;
+    }
+    commands.spawn((
+        Name::new("Log Viewer"),
+        LogPanel,
+        TextBundle::from_section("", TextStyle {
+            font_size: 14.0,
+            ..default()
+        })
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            max_width: Val::Percent(50.0),
+            padding: UiRect::all(Val::Px(5.0)),
+            ..default()
+        })
+        .with_background_color(NODE_BACKGROUND.with_alpha(0.8)),
+    ));
+}
+
+fn cycle_log_level(mut viewer: ResMut<LogViewer>) {
+    viewer.min_level = match viewer.min_level {
+        Level::ERROR => Level::WARN,
+        Level::WARN => Level::INFO,
+        Level::INFO => Level::DEBUG,
+        Level::DEBUG => Level::TRACE,
+        _ => Level::ERROR,
+    };
+}
+
+fn update_log_viewer(
+    buffer: Res<LogBuffer>,
+    viewer: Res<LogViewer>,
+    mut panel: Query<&mut Text, With<LogPanel>>,
+) {
+    let Ok(mut text) = panel.get_single_mut() else {
+        return;
+    };
+    let Ok(lines) = buffer.0.lock() else {
+        return;
+    };
+
+    // More verbose levels compare as greater.
+    let shown: Vec<_> = lines
+        .iter()
+        .rev()
+        .filter(|line| line.level <= viewer.min_level)
+        .take(LOG_LINES_SHOWN)
+        .collect();
+    let mut value = format!("Log (level {} or worse, F3 to change)\n", viewer.min_level);
+    for line in shown.into_iter().rev() {
+        let _ = writeln!(value, "{:>5} {}", line.level, line.message);
+    }
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
@@ -0,0 +1,186 @@
+//! Which key triggers which action in the editor and while running a script.
+
+use bevy::prelude::*;
+
+use crate::demo::action::{Facing, Parity, ScriptCommand, DEFAULT_REPEAT};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<KeyBindings>();
+}
+
+/// Something the player can do with a single key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyAction {
+    /// Type the command into the editor at the cursor.
+    Insert(ScriptCommand),
+    CursorLeft,
+    CursorRight,
+    CursorToStart,
+    CursorToEnd,
+    DeleteBackward,
+    DeleteForward,
+    Submit,
+    /// Abort the run and go back to editing.
+    Edit,
+    ToggleAutoplay,
+    Respawn,
+    /// Step in manual mode, or fast forward during autoplay.
+    Step,
+    /// Undo the last step in manual mode.
+    StepBack,
+    /// Cycle through the playback speeds of a run.
+    CyclePlaybackSpeed,
+    /// Move the cursor past the next command that would fail.
+    FocusFailure,
+    /// Switch between the full editor and the compact overview.
+    ToggleCompact,
+    /// Watch the script play out before submitting it.
+    Preview,
+    /// Open or close the replay of the last run.
+    ToggleReplay,
+    ResetZoom,
+    /// Zoom the camera to fit the whole level.
+    FrameLevel,
+    /// Undo an edit, with Ctrl held.
+    Undo,
+    /// Redo an undone edit, with Ctrl held.
+    Redo,
+    /// Copy the selected commands, with Ctrl held.
+    Copy,
+    /// Paste the copied commands at the cursor, with Ctrl held.
+    Paste,
+    /// Dev-only manual movement, read as held keys at the start of each tick.
+    DevLeft,
+    DevRight,
+    DevClimb,
+    DevDrop,
+    DevIdle,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct KeyBindings {
+    /// Every action exactly once. Commands are typed in this order when their
+    /// keys repeat in the same frame.
+    keys: Vec<(KeyAction, KeyCode)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let commands = [
+            (ScriptCommand::Walk, KeyCode::KeyW),
+            (ScriptCommand::Climb, KeyCode::KeyC),
+            (ScriptCommand::Drop, KeyCode::KeyD),
+            (ScriptCommand::Idle, KeyCode::KeyI),
+            (ScriptCommand::Jump, KeyCode::KeyJ),
+            (ScriptCommand::Turn, KeyCode::KeyT),
+            (ScriptCommand::Fall, KeyCode::KeyF),
+            (ScriptCommand::Face(Facing::Left), KeyCode::Comma),
+            (ScriptCommand::Face(Facing::Right), KeyCode::Period),
+            (ScriptCommand::Sync(Parity::Even), KeyCode::KeyE),
+            (ScriptCommand::Sync(Parity::Odd), KeyCode::KeyO),
+            (ScriptCommand::Mark, KeyCode::KeyB),
+            (ScriptCommand::Return, KeyCode::KeyH),
+            (ScriptCommand::Breakpoint, KeyCode::KeyK),
+            (ScriptCommand::OpenBracket, KeyCode::BracketLeft),
+            (ScriptCommand::Repeat(DEFAULT_REPEAT), KeyCode::KeyL),
+            (ScriptCommand::CloseBracket, KeyCode::BracketRight),
+        ]
+        .map(|(command, key)| (KeyAction::Insert(command), key));
+        let others = [
+            (KeyAction::CursorLeft, KeyCode::ArrowLeft),
+            (KeyAction::CursorRight, KeyCode::ArrowRight),
+            (KeyAction::CursorToStart, KeyCode::ArrowUp),
+            (KeyAction::CursorToEnd, KeyCode::ArrowDown),
+            (KeyAction::DeleteBackward, KeyCode::Backspace),
+            (KeyAction::DeleteForward, KeyCode::Delete),
+            (KeyAction::Submit, KeyCode::Enter),
+            (KeyAction::Edit, KeyCode::Escape),
+            (KeyAction::ToggleAutoplay, KeyCode::KeyG),
+            (KeyAction::Respawn, KeyCode::KeyR),
+            (KeyAction::Step, KeyCode::KeyF),
+            (KeyAction::StepBack, KeyCode::KeyZ),
+            (KeyAction::CyclePlaybackSpeed, KeyCode::KeyX),
+            (KeyAction::FocusFailure, KeyCode::KeyN),
+            (KeyAction::ToggleCompact, KeyCode::KeyM),
+            (KeyAction::Preview, KeyCode::KeyP),
+            (KeyAction::ToggleReplay, KeyCode::KeyV),
+            (KeyAction::ResetZoom, KeyCode::Digit0),
+            (KeyAction::FrameLevel, KeyCode::Home),
+            (KeyAction::Undo, KeyCode::KeyZ),
+            (KeyAction::Redo, KeyCode::KeyY),
+            (KeyAction::Copy, KeyCode::KeyC),
+            (KeyAction::Paste, KeyCode::KeyV),
+            // The editor keys only move the cursor while editing, when the
+            // player cannot be moved.
+            (KeyAction::DevLeft, KeyCode::ArrowLeft),
+            (KeyAction::DevRight, KeyCode::ArrowRight),
+            (KeyAction::DevClimb, KeyCode::ArrowUp),
+            (KeyAction::DevDrop, KeyCode::ArrowDown),
+            (KeyAction::DevIdle, KeyCode::Space),
+        ];
+        Self {
+            keys: commands.into_iter().chain(others).collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// The key bound to the action.
+    pub fn key(&self, action: KeyAction) -> KeyCode {
+        self.keys
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|&(_, key)| key)
+            .expect("every action should have a key")
+    }
+
+    /// Bind the action to another key. Other actions bound to the same key
+    /// keep it, which is only reported since the actions may be used at
+    /// different times.
+    pub fn rebind(&mut self, action: KeyAction, key: KeyCode) {
+        for &(bound, bound_key) in &self.keys {
+            if bound != action && bound_key == key {
+                log::warn!("{action:?} is now bound to {key:?}, like {bound:?}");
+            }
+        }
+        for (bound, bound_key) in &mut self.keys {
+            if *bound == action {
+                *bound_key = key;
+            }
+        }
+    }
+
+    /// The key of each command that can be typed into the editor.
+    pub fn commands(&self) -> impl Iterator<Item = (KeyCode, ScriptCommand)> + '_ {
+        self.keys.iter().filter_map(|&(action, key)| match action {
+            KeyAction::Insert(command) => Some((key, command)),
+            _ => None,
+        })
+    }
+}
+
+/// Run condition that is true in the frame the key of `action` is pressed.
+pub fn action_just_pressed(
+    action: KeyAction,
+) -> impl FnMut(Res<ButtonInput<KeyCode>>, Res<KeyBindings>) -> bool + Clone {
+    move |input: Res<ButtonInput<KeyCode>>, bindings: Res<KeyBindings>| {
+        input.just_pressed(bindings.key(action))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_a_command_changes_what_its_key_types() {
+        let mut bindings = KeyBindings::default();
+        bindings.rebind(KeyAction::Insert(ScriptCommand::Walk), KeyCode::KeyQ);
+        let walk_keys: Vec<_> = bindings
+            .commands()
+            .filter(|&(_, command)| command == ScriptCommand::Walk)
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(walk_keys, [KeyCode::KeyQ]);
+    }
+}
@@ -3,7 +3,11 @@ pub mod audio;
 mod demo;
 #[cfg(feature = "dev")]
 mod dev_tools;
+pub mod key_bindings;
+mod modding;
+mod persistence;
 mod screens;
+mod settings;
 mod theme;
 
 use bevy::{
@@ -11,8 +15,20 @@ use bevy::{
     audio::{AudioPlugin, Volume},
     input::mouse::MouseWheel,
     prelude::*,
+    ui::RelativeCursorPosition,
+    window::{CursorMoved, PrimaryWindow},
 };
+#[cfg(feature = "testing")]
+pub use demo::testing;
+use demo::{
+    animation::Easing,
+    editor::EditorUI,
+    level::{Level, WorldGrid},
+};
+use key_bindings::{action_just_pressed, KeyAction};
 use screens::Screen;
+use settings::Settings;
+use theme::interaction::text_input_focused;
 
 pub struct AppPlugin;
 
@@ -34,44 +50,73 @@ impl Plugin for AppPlugin {
         // Spawn the main camera.
         app.add_systems(Startup, spawn_camera);
 
+        // Let files in the mods folder override bundled assets.
+        #[cfg(not(target_family = "wasm"))]
+        modding::register_source(app);
+
         // Add Bevy plugins.
-        app.add_plugins(
-            DefaultPlugins
-                .set(AssetPlugin {
-                    // Wasm builds will check for meta files (that don't exist) if this isn't set.
-                    // This causes errors and even panics on web build on itch.
-                    // See https://github.com/bevyengine/bevy_github_ci_template/issues/48.
-                    meta_check: AssetMetaCheck::Never,
-                    ..default()
-                })
-                .set(WindowPlugin {
-                    primary_window: Window {
-                        title: "GMTK 2024".to_string(),
-                        canvas: Some("#bevy".to_string()),
-                        fit_canvas_to_parent: true,
-                        prevent_default_event_handling: true,
-                        ..default()
-                    }
-                    .into(),
-                    ..default()
-                })
-                .set(AudioPlugin {
-                    global_volume: GlobalVolume {
-                        volume: Volume::new(0.2),
-                    },
+        let default_plugins = DefaultPlugins
+            .set(AssetPlugin {
+                // Wasm builds will check for meta files (that don't exist) if this isn't set.
+                // This causes errors and even panics on web build on itch.
+                // See https://github.com/bevyengine/bevy_github_ci_template/issues/48.
+                meta_check: AssetMetaCheck::Never,
+                ..default()
+            })
+            .set(WindowPlugin {
+                primary_window: Window {
+                    title: "GMTK 2024".to_string(),
+                    canvas: Some("#bevy".to_string()),
+                    fit_canvas_to_parent: true,
+                    prevent_default_event_handling: true,
                     ..default()
-                }),
-        );
+                }
+                .into(),
+                ..default()
+            })
+            .set(AudioPlugin {
+                global_volume: GlobalVolume {
+                    volume: Volume::new(0.2),
+                },
+                ..default()
+            });
+        // Capture log lines for the in-game log viewer.
+        #[cfg(feature = "dev")]
+        let default_plugins = default_plugins.set(bevy::log::LogPlugin {
+            custom_layer: dev_tools::log_layer,
+            ..default()
+        });
+        app.add_plugins(default_plugins);
 
         // Add other plugins.
         app.add_plugins((
             asset_tracking::plugin,
             demo::plugin,
+            key_bindings::plugin,
             screens::plugin,
+            settings::plugin,
             theme::plugin,
         ));
 
         app.add_systems(Update, camera_zoom.run_if(in_state(Screen::Gameplay)));
+        app.add_systems(
+            Update,
+            (
+                camera_pan,
+                stop_camera_pan,
+                reset_zoom.run_if(action_just_pressed(KeyAction::ResetZoom)),
+                frame_level.run_if(action_just_pressed(KeyAction::FrameLevel)),
+            )
+                .chain()
+                .in_set(AppSet::RecordInput)
+                .run_if(in_state(Screen::Gameplay).and_then(not(text_input_focused))),
+        );
+        app.add_systems(
+            Update,
+            animate_camera_transition
+                .in_set(AppSet::UpdateCamera)
+                .run_if(resource_exists::<CameraTransition>),
+        );
 
         // Enable dev tools for dev builds.
         #[cfg(feature = "dev")]
@@ -94,12 +139,18 @@ enum AppSet {
     UpdateCamera,
 }
 
+/// Scale of the camera projection before zooming.
+const DEFAULT_SCALE: f32 = 0.25;
+/// Bounds of the camera projection scale when zooming.
+const MIN_SCALE: f32 = 0.1;
+const MAX_SCALE: f32 = 1.0;
+
 fn spawn_camera(mut commands: Commands) {
     commands.spawn((
         Name::new("Camera"),
         Camera2dBundle {
             projection: OrthographicProjection {
-                scale: 0.25,
+                scale: DEFAULT_SCALE,
                 ..default()
             },
             transform: Transform::from_translation(Vec3::new(16.0 * 15.0, 16.0 * 10.0, 100.0)),
@@ -115,13 +166,207 @@ fn spawn_camera(mut commands: Commands) {
     ));
 }
 
+/// While this exists the camera stays where the player dragged it instead of
+/// following the player. It is removed on a key press, or once the timer runs
+/// out if there is one.
+#[derive(Resource)]
+struct CameraPanned(Option<Timer>);
+
+impl CameraPanned {
+    const BUTTONS: [MouseButton; 1] = [MouseButton::Middle];
+    /// Seconds without dragging before the camera follows the player again.
+    const IDLE: f32 = 3.0;
+}
+
+/// Look around the level by dragging with the middle mouse button.
+fn camera_pan(
+    mut cursor_moved: EventReader<CursorMoved>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut camera: Query<(&OrthographicProjection, &mut Transform), With<IsDefaultUiCamera>>,
+    editor_ui: Query<&RelativeCursorPosition, With<EditorUI>>,
+    level: Res<Level>,
+    grid: Res<WorldGrid>,
+    panned: Option<ResMut<CameraPanned>>,
+    mut commands: Commands,
+) {
+    let delta: Vec2 = cursor_moved.read().filter_map(|moved| moved.delta).sum();
+    if !buttons.any_pressed(CameraPanned::BUTTONS) || delta == Vec2::ZERO {
+        return;
+    }
+    // Dragging over the editor is for the editor.
+    if editor_ui.iter().any(RelativeCursorPosition::mouse_over) {
+        return;
+    }
+    let Ok((projection, mut transform)) = camera.get_single_mut() else {
+        return;
+    };
+
+    // The world under the cursor moves along with it.
+    let mut target = transform.translation.xy() + delta * Vec2::new(-1.0, 1.0) * projection.scale;
+    if let Some(bounds) = level.bounds() {
+        let min = grid.project_to_world(bounds.min.as_vec2());
+        let max = grid.project_to_world(bounds.max.as_vec2());
+        target = target.clamp(min.min(max), min.max(max));
+    }
+    transform.translation = target.extend(transform.translation.z);
+
+    let idle = Timer::from_seconds(CameraPanned::IDLE, TimerMode::Once);
+    match panned {
+        Some(mut panned) => panned.0 = Some(idle),
+        None => commands.insert_resource(CameraPanned(Some(idle))),
+    }
+}
+
+/// Follow the player again after a while without dragging, or right away
+/// when a key is pressed.
+fn stop_camera_pan(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    panned: Option<ResMut<CameraPanned>>,
+    mut commands: Commands,
+) {
+    let Some(mut panned) = panned else {
+        return;
+    };
+    let mut idle = false;
+    if let Some(timer) = &mut panned.0 {
+        if !buttons.any_pressed(CameraPanned::BUTTONS) {
+            timer.tick(time.delta());
+        }
+        idle = timer.finished();
+    }
+    if idle || keys.get_just_pressed().next().is_some() {
+        commands.remove_resource::<CameraPanned>();
+    }
+}
+
+/// The camera moves smoothly to a new scale, and also to a new position if
+/// it does not follow the player.
+#[derive(Resource)]
+struct CameraTransition {
+    from_scale: f32,
+    to_scale: f32,
+    from: Vec2,
+    to: Option<Vec2>,
+    timer: Timer,
+}
+
+impl CameraTransition {
+    const DURATION: f32 = 0.4;
+    /// Empty space around the level when framing it, as a fraction of its
+    /// size.
+    const MARGIN: f32 = 0.1;
+
+    fn new(
+        projection: &OrthographicProjection,
+        transform: &Transform,
+        to_scale: f32,
+        to: Option<Vec2>,
+    ) -> Self {
+        Self {
+            from_scale: projection.scale,
+            to_scale,
+            from: transform.translation.xy(),
+            to,
+            timer: Timer::from_seconds(Self::DURATION, TimerMode::Once),
+        }
+    }
+}
+
+/// Go back to the default zoom, still following the player.
+fn reset_zoom(
+    camera: Query<(&OrthographicProjection, &Transform), With<IsDefaultUiCamera>>,
+    mut commands: Commands,
+) {
+    let Ok((projection, transform)) = camera.get_single() else {
+        return;
+    };
+    commands.insert_resource(CameraTransition::new(
+        projection,
+        transform,
+        DEFAULT_SCALE,
+        None,
+    ));
+}
+
+/// Zoom out to show the whole level. The camera stays there until the next
+/// key press or drag.
+fn frame_level(
+    camera: Query<(&OrthographicProjection, &Transform), With<IsDefaultUiCamera>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    level: Res<Level>,
+    grid: Res<WorldGrid>,
+    mut commands: Commands,
+) {
+    let (Ok((projection, transform)), Ok(window), Some(bounds)) =
+        (camera.get_single(), window.get_single(), level.bounds())
+    else {
+        return;
+    };
+    let center = grid.project_to_world(bounds.as_rect().center());
+    let scale = fit_scale(bounds, grid.cell_size(), window.size());
+    commands.insert_resource(CameraTransition::new(
+        projection,
+        transform,
+        scale,
+        Some(center),
+    ));
+    commands.insert_resource(CameraPanned(None));
+}
+
+/// The projection scale at which the cells in `bounds` just fit into a
+/// viewport of the given size, with a margin around them.
+fn fit_scale(bounds: IRect, cell_size: Vec2, viewport: Vec2) -> f32 {
+    let size = (bounds.size() + IVec2::ONE).as_vec2() * cell_size.abs();
+    let scale = (size * (1.0 + CameraTransition::MARGIN) / viewport).max_element();
+    scale.clamp(MIN_SCALE, MAX_SCALE)
+}
+
+fn animate_camera_transition(
+    time: Res<Time>,
+    mut transition: ResMut<CameraTransition>,
+    mut camera: Query<(&mut OrthographicProjection, &mut Transform), With<IsDefaultUiCamera>>,
+    mut commands: Commands,
+) {
+    transition.timer.tick(time.delta());
+    let eased = Easing::EaseInOut.apply(transition.timer.fraction());
+    for (mut projection, mut transform) in &mut camera {
+        projection.scale = transition.from_scale.lerp(transition.to_scale, eased);
+        if let Some(to) = transition.to {
+            let pos = transition.from.lerp(to, eased);
+            transform.translation = pos.extend(transform.translation.z);
+        }
+    }
+    if transition.timer.finished() {
+        commands.remove_resource::<CameraTransition>();
+    }
+}
+
 fn camera_zoom(
     mut evr_scroll: EventReader<MouseWheel>,
-    mut query: Query<&mut OrthographicProjection, With<IsDefaultUiCamera>>,
+    mut query: Query<(&mut OrthographicProjection, &mut Transform), With<IsDefaultUiCamera>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    editor_ui: Query<&RelativeCursorPosition, With<EditorUI>>,
+    settings: Res<Settings>,
 ) {
-    let Ok(mut projection) = query.get_single_mut() else {
+    let Ok((mut projection, mut transform)) = query.get_single_mut() else {
         return;
     };
+    // Scrolling over the editor moves its cursor instead.
+    if editor_ui.iter().any(RelativeCursorPosition::mouse_over) {
+        evr_scroll.clear();
+        return;
+    }
+
+    // Offset of the cursor from the center of the screen in world units at scale 1.
+    let cursor_offset = window
+        .get_single()
+        .ok()
+        .and_then(|window| {
+            Some((window.cursor_position()? - window.size() / 2.0) * Vec2::new(1.0, -1.0))
+        })
+        .unwrap_or(Vec2::ZERO);
 
     use bevy::input::mouse::MouseScrollUnit;
     for ev in evr_scroll.read() {
@@ -133,6 +378,90 @@ fn camera_zoom(
                 ev.y / 2000.0 // pixel units
             }
         };
-        projection.scale = (projection.scale - y_scroll).clamp(0.1, 1.0)
+        let y_scroll = match settings.invert_zoom {
+            true => -y_scroll,
+            false => y_scroll,
+        };
+        let old_scale = projection.scale;
+        projection.scale = (projection.scale - y_scroll).clamp(MIN_SCALE, MAX_SCALE);
+
+        // Keep the world point under the cursor fixed while zooming.
+        let shift = cursor_offset * (old_scale - projection.scale);
+        transform.translation += shift.extend(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce as _;
+
+    use super::*;
+    use crate::demo::testing::floor_with;
+
+    /// Move the cursor by `delta` and return where the camera ends up.
+    fn drag(world: &mut World, delta: Vec2) -> Vec2 {
+        world.resource_mut::<Events<CursorMoved>>().clear();
+        world.send_event(CursorMoved {
+            window: Entity::PLACEHOLDER,
+            position: Vec2::ZERO,
+            delta: Some(delta),
+        });
+        world.run_system_once(camera_pan);
+        let mut camera = world.query_filtered::<&Transform, With<IsDefaultUiCamera>>();
+        camera.single(world).translation.xy()
+    }
+
+    #[test]
+    fn dragging_moves_the_camera_within_the_level() {
+        let mut world = World::new();
+        world.init_resource::<Events<CursorMoved>>();
+        world.init_resource::<ButtonInput<MouseButton>>();
+        // From (-5, 0) to (12, 10).
+        world.insert_resource(floor_with([IVec2::new(0, 10)]));
+        let grid = WorldGrid::default();
+        world.insert_resource(grid);
+        let start = grid.project_to_world(Vec2::new(3.0, 5.0));
+        world.spawn((
+            OrthographicProjection {
+                scale: 2.0,
+                ..default()
+            },
+            Transform::from_translation(start.extend(0.0)),
+            IsDefaultUiCamera,
+        ));
+
+        // Only dragging with the middle button pans.
+        world
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .press(MouseButton::Right);
+        assert_eq!(drag(&mut world, Vec2::new(10.0, 4.0)), start);
+        world
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .press(MouseButton::Middle);
+        // The world under the cursor moves along with it.
+        let moved = drag(&mut world, Vec2::new(10.0, 4.0));
+        assert_eq!(moved, start + Vec2::new(-20.0, 8.0));
+
+        let moved = drag(&mut world, Vec2::new(-1000.0, -1000.0));
+        let corner = grid.project_to_world(Vec2::new(12.0, 0.0));
+        assert_eq!(moved, corner);
+    }
+
+    #[test]
+    fn fit_scale_fits_the_longer_side() {
+        let cell = Vec2::splat(16.0);
+        let viewport = Vec2::new(1280.0, 720.0);
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-4;
+        // 20 by 10 cells are 320 by 160 pixels, 352 by 176 with the margin.
+        let wide = IRect::new(0, 0, 19, 9);
+        assert!(close(fit_scale(wide, cell, viewport), 352.0 / 1280.0));
+        // 10 by 30 cells are limited by the height instead.
+        let tall = IRect::new(0, 0, 9, 29);
+        assert!(close(fit_scale(tall, cell, viewport), 528.0 / 720.0));
+        // Huge and tiny levels stay within the zoom limits.
+        let huge = IRect::new(0, 0, 199, 99);
+        assert_eq!(fit_scale(huge, cell, viewport), MAX_SCALE);
+        let tiny = IRect::new(0, 0, 0, 0);
+        assert_eq!(fit_scale(tiny, cell, viewport), MIN_SCALE);
     }
 }
@@ -0,0 +1,56 @@
+//! Asset overrides for modding. On native, a file in the `mods` folder next to
+//! `assets` is loaded instead of the bundled asset with the same path, which
+//! lets players reskin the game or replace the levels without recompiling.
+//! Web builds always use the bundled assets.
+
+use bevy::asset::AssetPath;
+#[cfg(not(target_family = "wasm"))]
+use bevy::{
+    asset::io::{file::FileAssetReader, AssetSource},
+    prelude::*,
+};
+
+#[cfg(not(target_family = "wasm"))]
+const MOD_SOURCE: &str = "mods";
+#[cfg(not(target_family = "wasm"))]
+const MOD_DIR: &str = "mods";
+
+/// Register the asset source for mods. This has to happen before the
+/// `AssetPlugin` is added.
+#[cfg(not(target_family = "wasm"))]
+pub fn register_source(app: &mut App) {
+    app.register_asset_source(
+        MOD_SOURCE,
+        // Without a watcher, since the mods folder might not exist.
+        AssetSource::build().with_reader(|| Box::new(FileAssetReader::new(MOD_DIR))),
+    );
+}
+
+/// The path to load the asset at `path` from, preferring the mods folder.
+#[cfg(not(target_family = "wasm"))]
+pub fn resolve(path: &'static str) -> AssetPath<'static> {
+    let modded = FileAssetReader::get_base_path().join(MOD_DIR).join(path);
+    if modded.is_file() {
+        log::info!("Loading `{path}` from the mods folder");
+        AssetPath::from(path).with_source(MOD_SOURCE)
+    } else {
+        AssetPath::from(path)
+    }
+}
+
+#[cfg(target_family = "wasm")]
+pub fn resolve(path: &'static str) -> AssetPath<'static> {
+    AssetPath::from(path)
+}
+
+/// The contents of the file at `path` in the mods folder, if there is one.
+#[cfg(not(target_family = "wasm"))]
+pub fn read_override(path: &str) -> Option<String> {
+    let modded = FileAssetReader::get_base_path().join(MOD_DIR).join(path);
+    std::fs::read_to_string(modded).ok()
+}
+
+#[cfg(target_family = "wasm")]
+pub fn read_override(_path: &str) -> Option<String> {
+    None
+}
@@ -0,0 +1,118 @@
+//! Simple key-value storage that survives restarts.
+//! Values are stored as JSON in `localStorage` on web and as one file per key
+//! in the user's data directory on native.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Load the value stored under `key`, if there is one and it can be parsed.
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    parse(key, &read(key)?)
+}
+
+/// Parse the JSON stored under `key`, if it is valid.
+pub fn parse<T: DeserializeOwned>(key: &str, json: &str) -> Option<T> {
+    match serde_json::from_str(json) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            log::error!("Could not parse saved `{key}`: {err}");
+            None
+        }
+    }
+}
+
+/// Store `value` under `key`. Returns whether saving succeeded.
+pub fn save<T: Serialize>(key: &str, value: &T) -> bool {
+    let json = match serde_json::to_string(value) {
+        Ok(json) => json,
+        Err(err) => {
+            log::error!("Could not serialize `{key}`: {err}");
+            return false;
+        }
+    };
+    write(key, &json)
+}
+
+#[cfg(target_family = "wasm")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+#[cfg(target_family = "wasm")]
+fn read(key: &str) -> Option<String> {
+    local_storage()?.get_item(key).ok().flatten()
+}
+
+#[cfg(target_family = "wasm")]
+fn write(key: &str, json: &str) -> bool {
+    let Some(storage) = local_storage() else {
+        log::error!("`localStorage` is not available");
+        return false;
+    };
+    // This fails when the storage quota is exceeded.
+    let result = storage.set_item(key, json);
+    if result.is_err() {
+        log::error!("Could not save `{key}` to `localStorage`");
+    }
+    result.is_ok()
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn data_dir() -> std::path::PathBuf {
+    use std::{env::var_os, path::PathBuf};
+
+    let base = if cfg!(target_os = "windows") {
+        var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    base.unwrap_or_default().join("gmtk-2024")
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn path(key: &str) -> std::path::PathBuf {
+    data_dir().join(format!("{key}.json"))
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn read(key: &str) -> Option<String> {
+    // Tests neither depend on nor change the saves of the player.
+    if cfg!(test) {
+        return None;
+    }
+    std::fs::read_to_string(path(key)).ok()
+}
+
+/// Append `line` to the log file `name`, creating it if needed.
+#[cfg(not(target_family = "wasm"))]
+pub fn append_line(name: &str, line: &str) -> bool {
+    use std::io::Write as _;
+
+    let path = data_dir().join(format!("{name}.jsonl"));
+    let result = std::fs::create_dir_all(data_dir()).and_then(|()| {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{line}")
+    });
+    if let Err(err) = &result {
+        log::error!("Could not append to `{name}`: {err}");
+    }
+    result.is_ok()
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn write(key: &str, json: &str) -> bool {
+    if cfg!(test) {
+        return true;
+    }
+    let result = std::fs::create_dir_all(data_dir()).and_then(|()| std::fs::write(path(key), json));
+    if let Err(err) = &result {
+        log::error!("Could not save `{key}`: {err}");
+    }
+    result.is_ok()
+}
@@ -1,11 +1,19 @@
 //! The screen state for the main gameplay.
 
-use bevy::{prelude::*, ui::Val::*};
+use bevy::{
+    prelude::*,
+    ui::{RelativeCursorPosition, Val::*},
+};
 
 use crate::{
-    demo::{editor::EditorUI, level::spawn_level as spawn_level_command},
+    demo::{
+        editor::EditorUI,
+        level::spawn_level as spawn_level_command,
+        solutions::{delete_slot, load_slot, save_slot, SlotList, SlotNameInput},
+    },
     screens::Screen,
-    theme::palette::LABEL_TEXT,
+    settings::{Difficulty, Settings},
+    theme::{palette::LABEL_TEXT, prelude::*},
 };
 
 pub(super) fn plugin(app: &mut App) {
@@ -15,15 +23,48 @@ pub(super) fn plugin(app: &mut App) {
 #[derive(Component)]
 pub struct AutoplayLabel;
 impl AutoplayLabel {
-    pub const DISABLED: &'static str = " (step F) (autoplay G) (respawn R)";
+    pub const DISABLED: &'static str =
+        " (step F) (step 5 Shift+F) (back Z) (autoplay G) (respawn R) (edit Esc) (compact M)";
     pub const DISABLED_BIG: &'static str = "MANUAL MODE";
-    pub const ENABLED: &'static str = "autoplay enabled (fast forward F) (manual G) (respawn R)";
+    pub const ENABLED: &'static str =
+        "autoplay enabled (fast forward F) (manual G) (respawn R) (edit Esc) (compact M)";
 }
 
 #[derive(Component)]
 pub struct UnlockedList;
 
-fn spawn_level(mut commands: Commands) {
+/// Shows how many of the commands in the level are unlocked.
+#[derive(Component)]
+pub struct UnlockProgressLabel;
+
+/// Shows how many checkpoints were visited in levels where all of them have
+/// to be visited.
+#[derive(Component)]
+pub struct CheckpointProgressLabel;
+
+/// Shows the length of the script against the command budget.
+#[derive(Component)]
+pub struct ScriptLengthLabel;
+
+/// Tells the player which brackets were added to balance the submitted
+/// script, or that it was rejected for unbalanced brackets.
+#[derive(Component)]
+pub struct BracketNoticeLabel;
+
+/// Tells the player when the script ran to its end without reaching a
+/// checkpoint.
+#[derive(Component)]
+pub struct ScriptFinishedLabel;
+
+/// Shows how often the innermost loop has repeated during a run.
+#[derive(Component)]
+pub struct LoopCounterLabel;
+
+/// Shows the moves and time of the current run.
+#[derive(Component)]
+pub struct RunStatsLabel;
+
+fn spawn_level(mut commands: Commands, settings: Res<Settings>) {
     commands.add(spawn_level_command);
     commands
         .spawn((Name::new("Gameplay UI Root"), NodeBundle {
@@ -41,18 +82,59 @@ fn spawn_level(mut commands: Commands) {
         }))
         .insert(StateScoped(Screen::Gameplay))
         .with_children(|children| {
-            children.spawn((Name::new("Editor UI"), EditorUI, NodeBundle {
-                style: Style {
-                    width: Auto,
-                    height: Percent(10.0),
-                    justify_content: JustifyContent::Center,
-                    align_items: AlignItems::Center,
-                    flex_direction: FlexDirection::Row,
+            children.spawn((
+                Name::new("Editor UI"),
+                EditorUI,
+                NodeBundle {
+                    style: Style {
+                        width: Auto,
+                        height: Percent(10.0),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::hsl(0., 0., 0.9)),
                     ..default()
                 },
-                background_color: BackgroundColor(Color::hsl(0., 0., 0.9)),
-                ..default()
-            }));
+                // Used to scroll through the script with the mouse wheel.
+                RelativeCursorPosition::default(),
+            ));
+            children.label("").insert(ScriptLengthLabel);
+            children.label("").insert(BracketNoticeLabel);
+            children.label("").insert(LoopCounterLabel);
+            children.label("").insert(RunStatsLabel);
+            children.label("").insert(ScriptFinishedLabel);
+            children
+                .spawn((Name::new("Solution Slots"), NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Px(10.0),
+                        top: Percent(15.0),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Px(5.0),
+                        ..default()
+                    },
+                    ..default()
+                }))
+                .with_children(|children| {
+                    children.text_input().insert(SlotNameInput);
+                    children
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                column_gap: Px(5.0),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|children| {
+                            children.small_button("Save").observe(save_slot);
+                            children.small_button("Load").observe(load_slot);
+                            children.small_button("Delete").observe(delete_slot);
+                        });
+                    children.label("No saved solutions").insert(SlotList);
+                });
             children
                 .spawn(NodeBundle {
                     style: Style {
@@ -79,6 +161,35 @@ fn spawn_level(mut commands: Commands) {
                             ..default()
                         })
                         .with_children(|children| {
+                            // Make it clear that this run does not count as a regular solve.
+                            if settings.difficulty == Difficulty::Assist {
+                                children.spawn(
+                                    TextBundle::from_section("ASSIST ", TextStyle {
+                                        font_size: 24.0,
+                                        color: LABEL_TEXT,
+                                        ..default()
+                                    })
+                                    .with_no_wrap(),
+                                );
+                            }
+                            children.spawn((
+                                CheckpointProgressLabel,
+                                TextBundle::from_section("", TextStyle {
+                                    font_size: 24.0,
+                                    color: LABEL_TEXT,
+                                    ..default()
+                                })
+                                .with_no_wrap(),
+                            ));
+                            children.spawn((
+                                UnlockProgressLabel,
+                                TextBundle::from_section("", TextStyle {
+                                    font_size: 24.0,
+                                    color: LABEL_TEXT,
+                                    ..default()
+                                })
+                                .with_no_wrap(),
+                            ));
                             children.spawn((
                                 AutoplayLabel,
                                 TextBundle::from_section(AutoplayLabel::ENABLED, TextStyle {
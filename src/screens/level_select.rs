@@ -0,0 +1,172 @@
+//! A screen to pick which level to play, opened from the title screen.
+
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::raw_level_accessor::RawLevelAccessor, prelude::*};
+
+use crate::{
+    demo::{level::LevelAssets, progress::Progress, style::best_grade},
+    screens::Screen,
+    theme::{interaction::InteractionPalette, palette::BUTTON_LOCKED_BACKGROUND, prelude::*},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::LevelSelect), spawn_level_select_screen);
+}
+
+/// A button that starts the level with this index.
+#[derive(Component, Debug)]
+struct LevelButton(usize);
+
+/// A level button that does nothing until the previous level is progressed.
+#[derive(Component, Debug)]
+struct LockedLevel;
+
+fn spawn_level_select_screen(
+    mut commands: Commands,
+    level_assets: Res<LevelAssets>,
+    ldtk_projects: Res<Assets<LdtkProject>>,
+    progress: Res<Progress>,
+) {
+    let level_count = ldtk_projects
+        .get(&level_assets.ldtk_project)
+        .map_or(0, |project| project.iter_raw_levels().count());
+
+    commands
+        .ui_root()
+        .insert(StateScoped(Screen::LevelSelect))
+        .with_children(|children| {
+            children.header("Levels");
+            for index in 0..level_count {
+                // A level is unlocked once a checkpoint of the previous level
+                // was reached, or it was completed, e.g. for tutorial levels
+                // without checkpoints.
+                let unlocked =
+                    index == 0 || progress.reached_any(index - 1) || progress.completed(index - 1);
+                if !unlocked {
+                    children
+                        .button(format!("Level {} (locked)", index + 1))
+                        .insert((
+                            LevelButton(index),
+                            LockedLevel,
+                            BackgroundColor(BUTTON_LOCKED_BACKGROUND),
+                            InteractionPalette {
+                                none: BUTTON_LOCKED_BACKGROUND,
+                                hovered: BUTTON_LOCKED_BACKGROUND,
+                                pressed: BUTTON_LOCKED_BACKGROUND,
+                            },
+                        ));
+                    continue;
+                }
+                let text = match best_grade(index) {
+                    Some(grade) => format!("Level {} ({grade})", index + 1),
+                    None if progress.assisted(index) => format!("Level {} (assist)", index + 1),
+                    None => format!("Level {}", index + 1),
+                };
+                children
+                    .button(text)
+                    .insert(LevelButton(index))
+                    .observe(enter_level);
+            }
+            children.button("Back").observe(enter_title_screen);
+        });
+}
+
+fn enter_level(
+    trigger: Trigger<OnPress>,
+    buttons: Query<&LevelButton>,
+    mut selection: ResMut<LevelSelection>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let Ok(&LevelButton(index)) = buttons.get(trigger.entity()) else {
+        return;
+    };
+    *selection = LevelSelection::index(index);
+    next_screen.set(Screen::Gameplay);
+}
+
+fn enter_title_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Title);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use bevy::{
+        asset::io::{
+            memory::{Dir, MemoryAssetReader},
+            AssetSource,
+        },
+        state::app::StatesPlugin,
+    };
+    use bevy_ecs_ldtk::assets::LdtkAssetPlugin;
+
+    use super::*;
+    use crate::demo::level::Level;
+
+    /// The map with only its first `count` levels.
+    fn map(count: usize) -> String {
+        let json = std::fs::read_to_string("assets/map.ldtk").unwrap();
+        let mut map: serde_json::Value = serde_json::from_str(&json).unwrap();
+        map["levels"].as_array_mut().unwrap().truncate(count);
+        map.to_string()
+    }
+
+    /// A headless app with `json` loaded as the LDtk project, like the
+    /// loading screen does.
+    fn app_with_project(json: &str) -> App {
+        let dir = Dir::default();
+        dir.insert_asset_text(Path::new("map.ldtk"), json);
+        let mut app = App::new();
+        app.register_asset_source(
+            "memory",
+            AssetSource::build()
+                .with_reader(move || Box::new(MemoryAssetReader { root: dir.clone() })),
+        );
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            StatesPlugin,
+            LdtkAssetPlugin,
+        ));
+        app.init_asset::<Image>();
+        app.init_state::<Screen>();
+
+        let ldtk_project = app
+            .world()
+            .resource::<AssetServer>()
+            .load("memory://map.ldtk");
+        for _ in 0..1000 {
+            app.update();
+            let projects = app.world().resource::<Assets<LdtkProject>>();
+            if projects.contains(&ldtk_project) {
+                app.insert_resource(LevelAssets { ldtk_project });
+                return app;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("the project did not load");
+    }
+
+    #[test]
+    fn every_level_has_a_button_locked_by_progress() {
+        let mut app = app_with_project(&map(3));
+        app.add_plugins(plugin);
+        // Reaching a checkpoint of the first level unlocks the second.
+        let mut progress = Progress::default();
+        progress.reach(0, IVec2::ZERO, &Level::default());
+        app.insert_resource(progress);
+        app.world_mut()
+            .resource_mut::<NextState<Screen>>()
+            .set(Screen::LevelSelect);
+        app.update();
+
+        let mut buttons = app.world_mut().query::<(&LevelButton, Has<LockedLevel>)>();
+        let mut buttons: Vec<_> = buttons
+            .iter(app.world())
+            .map(|(&LevelButton(index), locked)| (index, locked))
+            .collect();
+        buttons.sort();
+        assert_eq!(buttons, [(0, false), (1, false), (2, true)]);
+    }
+}
@@ -2,8 +2,11 @@
 
 mod credits;
 pub mod gameplay;
+mod level_select;
 mod loading;
+mod settings;
 mod splash;
+mod stats;
 mod title;
 
 use bevy::prelude::*;
@@ -15,8 +18,11 @@ pub(super) fn plugin(app: &mut App) {
     app.add_plugins((
         credits::plugin,
         gameplay::plugin,
+        level_select::plugin,
         loading::plugin,
+        settings::plugin,
         splash::plugin,
+        stats::plugin,
         title::plugin,
     ));
 }
@@ -29,5 +35,96 @@ pub enum Screen {
     Loading,
     Title,
     Credits,
+    Settings,
+    Stats,
+    LevelSelect,
     Gameplay,
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::observer::ObserverState, state::app::StatesPlugin};
+    use bevy_ecs_ldtk::prelude::*;
+
+    use super::*;
+    use crate::{
+        demo::{animation::PlayerAssets, editor::EditorUI, level::LevelAssets},
+        settings::Settings,
+        theme::interaction::InteractionAssets,
+    };
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin));
+        app.init_state::<Screen>();
+        app.enable_state_scoped_entities::<Screen>();
+        app.add_plugins((
+            credits::plugin,
+            gameplay::plugin,
+            loading::plugin,
+            title::plugin,
+        ));
+        app.init_resource::<Settings>();
+        app
+    }
+
+    fn go_to(app: &mut App, screen: Screen) {
+        app.world_mut()
+            .resource_mut::<NextState<Screen>>()
+            .set(screen);
+        app.update();
+    }
+
+    /// The screens of all state scoped entities.
+    fn scopes(app: &mut App) -> Vec<Screen> {
+        let world = app.world_mut();
+        let mut query = world.query::<&StateScoped<Screen>>();
+        query.iter(world).map(|scoped| scoped.0.clone()).collect()
+    }
+
+    fn count<F: bevy::ecs::query::QueryFilter>(app: &mut App) -> usize {
+        let world = app.world_mut();
+        world.query_filtered::<(), F>().iter(world).count()
+    }
+
+    #[test]
+    fn screens_clean_up_after_themselves() {
+        let mut app = app();
+        go_to(&mut app, Screen::Loading);
+        assert_eq!(scopes(&mut app), [Screen::Loading]);
+
+        // Loading continues to the title screen once all assets are there.
+        let mut layouts = Assets::default();
+        let player_assets = PlayerAssets::new(default(), default(), &mut layouts);
+        app.insert_resource(player_assets);
+        app.insert_resource(InteractionAssets::new(default(), default()));
+        app.insert_resource(LevelAssets {
+            ldtk_project: default(),
+        });
+        app.update();
+        app.update();
+        assert_eq!(
+            app.world().resource::<State<Screen>>().get(),
+            &Screen::Title
+        );
+        assert_eq!(scopes(&mut app), [Screen::Title]);
+
+        go_to(&mut app, Screen::Gameplay);
+        assert_eq!(count::<With<Handle<LdtkProject>>>(&mut app), 1);
+        assert_eq!(count::<With<EditorUI>>(&mut app), 1);
+        assert!(scopes(&mut app)
+            .iter()
+            .all(|scope| scope == &Screen::Gameplay));
+
+        go_to(&mut app, Screen::Credits);
+        assert_eq!(count::<With<Handle<LdtkProject>>>(&mut app), 0);
+        assert_eq!(count::<With<EditorUI>>(&mut app), 0);
+        assert_eq!(scopes(&mut app), [Screen::Credits]);
+        // Nothing is left over besides the credits, apart from the observers of
+        // their buttons.
+        assert_eq!(
+            count::<(Without<Parent>, Without<ObserverState>)>(&mut app),
+            1
+        );
+    }
+}
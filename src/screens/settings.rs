@@ -0,0 +1,238 @@
+//! A settings screen that can be accessed from the title screen.
+
+use bevy::prelude::*;
+
+use crate::{
+    demo::dialogue::SeenTutorials,
+    persistence,
+    screens::Screen,
+    settings::Settings,
+    theme::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Settings), spawn_settings_screen);
+    app.add_systems(
+        Update,
+        update_setting_labels
+            .run_if(in_state(Screen::Settings).and_then(resource_changed::<Settings>)),
+    );
+}
+
+/// Marks a label that displays the current value of a setting.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingLabel {
+    Difficulty,
+    IdleCadence,
+    ReduceMotion,
+    PauseBetweenSteps,
+    OnSubmit,
+    RepeatKeys,
+    InvertZoom,
+    BalanceBrackets,
+    CarryOverScript,
+    MirrorLevels,
+    SkipSplash,
+    RecordRuns,
+}
+
+impl SettingLabel {
+    fn text(self, settings: &Settings) -> String {
+        match self {
+            SettingLabel::Difficulty => format!("Difficulty: {}", settings.difficulty.name()),
+            SettingLabel::IdleCadence => {
+                format!("Retry when stuck: {}", settings.idle_cadence.name())
+            }
+            SettingLabel::ReduceMotion => {
+                format!("Reduce motion: {}", on_off(settings.reduce_motion))
+            }
+            SettingLabel::PauseBetweenSteps => {
+                format!(
+                    "Pause after each manual step: {}",
+                    on_off(settings.pause_between_steps)
+                )
+            }
+            SettingLabel::OnSubmit => {
+                format!("After submitting: {}", settings.on_submit.name())
+            }
+            SettingLabel::RepeatKeys => {
+                format!("Repeat held keys: {}", on_off(settings.repeat_keys))
+            }
+            SettingLabel::InvertZoom => {
+                format!("Invert scroll zoom: {}", on_off(settings.invert_zoom))
+            }
+            SettingLabel::BalanceBrackets => {
+                format!(
+                    "Add missing brackets on submit: {}",
+                    on_off(settings.balance_brackets)
+                )
+            }
+            SettingLabel::CarryOverScript => {
+                format!(
+                    "Keep script for the next level: {}",
+                    on_off(settings.carry_over_script)
+                )
+            }
+            SettingLabel::MirrorLevels => {
+                format!("Mirrored levels: {}", on_off(settings.mirror_levels))
+            }
+            SettingLabel::SkipSplash => {
+                format!("Skip splash screen: {}", on_off(settings.skip_splash))
+            }
+            SettingLabel::RecordRuns => {
+                format!("Record runs locally: {}", on_off(settings.record_runs))
+            }
+        }
+    }
+}
+
+fn spawn_settings_screen(mut commands: Commands, settings: Res<Settings>) {
+    commands
+        .ui_root()
+        .insert(StateScoped(Screen::Settings))
+        .with_children(|children| {
+            children.header("Settings");
+
+            children
+                .label(SettingLabel::Difficulty.text(&settings))
+                .insert(SettingLabel::Difficulty);
+            children.button("Difficulty").observe(cycle_difficulty);
+
+            children
+                .label(SettingLabel::IdleCadence.text(&settings))
+                .insert(SettingLabel::IdleCadence);
+            children.button("Retry").observe(cycle_idle_cadence);
+
+            children
+                .label(SettingLabel::ReduceMotion.text(&settings))
+                .insert(SettingLabel::ReduceMotion);
+            children.button("Motion").observe(toggle_reduce_motion);
+
+            children
+                .label(SettingLabel::PauseBetweenSteps.text(&settings))
+                .insert(SettingLabel::PauseBetweenSteps);
+            children.button("Pause").observe(toggle_pause_between_steps);
+
+            children
+                .label(SettingLabel::OnSubmit.text(&settings))
+                .insert(SettingLabel::OnSubmit);
+            children.button("Submit").observe(cycle_on_submit);
+
+            children
+                .label(SettingLabel::RepeatKeys.text(&settings))
+                .insert(SettingLabel::RepeatKeys);
+            children.button("Keys").observe(toggle_repeat_keys);
+
+            children
+                .label(SettingLabel::InvertZoom.text(&settings))
+                .insert(SettingLabel::InvertZoom);
+            children.button("Zoom").observe(toggle_invert_zoom);
+
+            children
+                .label(SettingLabel::BalanceBrackets.text(&settings))
+                .insert(SettingLabel::BalanceBrackets);
+            children.button("Brackets").observe(toggle_balance_brackets);
+
+            children
+                .label(SettingLabel::CarryOverScript.text(&settings))
+                .insert(SettingLabel::CarryOverScript);
+            children.button("Script").observe(toggle_carry_over_script);
+
+            children
+                .label(SettingLabel::MirrorLevels.text(&settings))
+                .insert(SettingLabel::MirrorLevels);
+            children.button("Mirror").observe(toggle_mirror_levels);
+
+            children
+                .label(SettingLabel::SkipSplash.text(&settings))
+                .insert(SettingLabel::SkipSplash);
+            children.button("Splash").observe(toggle_skip_splash);
+
+            // Run summaries are written to a file, which is not possible on web.
+            #[cfg(not(target_family = "wasm"))]
+            {
+                children
+                    .label(SettingLabel::RecordRuns.text(&settings))
+                    .insert(SettingLabel::RecordRuns);
+                children.button("Record").observe(toggle_record_runs);
+            }
+
+            children.label("Show tutorials again");
+            children.button("Tutorials").observe(reset_tutorials);
+
+            children.button("Back").observe(enter_title_screen);
+        });
+}
+
+fn update_setting_labels(settings: Res<Settings>, mut labels: Query<(&SettingLabel, &mut Text)>) {
+    for (label, mut text) in &mut labels {
+        text.sections[0].value = label.text(&settings);
+    }
+}
+
+fn cycle_difficulty(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.difficulty = settings.difficulty.next();
+}
+
+fn cycle_idle_cadence(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.idle_cadence = settings.idle_cadence.next();
+}
+
+fn toggle_reduce_motion(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.reduce_motion = !settings.reduce_motion;
+}
+
+fn toggle_pause_between_steps(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.pause_between_steps = !settings.pause_between_steps;
+}
+
+fn cycle_on_submit(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.on_submit = settings.on_submit.next();
+}
+
+fn toggle_repeat_keys(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.repeat_keys = !settings.repeat_keys;
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn toggle_invert_zoom(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.invert_zoom = !settings.invert_zoom;
+    persistence::save(Settings::INVERT_ZOOM_KEY, &settings.invert_zoom);
+}
+
+fn toggle_record_runs(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.record_runs = !settings.record_runs;
+}
+
+fn toggle_balance_brackets(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.balance_brackets = !settings.balance_brackets;
+}
+
+fn toggle_carry_over_script(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.carry_over_script = !settings.carry_over_script;
+}
+
+fn toggle_mirror_levels(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.mirror_levels = !settings.mirror_levels;
+}
+
+fn toggle_skip_splash(_trigger: Trigger<OnPress>, mut settings: ResMut<Settings>) {
+    settings.skip_splash = !settings.skip_splash;
+    persistence::save(Settings::SKIP_SPLASH_KEY, &settings.skip_splash);
+}
+
+fn reset_tutorials(_trigger: Trigger<OnPress>, mut seen: ResMut<SeenTutorials>) {
+    seen.reset();
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+fn enter_title_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Title);
+}
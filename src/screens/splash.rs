@@ -1,17 +1,22 @@
 //! A splash screen that plays briefly at startup.
 
 use bevy::{
-    input::common_conditions::input_just_pressed,
     prelude::*,
     render::texture::{ImageLoaderSettings, ImageSampler},
 };
 
-use crate::{screens::Screen, theme::prelude::*, AppSet};
+use crate::{screens::Screen, settings::Settings, theme::prelude::*, AppSet};
 
 pub(super) fn plugin(app: &mut App) {
     // Spawn splash screen.
     app.insert_resource(ClearColor(SPLASH_BACKGROUND_COLOR));
-    app.add_systems(OnEnter(Screen::Splash), spawn_splash_screen);
+    app.add_systems(
+        OnEnter(Screen::Splash),
+        (
+            spawn_splash_screen,
+            continue_to_loading_screen.run_if(|settings: Res<Settings>| settings.skip_splash),
+        ),
+    );
 
     // Animate splash screen.
     app.add_systems(
@@ -36,14 +41,20 @@ pub(super) fn plugin(app: &mut App) {
             .run_if(in_state(Screen::Splash)),
     );
 
-    // Exit the splash screen early if the player hits escape.
+    // Exit the splash screen early if the player presses any key or button.
     app.add_systems(
         Update,
-        continue_to_loading_screen
-            .run_if(input_just_pressed(KeyCode::Escape).and_then(in_state(Screen::Splash))),
+        continue_to_loading_screen.run_if(in_state(Screen::Splash).and_then(any_input_pressed)),
     );
 }
 
+fn any_input_pressed(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+) -> bool {
+    keys.get_just_pressed().next().is_some() || mouse.get_just_pressed().next().is_some()
+}
+
 const SPLASH_BACKGROUND_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
 const SPLASH_DURATION_SECS: f32 = 1.8;
 const SPLASH_FADE_DURATION_SECS: f32 = 0.6;
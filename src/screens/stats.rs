@@ -0,0 +1,112 @@
+//! A statistics screen that can be accessed from the title screen.
+
+use bevy::{prelude::*, ui::Val::*};
+
+use crate::{
+    demo::stats::CommandStats,
+    screens::Screen,
+    theme::{palette::NODE_BACKGROUND, prelude::*},
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Stats), spawn_stats_screen);
+    app.add_systems(
+        Update,
+        show_command_histogram
+            .run_if(in_state(Screen::Stats).and_then(resource_changed::<CommandStats>)),
+    );
+}
+
+/// Width of the bar of the most used command.
+const MAX_BAR_WIDTH: f32 = 300.0;
+
+/// Holds one row per command, rebuilt whenever the statistics change.
+#[derive(Component)]
+struct CommandHistogram;
+
+fn spawn_stats_screen(mut commands: Commands, mut stats: ResMut<CommandStats>) {
+    commands
+        .ui_root()
+        .insert(StateScoped(Screen::Stats))
+        .with_children(|children| {
+            children.header("Most used commands");
+            children.spawn((
+                Name::new("Command Histogram"),
+                CommandHistogram,
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Px(5.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+
+            children.button("Reset").observe(reset_stats);
+            children.button("Back").observe(enter_title_screen);
+        });
+    // Fill the histogram even if the statistics did not change.
+    stats.set_changed();
+}
+
+fn show_command_histogram(
+    stats: Res<CommandStats>,
+    histogram: Query<Entity, With<CommandHistogram>>,
+    mut commands: Commands,
+) {
+    let Ok(histogram) = histogram.get_single() else {
+        return;
+    };
+    let counts = stats.most_used();
+    let max = counts.first().map_or(0, |&(_, count)| count);
+
+    commands
+        .entity(histogram)
+        .despawn_descendants()
+        .with_children(|children| {
+            if max == 0 {
+                children
+                    .label("Reach a checkpoint without assist mode to see which commands you use.");
+                return;
+            }
+            for (command, count) in counts {
+                children
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Px(10.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|children| {
+                        children.label(format!("{command:?}")).insert(Style {
+                            width: Px(200.0),
+                            ..default()
+                        });
+                        children.spawn(NodeBundle {
+                            style: Style {
+                                width: Px(MAX_BAR_WIDTH * count as f32 / max as f32),
+                                height: Px(20.0),
+                                ..default()
+                            },
+                            background_color: BackgroundColor(NODE_BACKGROUND),
+                            ..default()
+                        });
+                        children.label(count.to_string()).insert(Style {
+                            width: Px(80.0),
+                            ..default()
+                        });
+                    });
+            }
+        });
+}
+
+fn reset_stats(_trigger: Trigger<OnPress>, mut stats: ResMut<CommandStats>) {
+    stats.reset();
+}
+
+fn enter_title_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Title);
+}
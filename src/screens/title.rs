@@ -1,8 +1,13 @@
 //! The title screen that appears when the game starts.
 
 use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
 
-use crate::{screens::Screen, theme::prelude::*};
+use crate::{
+    demo::{level::LevelAssets, progress::Progress, tutorial::is_tutorial},
+    screens::Screen,
+    theme::prelude::*,
+};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(Screen::Title), spawn_title_screen);
@@ -13,7 +18,9 @@ fn spawn_title_screen(mut commands: Commands) {
         .ui_root()
         .insert(StateScoped(Screen::Title))
         .with_children(|children| {
-            children.button("Play").observe(enter_gameplay_screen);
+            children.button("Play").observe(start_playing);
+            children.button("Settings").observe(enter_settings_screen);
+            children.button("Stats").observe(enter_stats_screen);
             children.button("Credits").observe(enter_credits_screen);
 
             #[cfg(not(target_family = "wasm"))]
@@ -21,8 +28,32 @@ fn spawn_title_screen(mut commands: Commands) {
         });
 }
 
-fn enter_gameplay_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
-    next_screen.set(Screen::Gameplay);
+/// New players go straight into the tutorial, everyone else picks a level.
+fn start_playing(
+    _trigger: Trigger<OnPress>,
+    progress: Res<Progress>,
+    level_assets: Res<LevelAssets>,
+    ldtk_projects: Res<Assets<LdtkProject>>,
+    mut selection: ResMut<LevelSelection>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let has_tutorial = ldtk_projects
+        .get(&level_assets.ldtk_project)
+        .is_some_and(|project| is_tutorial(project, 0));
+    if has_tutorial && !progress.tutorial_complete() {
+        *selection = LevelSelection::index(0);
+        next_screen.set(Screen::Gameplay);
+    } else {
+        next_screen.set(Screen::LevelSelect);
+    }
+}
+
+fn enter_settings_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Settings);
+}
+
+fn enter_stats_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Stats);
 }
 
 fn enter_credits_screen(_trigger: Trigger<OnPress>, mut next_screen: ResMut<NextState<Screen>>) {
@@ -0,0 +1,212 @@
+//! Player-facing settings that can be changed from the settings screen.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{demo::action::ScriptCommand, persistence};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Settings>();
+    app.insert_resource(Settings {
+        skip_splash: persistence::load(Settings::SKIP_SPLASH_KEY).unwrap_or_default(),
+        invert_zoom: persistence::load(Settings::INVERT_ZOOM_KEY).unwrap_or_default(),
+        playback_speed: persistence::load(Settings::PLAYBACK_SPEED_KEY).unwrap_or_default(),
+        ..default()
+    });
+}
+
+#[derive(Resource, Reflect, Debug, Clone, PartialEq, Default)]
+#[reflect(Resource)]
+pub struct Settings {
+    pub difficulty: Difficulty,
+    /// How long autoplay waits before trying the next command when the
+    /// current one cannot be executed.
+    pub idle_cadence: IdleCadence,
+    /// Skip or tone down purely decorative animations.
+    pub reduce_motion: bool,
+    /// In manual mode, holding the step key does not keep stepping, each step
+    /// waits for its own press.
+    pub pause_between_steps: bool,
+    /// Holding an editor key repeats it, like in a text field. Otherwise each
+    /// press is handled once.
+    pub repeat_keys: bool,
+    /// Scrolling up zooms out instead of in. This is saved, like
+    /// `skip_splash`.
+    pub invert_zoom: bool,
+    /// Add missing brackets when submitting. Otherwise scripts with
+    /// unbalanced brackets are rejected with a warning.
+    pub balance_brackets: bool,
+    /// Keep the script in the editor when a new level is loaded, e.g. to use
+    /// it as a template. By default the editor starts empty.
+    pub carry_over_script: bool,
+    /// Play levels mirrored horizontally, for a new take on known levels.
+    pub mirror_levels: bool,
+    /// Append a summary of every run to a local file for playtesting.
+    /// Only available on native.
+    pub record_runs: bool,
+    /// Preferred order of the commands in the palette.
+    pub palette_order: Vec<ScriptCommand>,
+    /// How fast autoplay runs, on top of fast forwarding with F. Changed with
+    /// X while playing and saved, so that it sticks across sessions.
+    pub playback_speed: PlaybackSpeed,
+    /// How a script runs right after it is submitted.
+    pub on_submit: SubmitMode,
+    /// Go straight to loading on startup. This is meant for returning
+    /// players, so unlike most other settings it is saved.
+    pub skip_splash: bool,
+}
+
+impl Settings {
+    pub const INVERT_ZOOM_KEY: &'static str = "invert-zoom";
+    pub const PLAYBACK_SPEED_KEY: &'static str = "playback-speed";
+    pub const SKIP_SPLASH_KEY: &'static str = "skip-splash";
+}
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubmitMode {
+    /// Play the script on its own.
+    #[default]
+    Autoplay,
+    /// Wait in manual mode, where holding F runs the script.
+    Manual,
+    /// Wait in manual mode, where each press of F runs a single step.
+    Step,
+}
+
+impl SubmitMode {
+    pub fn next(self) -> Self {
+        match self {
+            SubmitMode::Autoplay => SubmitMode::Manual,
+            SubmitMode::Manual => SubmitMode::Step,
+            SubmitMode::Step => SubmitMode::Autoplay,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SubmitMode::Autoplay => "Autoplay",
+            SubmitMode::Manual => "Hold F",
+            SubmitMode::Step => "Step with F",
+        }
+    }
+}
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PlaybackSpeed {
+    #[default]
+    Normal,
+    Double,
+    Quadruple,
+}
+
+impl PlaybackSpeed {
+    /// Factor applied to the duration of every tick during autoplay.
+    pub fn duration_scale(self) -> f32 {
+        match self {
+            PlaybackSpeed::Normal => 1.0,
+            PlaybackSpeed::Double => 0.5,
+            PlaybackSpeed::Quadruple => 0.25,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            PlaybackSpeed::Normal => PlaybackSpeed::Double,
+            PlaybackSpeed::Double => PlaybackSpeed::Quadruple,
+            PlaybackSpeed::Quadruple => PlaybackSpeed::Normal,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PlaybackSpeed::Normal => "1x",
+            PlaybackSpeed::Double => "2x",
+            PlaybackSpeed::Quadruple => "4x",
+        }
+    }
+}
+
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdleCadence {
+    Fast,
+    #[default]
+    Normal,
+    Slow,
+}
+
+impl IdleCadence {
+    pub fn seconds(self) -> f32 {
+        match self {
+            IdleCadence::Fast => 0.05,
+            IdleCadence::Normal => 0.25,
+            IdleCadence::Slow => 0.5,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            IdleCadence::Fast => IdleCadence::Normal,
+            IdleCadence::Normal => IdleCadence::Slow,
+            IdleCadence::Slow => IdleCadence::Fast,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IdleCadence::Fast => "Fast",
+            IdleCadence::Normal => "Normal",
+            IdleCadence::Slow => "Slow",
+        }
+    }
+}
+
+/// Scales how fast the simulation runs without changing any puzzle logic.
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Difficulty {
+    #[default]
+    Normal,
+    /// Hazards (and everything else in lockstep with them) move at half speed.
+    Assist,
+}
+
+impl Difficulty {
+    /// Factor applied to the speed of the animation tick timer.
+    pub fn time_scale(self) -> f32 {
+        match self {
+            Difficulty::Normal => 1.0,
+            Difficulty::Assist => 0.5,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Normal => Difficulty::Assist,
+            Difficulty::Assist => Difficulty::Normal,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Normal => "Normal",
+            Difficulty::Assist => "Assist (hazards 50% slower)",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn playback_speed_cycles_back_to_normal() {
+        let speeds = [
+            PlaybackSpeed::Normal,
+            PlaybackSpeed::Double,
+            PlaybackSpeed::Quadruple,
+        ];
+        for (speed, next) in speeds.iter().zip(speeds.iter().cycle().skip(1)) {
+            assert_eq!(speed.next(), *next);
+            assert!(next.duration_scale() != speed.duration_scale());
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use bevy_simple_text_input::{TextInputInactive, TextInputSubmitEvent};
 
 use crate::{asset_tracking::LoadResource, audio::SoundEffect};
 
@@ -14,8 +15,14 @@ pub(super) fn plugin(app: &mut App) {
         )
             .run_if(resource_exists::<InteractionAssets>),
     );
+    app.add_systems(Update, (focus_text_input, unfocus_text_input));
 }
 
+/// Interactions with this entity do not play sound effects, e.g. for items that
+/// are hovered often or respawned under the mouse.
+#[derive(Component, Debug)]
+pub struct SilentInteraction;
+
 /// Palette for widget interactions. Add this to an entity that supports
 /// [`Interaction`]s, such as a button, to change its [`BackgroundColor`] based
 /// on the current interaction state.
@@ -71,20 +78,24 @@ pub struct InteractionAssets {
 impl InteractionAssets {
     pub const PATH_BUTTON_HOVER: &'static str = "audio/sound_effects/button_hover.ogg";
     pub const PATH_BUTTON_PRESS: &'static str = "audio/sound_effects/button_press.ogg";
+
+    pub fn new(hover: Handle<AudioSource>, press: Handle<AudioSource>) -> Self {
+        Self { hover, press }
+    }
 }
 
 impl FromWorld for InteractionAssets {
     fn from_world(world: &mut World) -> Self {
         let assets = world.resource::<AssetServer>();
-        Self {
-            hover: assets.load(Self::PATH_BUTTON_HOVER),
-            press: assets.load(Self::PATH_BUTTON_PRESS),
-        }
+        Self::new(
+            assets.load(Self::PATH_BUTTON_HOVER),
+            assets.load(Self::PATH_BUTTON_PRESS),
+        )
     }
 }
 
 fn trigger_interaction_sound_effect(
-    interaction_query: Query<&Interaction, Changed<Interaction>>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, Without<SilentInteraction>)>,
     interaction_assets: Res<InteractionAssets>,
     mut commands: Commands,
 ) {
@@ -103,3 +114,32 @@ fn trigger_interaction_sound_effect(
         ));
     }
 }
+
+/// Run condition that is true while the player is typing into a text input.
+/// Use it to keep keyboard shortcuts from firing at the same time.
+pub fn text_input_focused(text_inputs: Query<&TextInputInactive>) -> bool {
+    text_inputs.iter().any(|inactive| !inactive.0)
+}
+
+fn focus_text_input(
+    mut text_input_query: Query<(&Interaction, &mut TextInputInactive), Changed<Interaction>>,
+) {
+    for (interaction, mut inactive) in &mut text_input_query {
+        if matches!(interaction, Interaction::Pressed) {
+            inactive.0 = false;
+        }
+    }
+}
+
+fn unfocus_text_input(
+    input: Res<ButtonInput<KeyCode>>,
+    mut submitted: EventReader<TextInputSubmitEvent>,
+    mut text_input_query: Query<&mut TextInputInactive>,
+) {
+    if submitted.read().count() == 0 && !input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    for mut inactive in &mut text_input_query {
+        inactive.0 = true;
+    }
+}
@@ -2,6 +2,8 @@ use bevy::prelude::*;
 
 pub const BUTTON_HOVERED_BACKGROUND: Color = Color::srgb(0.186, 0.328, 0.573);
 pub const BUTTON_PRESSED_BACKGROUND: Color = Color::srgb(0.286, 0.478, 0.773);
+/// Buttons that cannot be pressed yet.
+pub const BUTTON_LOCKED_BACKGROUND: Color = Color::srgb(0.3, 0.3, 0.3);
 
 pub const BUTTON_TEXT: Color = Color::srgb(0.925, 0.925, 0.925);
 pub const LABEL_TEXT: Color = Color::srgb(0.867, 0.827, 0.412);
@@ -10,6 +10,9 @@ pub trait Widgets {
     /// Spawn a simple button with text.
     fn button(&mut self, text: impl Into<String>) -> EntityCommands;
 
+    /// Spawn a smaller button with text, for use next to other UI.
+    fn small_button(&mut self, text: impl Into<String>) -> EntityCommands;
+
     /// Spawn a simple header label. Bigger than [`Widgets::label`].
     fn header(&mut self, text: impl Into<String>) -> EntityCommands;
 
@@ -55,6 +58,40 @@ impl<T: Spawn> Widgets for T {
         entity
     }
 
+    fn small_button(&mut self, text: impl Into<String>) -> EntityCommands {
+        let mut entity = self.spawn((
+            Name::new("Small Button"),
+            ButtonBundle {
+                style: Style {
+                    width: Px(100.0),
+                    height: Px(40.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BackgroundColor(NODE_BACKGROUND),
+                ..default()
+            },
+            InteractionPalette {
+                none: NODE_BACKGROUND,
+                hovered: BUTTON_HOVERED_BACKGROUND,
+                pressed: BUTTON_PRESSED_BACKGROUND,
+            },
+        ));
+        entity.with_children(|children| {
+            children.spawn((
+                Name::new("Button Text"),
+                TextBundle::from_section(text, TextStyle {
+                    font_size: 24.0,
+                    color: BUTTON_TEXT,
+                    ..default()
+                }),
+            ));
+        });
+
+        entity
+    }
+
     fn header(&mut self, text: impl Into<String>) -> EntityCommands {
         let mut entity = self.spawn((Name::new("Header"), NodeBundle {
             style: Style {
@@ -120,7 +157,9 @@ impl<T: Spawn> Widgets for T {
                 .with_settings(TextInputSettings {
                     retain_on_submit: true,
                     ..default()
-                }),
+                })
+                // Only start typing once the input is clicked.
+                .with_inactive(true),
         ));
 
         entity
@@ -0,0 +1,17 @@
+//! Runs short scripts through the whole player system stack, without a window.
+
+use bevy::prelude::*;
+use gmtk_2024::testing::{floor, floor_with, run_script, ScriptCommand};
+
+#[test]
+fn walking_moves_one_cell_per_tick() {
+    let end = run_script(&mut World::new(), floor(), vec![ScriptCommand::Walk], 3);
+    assert_eq!(end, IVec2::new(3, 1));
+}
+
+#[test]
+fn walking_stops_at_a_wall() {
+    let level = floor_with([IVec2::new(3, 1)]);
+    let end = run_script(&mut World::new(), level, vec![ScriptCommand::Walk], 10);
+    assert_eq!(end, IVec2::new(2, 1));
+}